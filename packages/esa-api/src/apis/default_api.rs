@@ -14,6 +14,14 @@ use reqwest;
 use crate::apis::ResponseContent;
 use super::{Error, configuration};
 
+/// struct for passing parameters to the method `v1_teams_team_name_emojis_get`
+#[derive(Clone, Debug)]
+pub struct V1TeamsTeamNameEmojisGetParams {
+    pub team_name: String,
+    /// Use \"all\" to include the default emojis as well.
+    pub include: Option<String>
+}
+
 /// struct for passing parameters to the method `v1_teams_team_name_posts_get`
 #[derive(Clone, Debug)]
 pub struct V1TeamsTeamNamePostsGetParams {
@@ -24,6 +32,8 @@ pub struct V1TeamsTeamNamePostsGetParams {
     pub include: Option<String>,
     /// 取得するページ
     pub page: Option<i32>,
+    /// Posts per page (up to 100).
+    pub per_page: Option<i32>,
     /// Sort key.
     pub sort: Option<String>,
     /// Sort order.
@@ -39,6 +49,14 @@ pub struct V1TeamsTeamNamePostsPostNumberGetParams {
     pub include: Option<String>
 }
 
+/// struct for passing parameters to the method `v1_teams_team_name_posts_post_number_patch`
+#[derive(Clone, Debug)]
+pub struct V1TeamsTeamNamePostsPostNumberPatchParams {
+    pub team_name: String,
+    pub post_number: i32,
+    pub update_post_request: crate::models::UpdatePostRequest
+}
+
 /// struct for passing parameters to the method `v1_teams_team_name_posts_post_number_star_delete`
 #[derive(Clone, Debug)]
 pub struct V1TeamsTeamNamePostsPostNumberStarDeleteParams {
@@ -76,6 +94,13 @@ pub struct V1UserGetParams {
 }
 
 
+/// struct for typed errors of method `v1_teams_team_name_emojis_get`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum V1TeamsTeamNameEmojisGetError {
+    UnknownValue(serde_json::Value),
+}
+
 /// struct for typed errors of method `v1_teams_team_name_posts_get`
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -90,6 +115,13 @@ pub enum V1TeamsTeamNamePostsPostNumberGetError {
     UnknownValue(serde_json::Value),
 }
 
+/// struct for typed errors of method `v1_teams_team_name_posts_post_number_patch`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum V1TeamsTeamNamePostsPostNumberPatchError {
+    UnknownValue(serde_json::Value),
+}
+
 /// struct for typed errors of method `v1_teams_team_name_posts_post_number_star_delete`
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -126,6 +158,52 @@ pub enum V1UserGetError {
 }
 
 
+/// Returns the custom emojis of the team.
+pub async fn v1_teams_team_name_emojis_get(configuration: &configuration::Configuration, params: V1TeamsTeamNameEmojisGetParams) -> Result<crate::models::EmojiListResponse, Error<V1TeamsTeamNameEmojisGetError>> {
+    // unbox the parameters
+    let team_name = params.team_name;
+    let include = params.include;
+
+
+    let local_var_client = &configuration.client;
+
+    let local_var_uri_str = format!("{}/v1/teams/{team_name}/emojis", configuration.base_path, team_name=crate::apis::urlencode(team_name));
+    let mut local_var_req_builder = local_var_client.get(local_var_uri_str.as_str());
+
+    if let Some(ref local_var_str) = include {
+        local_var_req_builder = local_var_req_builder.query(&[("include", &local_var_str.to_string())]);
+    }
+    if let Some(ref local_var_apikey) = configuration.api_key {
+        let local_var_key = local_var_apikey.key.clone();
+        let local_var_value = match local_var_apikey.prefix {
+            Some(ref local_var_prefix) => format!("{} {}", local_var_prefix, local_var_key),
+            None => local_var_key,
+        };
+        local_var_req_builder = local_var_req_builder.query(&[("access_token", local_var_value)]);
+    }
+    if let Some(ref local_var_user_agent) = configuration.user_agent {
+        local_var_req_builder = local_var_req_builder.header(reqwest::header::USER_AGENT, local_var_user_agent.clone());
+    }
+    if let Some(ref local_var_token) = configuration.bearer_access_token {
+        local_var_req_builder = local_var_req_builder.bearer_auth(local_var_token.to_owned());
+    };
+
+    let local_var_req = local_var_req_builder.build()?;
+    let local_var_resp = local_var_client.execute(local_var_req).await?;
+
+    let local_var_status = local_var_resp.status();
+    let local_var_headers = local_var_resp.headers().clone();
+    let local_var_content = local_var_resp.text().await?;
+
+    if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
+        serde_json::from_str(&local_var_content).map_err(Error::from)
+    } else {
+        let local_var_entity: Option<V1TeamsTeamNameEmojisGetError> = serde_json::from_str(&local_var_content).ok();
+        let local_var_error = ResponseContent { status: local_var_status, headers: local_var_headers, content: local_var_content, entity: local_var_entity };
+        Err(Error::ResponseError(local_var_error))
+    }
+}
+
 /// Returns a list of posts.
 pub async fn v1_teams_team_name_posts_get(configuration: &configuration::Configuration, params: V1TeamsTeamNamePostsGetParams) -> Result<crate::models::PostListResponse, Error<V1TeamsTeamNamePostsGetError>> {
     // unbox the parameters
@@ -133,6 +211,7 @@ pub async fn v1_teams_team_name_posts_get(configuration: &configuration::Configu
     let q = params.q;
     let include = params.include;
     let page = params.page;
+    let per_page = params.per_page;
     let sort = params.sort;
     let order = params.order;
 
@@ -151,6 +230,9 @@ pub async fn v1_teams_team_name_posts_get(configuration: &configuration::Configu
     if let Some(ref local_var_str) = page {
         local_var_req_builder = local_var_req_builder.query(&[("page", &local_var_str.to_string())]);
     }
+    if let Some(ref local_var_str) = per_page {
+        local_var_req_builder = local_var_req_builder.query(&[("per_page", &local_var_str.to_string())]);
+    }
     if let Some(ref local_var_str) = sort {
         local_var_req_builder = local_var_req_builder.query(&[("sort", &local_var_str.to_string())]);
     }
@@ -176,13 +258,14 @@ pub async fn v1_teams_team_name_posts_get(configuration: &configuration::Configu
     let local_var_resp = local_var_client.execute(local_var_req).await?;
 
     let local_var_status = local_var_resp.status();
+    let local_var_headers = local_var_resp.headers().clone();
     let local_var_content = local_var_resp.text().await?;
 
     if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
         serde_json::from_str(&local_var_content).map_err(Error::from)
     } else {
         let local_var_entity: Option<V1TeamsTeamNamePostsGetError> = serde_json::from_str(&local_var_content).ok();
-        let local_var_error = ResponseContent { status: local_var_status, content: local_var_content, entity: local_var_entity };
+        let local_var_error = ResponseContent { status: local_var_status, headers: local_var_headers, content: local_var_content, entity: local_var_entity };
         Err(Error::ResponseError(local_var_error))
     }
 }
@@ -222,13 +305,59 @@ pub async fn v1_teams_team_name_posts_post_number_get(configuration: &configurat
     let local_var_resp = local_var_client.execute(local_var_req).await?;
 
     let local_var_status = local_var_resp.status();
+    let local_var_headers = local_var_resp.headers().clone();
     let local_var_content = local_var_resp.text().await?;
 
     if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
         serde_json::from_str(&local_var_content).map_err(Error::from)
     } else {
         let local_var_entity: Option<V1TeamsTeamNamePostsPostNumberGetError> = serde_json::from_str(&local_var_content).ok();
-        let local_var_error = ResponseContent { status: local_var_status, content: local_var_content, entity: local_var_entity };
+        let local_var_error = ResponseContent { status: local_var_status, headers: local_var_headers, content: local_var_content, entity: local_var_entity };
+        Err(Error::ResponseError(local_var_error))
+    }
+}
+
+/// Updates the post. Pass original_revision to have esa detect edits made since it was loaded.
+pub async fn v1_teams_team_name_posts_post_number_patch(configuration: &configuration::Configuration, params: V1TeamsTeamNamePostsPostNumberPatchParams) -> Result<crate::models::Post, Error<V1TeamsTeamNamePostsPostNumberPatchError>> {
+    // unbox the parameters
+    let team_name = params.team_name;
+    let post_number = params.post_number;
+    let update_post_request = params.update_post_request;
+
+
+    let local_var_client = &configuration.client;
+
+    let local_var_uri_str = format!("{}/v1/teams/{team_name}/posts/{post_number}", configuration.base_path, team_name=crate::apis::urlencode(team_name), post_number=post_number);
+    let mut local_var_req_builder = local_var_client.patch(local_var_uri_str.as_str());
+
+    if let Some(ref local_var_apikey) = configuration.api_key {
+        let local_var_key = local_var_apikey.key.clone();
+        let local_var_value = match local_var_apikey.prefix {
+            Some(ref local_var_prefix) => format!("{} {}", local_var_prefix, local_var_key),
+            None => local_var_key,
+        };
+        local_var_req_builder = local_var_req_builder.query(&[("access_token", local_var_value)]);
+    }
+    if let Some(ref local_var_user_agent) = configuration.user_agent {
+        local_var_req_builder = local_var_req_builder.header(reqwest::header::USER_AGENT, local_var_user_agent.clone());
+    }
+    if let Some(ref local_var_token) = configuration.bearer_access_token {
+        local_var_req_builder = local_var_req_builder.bearer_auth(local_var_token.to_owned());
+    };
+    local_var_req_builder = local_var_req_builder.json(&update_post_request);
+
+    let local_var_req = local_var_req_builder.build()?;
+    let local_var_resp = local_var_client.execute(local_var_req).await?;
+
+    let local_var_status = local_var_resp.status();
+    let local_var_headers = local_var_resp.headers().clone();
+    let local_var_content = local_var_resp.text().await?;
+
+    if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
+        serde_json::from_str(&local_var_content).map_err(Error::from)
+    } else {
+        let local_var_entity: Option<V1TeamsTeamNamePostsPostNumberPatchError> = serde_json::from_str(&local_var_content).ok();
+        let local_var_error = ResponseContent { status: local_var_status, headers: local_var_headers, content: local_var_content, entity: local_var_entity };
         Err(Error::ResponseError(local_var_error))
     }
 }
@@ -264,13 +393,14 @@ pub async fn v1_teams_team_name_posts_post_number_star_delete(configuration: &co
     let local_var_resp = local_var_client.execute(local_var_req).await?;
 
     let local_var_status = local_var_resp.status();
+    let local_var_headers = local_var_resp.headers().clone();
     let local_var_content = local_var_resp.text().await?;
 
     if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
         Ok(())
     } else {
         let local_var_entity: Option<V1TeamsTeamNamePostsPostNumberStarDeleteError> = serde_json::from_str(&local_var_content).ok();
-        let local_var_error = ResponseContent { status: local_var_status, content: local_var_content, entity: local_var_entity };
+        let local_var_error = ResponseContent { status: local_var_status, headers: local_var_headers, content: local_var_content, entity: local_var_entity };
         Err(Error::ResponseError(local_var_error))
     }
 }
@@ -308,13 +438,14 @@ pub async fn v1_teams_team_name_posts_post_number_star_post(configuration: &conf
     let local_var_resp = local_var_client.execute(local_var_req).await?;
 
     let local_var_status = local_var_resp.status();
+    let local_var_headers = local_var_resp.headers().clone();
     let local_var_content = local_var_resp.text().await?;
 
     if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
         Ok(())
     } else {
         let local_var_entity: Option<V1TeamsTeamNamePostsPostNumberStarPostError> = serde_json::from_str(&local_var_content).ok();
-        let local_var_error = ResponseContent { status: local_var_status, content: local_var_content, entity: local_var_entity };
+        let local_var_error = ResponseContent { status: local_var_status, headers: local_var_headers, content: local_var_content, entity: local_var_entity };
         Err(Error::ResponseError(local_var_error))
     }
 }
@@ -350,13 +481,14 @@ pub async fn v1_teams_team_name_posts_post_number_watch_delete(configuration: &c
     let local_var_resp = local_var_client.execute(local_var_req).await?;
 
     let local_var_status = local_var_resp.status();
+    let local_var_headers = local_var_resp.headers().clone();
     let local_var_content = local_var_resp.text().await?;
 
     if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
         Ok(())
     } else {
         let local_var_entity: Option<V1TeamsTeamNamePostsPostNumberWatchDeleteError> = serde_json::from_str(&local_var_content).ok();
-        let local_var_error = ResponseContent { status: local_var_status, content: local_var_content, entity: local_var_entity };
+        let local_var_error = ResponseContent { status: local_var_status, headers: local_var_headers, content: local_var_content, entity: local_var_entity };
         Err(Error::ResponseError(local_var_error))
     }
 }
@@ -392,13 +524,14 @@ pub async fn v1_teams_team_name_posts_post_number_watch_post(configuration: &con
     let local_var_resp = local_var_client.execute(local_var_req).await?;
 
     let local_var_status = local_var_resp.status();
+    let local_var_headers = local_var_resp.headers().clone();
     let local_var_content = local_var_resp.text().await?;
 
     if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
         Ok(())
     } else {
         let local_var_entity: Option<V1TeamsTeamNamePostsPostNumberWatchPostError> = serde_json::from_str(&local_var_content).ok();
-        let local_var_error = ResponseContent { status: local_var_status, content: local_var_content, entity: local_var_entity };
+        let local_var_error = ResponseContent { status: local_var_status, headers: local_var_headers, content: local_var_content, entity: local_var_entity };
         Err(Error::ResponseError(local_var_error))
     }
 }
@@ -436,13 +569,14 @@ pub async fn v1_user_get(configuration: &configuration::Configuration, params: V
     let local_var_resp = local_var_client.execute(local_var_req).await?;
 
     let local_var_status = local_var_resp.status();
+    let local_var_headers = local_var_resp.headers().clone();
     let local_var_content = local_var_resp.text().await?;
 
     if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
         serde_json::from_str(&local_var_content).map_err(Error::from)
     } else {
         let local_var_entity: Option<V1UserGetError> = serde_json::from_str(&local_var_content).ok();
-        let local_var_error = ResponseContent { status: local_var_status, content: local_var_content, entity: local_var_entity };
+        let local_var_error = ResponseContent { status: local_var_status, headers: local_var_headers, content: local_var_content, entity: local_var_entity };
         Err(Error::ResponseError(local_var_error))
     }
 }
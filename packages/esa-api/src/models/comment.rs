@@ -0,0 +1,40 @@
+/*
+ * esa API
+ *
+ * API reference for selected endpoints from esa.io.
+ *
+ * The version of the OpenAPI document: v1
+ * 
+ * Generated by: https://openapi-generator.tech
+ */
+
+
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    #[serde(rename = "id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+    #[serde(rename = "body_md", skip_serializing_if = "Option::is_none")]
+    pub body_md: Option<String>,
+    #[serde(rename = "created_at", skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(rename = "updated_at", skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    #[serde(rename = "created_by", skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<Box<crate::models::UserSummary>>,
+}
+
+impl Comment {
+    pub fn new() -> Comment {
+        Comment {
+            id: None,
+            body_md: None,
+            created_at: None,
+            updated_at: None,
+            created_by: None,
+        }
+    }
+}
+
+
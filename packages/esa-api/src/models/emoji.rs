@@ -0,0 +1,37 @@
+/*
+ * esa API
+ *
+ * API reference for selected endpoints from esa.io.
+ *
+ * The version of the OpenAPI document: v1
+ * 
+ * Generated by: https://openapi-generator.tech
+ */
+
+
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Emoji {
+    #[serde(rename = "code", skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(rename = "aliases", skip_serializing_if = "Option::is_none")]
+    pub aliases: Option<Vec<String>>,
+    #[serde(rename = "category", skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(rename = "url", skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl Emoji {
+    pub fn new() -> Emoji {
+        Emoji {
+            code: None,
+            aliases: None,
+            category: None,
+            url: None,
+        }
+    }
+}
+
+
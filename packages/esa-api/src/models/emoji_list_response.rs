@@ -0,0 +1,28 @@
+/*
+ * esa API
+ *
+ * API reference for selected endpoints from esa.io.
+ *
+ * The version of the OpenAPI document: v1
+ * 
+ * Generated by: https://openapi-generator.tech
+ */
+
+
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EmojiListResponse {
+    #[serde(rename = "emojis", skip_serializing_if = "Option::is_none")]
+    pub emojis: Option<Vec<crate::models::Emoji>>,
+}
+
+impl EmojiListResponse {
+    pub fn new() -> EmojiListResponse {
+        EmojiListResponse {
+            emojis: None,
+        }
+    }
+}
+
+
@@ -1,11 +1,23 @@
+pub mod comment;
+pub use self::comment::Comment;
+pub mod emoji;
+pub use self::emoji::Emoji;
+pub mod emoji_list_response;
+pub use self::emoji_list_response::EmojiListResponse;
 pub mod inline_object;
 pub use self::inline_object::InlineObject;
+pub mod original_revision;
+pub use self::original_revision::OriginalRevision;
 pub mod post;
 pub use self::post::Post;
 pub mod post_list_response;
 pub use self::post_list_response::PostListResponse;
 pub mod team;
 pub use self::team::Team;
+pub mod update_post_request;
+pub use self::update_post_request::UpdatePostRequest;
+pub mod update_post_request_post;
+pub use self::update_post_request_post::UpdatePostRequestPost;
 pub mod user;
 pub use self::user::User;
 pub mod user_summary;
@@ -0,0 +1,41 @@
+/*
+ * esa API
+ *
+ * API reference for selected endpoints from esa.io.
+ *
+ * The version of the OpenAPI document: v1
+ * 
+ * Generated by: https://openapi-generator.tech
+ */
+
+
+
+
+/// OriginalRevision : The revision the update is based on, used by esa to detect conflicting edits.
+
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OriginalRevision {
+    #[serde(rename = "body_md", skip_serializing_if = "Option::is_none")]
+    pub body_md: Option<String>,
+    /// Revision number of the post when it was loaded.
+    #[serde(rename = "number", skip_serializing_if = "Option::is_none")]
+    pub number: Option<i32>,
+    /// Screen name of the user who made that revision.
+    #[serde(rename = "user", skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+impl OriginalRevision {
+    /// The revision the update is based on, used by esa to detect conflicting edits.
+    pub fn new() -> OriginalRevision {
+        OriginalRevision {
+            body_md: None,
+            number: None,
+            user: None,
+        }
+    }
+}
+
+
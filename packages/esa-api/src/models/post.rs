@@ -60,6 +60,12 @@ pub struct Post {
     pub star: Option<bool>,
     #[serde(rename = "watch", skip_serializing_if = "Option::is_none")]
     pub watch: Option<bool>,
+    /// True when an update conflicted with a newer revision and was merged with conflict markers.
+    #[serde(rename = "overlapped", skip_serializing_if = "Option::is_none")]
+    pub overlapped: Option<bool>,
+    /// Present when requested with `include=comments`.
+    #[serde(rename = "comments", skip_serializing_if = "Option::is_none")]
+    pub comments: Option<Vec<crate::models::Comment>>,
 }
 
 impl Post {
@@ -88,6 +94,8 @@ impl Post {
             watchers_count: None,
             star: None,
             watch: None,
+            overlapped: None,
+            comments: None,
         }
     }
 }
@@ -0,0 +1,28 @@
+/*
+ * esa API
+ *
+ * API reference for selected endpoints from esa.io.
+ *
+ * The version of the OpenAPI document: v1
+ * 
+ * Generated by: https://openapi-generator.tech
+ */
+
+
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UpdatePostRequest {
+    #[serde(rename = "post")]
+    pub post: Box<crate::models::UpdatePostRequestPost>,
+}
+
+impl UpdatePostRequest {
+    pub fn new(post: crate::models::UpdatePostRequestPost) -> UpdatePostRequest {
+        UpdatePostRequest {
+            post: Box::new(post),
+        }
+    }
+}
+
+
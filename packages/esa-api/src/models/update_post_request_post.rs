@@ -0,0 +1,35 @@
+/*
+ * esa API
+ *
+ * API reference for selected endpoints from esa.io.
+ *
+ * The version of the OpenAPI document: v1
+ * 
+ * Generated by: https://openapi-generator.tech
+ */
+
+
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UpdatePostRequestPost {
+    #[serde(rename = "body_md", skip_serializing_if = "Option::is_none")]
+    pub body_md: Option<String>,
+    /// Change message shown in the revision history.
+    #[serde(rename = "message", skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(rename = "original_revision", skip_serializing_if = "Option::is_none")]
+    pub original_revision: Option<Box<crate::models::OriginalRevision>>,
+}
+
+impl UpdatePostRequestPost {
+    pub fn new() -> UpdatePostRequestPost {
+        UpdatePostRequestPost {
+            body_md: None,
+            message: None,
+            original_revision: None,
+        }
+    }
+}
+
+
@@ -1,31 +1,161 @@
-use crate::domains::{Theme, WorkspaceConfig};
-use crate::http_gateways::EsaClient;
+use crate::browser;
+use crate::clipboard::{self, CopyMethod};
+use crate::domains::{
+    ClipboardConfig, Config, LayoutConfig, Pattern, PostNumber, RecentlyViewed, SearchHit, State,
+    Theme, TimeDisplay, WorkspaceConfig,
+};
+use crate::export::HtmlExport;
+use crate::http_gateways::{CachedClient, EsaClient, EsaClientHttpGateway, FetchedPage};
+use crate::local_search::LocalSearch;
+use crate::post_cache::PostCache;
+use crate::state_file::StateFile;
 use crate::widgets::{self};
-use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::event::{
+    Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+    MouseEventKind,
+};
 use ratatui::{
     DefaultTerminal, Frame,
-    layout::{Constraint, Layout},
+    layout::{Constraint, Layout, Rect},
 };
 use std::io;
 use futures_util::StreamExt;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::{
+    Mutex,
+    mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+};
 use tokio::time::interval;
-use std::process::Command;
+
+/// The smallest share the post list can be resized to, in percent.
+const MIN_LIST_PERCENT: u16 = 20;
+const MAX_LIST_PERCENT: u16 = 80;
+/// How much `[` and `]` resize the panes, in percent.
+const RESIZE_STEP: u16 = 5;
+/// Results asked of esa's search from the search popup.
+const SEARCH_PER_PAGE: u32 = 50;
 
 pub struct App {
     exit: bool,
     post_list: widgets::PostList,
     post_content: widgets::PostContent,
+    recent_posts: widgets::RecentPosts,
+    search: widgets::Search,
+    /// Shared with the blocking task a local search runs on.
+    local_search: Arc<Mutex<LocalSearch>>,
+    html_export: HtmlExport,
+    status_bar: widgets::StatusBar,
+    clipboard: ClipboardConfig,
+    api: Arc<dyn EsaClientHttpGateway>,
+    /// Pages of the post list fetched in the background, or refetched after the cache
+    /// answered.
+    pages: UnboundedReceiver<FetchedPage>,
+    /// Local search results, as the query they are for and the hits.
+    search_results: UnboundedReceiver<(String, Vec<SearchHit>)>,
+    search_sender: UnboundedSender<(String, Vec<SearchHit>)>,
+    /// Progress of exports running in the background, for the status bar.
+    export_messages: UnboundedReceiver<widgets::StatusMessage>,
+    export_sender: UnboundedSender<widgets::StatusMessage>,
+    team_name: String,
+    state: State,
+    state_file: StateFile,
+    layout: LayoutConfig,
+    /// Share of the post list in percent, changed with `[` / `]` or by dragging the divider.
+    list_percent: u16,
+    /// Whether the post content takes the whole screen.
+    zoomed: bool,
+    /// The area the two panes were last drawn in, for mouse events.
+    main_area: Rect,
+    /// Whether the panes were last drawn one above the other.
+    stacked: bool,
+    /// The column, or the row when stacked, where the post content starts as of the last
+    /// draw; its border is the divider.
+    divider: u16,
+    dragging_divider: bool,
 }
 
 impl App {
-    pub fn new(conf: &WorkspaceConfig, theme: Theme) -> Self {
-        let api = Box::new(EsaClient::new(&conf.team_name(), &conf.token()));
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        conf: &WorkspaceConfig,
+        config: &Config,
+        images: Option<widgets::InlineImages>,
+        theme: Theme,
+        state_file: StateFile,
+        post_cache: PostCache,
+        html_export: HtmlExport,
+        offline: bool,
+    ) -> Self {
+        let client = EsaClient::new(&conf.team_name(), &conf.token());
+        let (page_sender, pages) = unbounded_channel();
+        let (search_sender, search_results) = unbounded_channel();
+        let (export_sender, export_messages) = unbounded_channel();
+        // offline reading needs the cache even when it is turned off
+        let cached = (config.cache.enabled || offline).then(|| {
+            CachedClient::new(
+                client.clone(),
+                post_cache.clone(),
+                offline,
+                page_sender.clone(),
+            )
+        });
+        // the list and the content pane each hold a client, sharing what the cache knows
+        let gateway = || -> Arc<dyn EsaClientHttpGateway> {
+            match &cached {
+                Some(cached) => Arc::new(cached.clone()),
+                None => Arc::new(client.clone()),
+            }
+        };
+        let api = gateway();
         let post_views = conf.post_views.values().cloned().collect();
+        let state = state_file.load();
+        let list_percent = state
+            .list_percent
+            .unwrap_or(config.layout.list_percent)
+            .clamp(MIN_LIST_PERCENT, MAX_LIST_PERCENT);
         Self {
             exit: false,
-            post_list: widgets::PostList::new(api.clone(), post_views, theme.clone()),
-            post_content: widgets::PostContent::new(api, theme),
+            post_list: widgets::PostList::new(
+                gateway(),
+                page_sender,
+                post_views,
+                config.list.clone(),
+                TimeDisplay::new(
+                    config.display.timezone.as_deref(),
+                    config.display.relative_times,
+                ),
+                theme.clone(),
+            ),
+            post_content: widgets::PostContent::new(
+                gateway(),
+                conf.team_name(),
+                config.display.clone(),
+                images,
+                theme.clone(),
+            ),
+            recent_posts: widgets::RecentPosts::new(theme.clone()),
+            search: widgets::Search::new(theme.clone()),
+            local_search: Arc::new(Mutex::new(LocalSearch::new(post_cache))),
+            html_export,
+            status_bar: widgets::StatusBar::new(theme),
+            clipboard: config.clipboard.clone(),
+            api,
+            pages,
+            search_results,
+            search_sender,
+            export_messages,
+            export_sender,
+            team_name: conf.team_name(),
+            state,
+            state_file,
+            layout: config.layout.clone(),
+            list_percent,
+            zoomed: false,
+            main_area: Rect::default(),
+            stacked: false,
+            divider: 0,
+            dragging_divider: false,
         }
     }
 
@@ -37,16 +167,43 @@ impl App {
         while !self.exit {
             terminal.draw(|frame| self.draw(frame))?;
             self.handle_events(&mut events, &mut tick).await?;
+            self.record_current_post();
+        }
+        if let Err(e) = self.state_file.save(&self.state) {
+            eprintln!("failed to save state: {}", e);
         }
         Ok(())
     }
 
     fn draw(&mut self, frame: &mut Frame) {
         let frame_area = frame.area();
-        let horizontal = Layout::horizontal([Constraint::Fill(1); 2]);
-        let [left_area, right_area] = horizontal.areas(frame_area);
-        frame.render_widget(&mut self.post_list, left_area);
-        frame.render_widget(&mut self.post_content, right_area);
+        let [main_area, status_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame_area);
+        self.main_area = main_area;
+        self.stacked = self.layout.is_stacked(main_area.width);
+        if self.zoomed {
+            frame.render_widget(&mut self.post_content, main_area);
+        } else {
+            let constraints = [
+                Constraint::Percentage(self.list_percent),
+                Constraint::Fill(1),
+            ];
+            let [list_area, content_area] = if self.stacked {
+                Layout::vertical(constraints).areas(main_area)
+            } else {
+                Layout::horizontal(constraints).areas(main_area)
+            };
+            self.divider = if self.stacked {
+                content_area.y
+            } else {
+                content_area.x
+            };
+            frame.render_widget(&mut self.post_list, list_area);
+            frame.render_widget(&mut self.post_content, content_area);
+        }
+        frame.render_widget(&mut self.status_bar, status_area);
+        frame.render_widget(&mut self.recent_posts, frame_area);
+        frame.render_widget(&mut self.search, frame_area);
     }
 
     async fn handle_events(
@@ -55,21 +212,59 @@ impl App {
         tick: &mut tokio::time::Interval,
     ) -> io::Result<()> {
         tokio::select! {
-            maybe_event = events.next() => {
-                if let Some(Ok(Event::Key(key_event))) = maybe_event
-                    && key_event.kind == KeyEventKind::Press
-                {
+            maybe_event = events.next() => match maybe_event {
+                Some(Ok(Event::Key(key_event))) if key_event.kind == KeyEventKind::Press => {
                     self.handle_key_event(key_event).await;
                 }
+                Some(Ok(Event::Mouse(mouse_event))) => self.handle_mouse_event(mouse_event).await,
+                _ => {}
+            },
+            Some(page) = self.pages.recv() => self.post_list.receive_page(page),
+            Some((query, hits)) = self.search_results.recv() => {
+                self.search.show_results(query, widgets::SearchScope::Local, hits);
             }
+            Some(message) = self.export_messages.recv() => self.status_bar.show(message),
             _ = tick.tick() => {}
         }
         Ok(())
     }
 
     async fn handle_key_event(&mut self, key_event: KeyEvent) {
+        if self.recent_posts.is_open() {
+            if let Some(entry) = self.recent_posts.handle_key(key_event) {
+                self.open_recently_viewed(entry).await;
+            }
+            return;
+        }
+        if self.search.is_open() {
+            match self.search.handle_key(key_event) {
+                Some(widgets::SearchRequest::Run { query, scope }) => {
+                    self.run_search(query, scope).await
+                }
+                Some(widgets::SearchRequest::Open(post)) => {
+                    if let Err(e) = self.post_content.show_post(&post).await {
+                        eprintln!("failed to show post: {}", e);
+                    }
+                }
+                None => {}
+            }
+            return;
+        }
+        if self.post_content.is_capturing_keys() {
+            self.post_content.handle_key(key_event).await;
+            // toggling a task changes the progress shown in the list
+            if let Some((post, _)) = self.post_content.current_post() {
+                self.post_list.update_task_progress(post);
+            }
+            if let Some(error) = self.post_content.take_task_error() {
+                self.status_bar.show(widgets::StatusMessage::Error(error));
+            }
+            self.copy_yanked();
+            return;
+        }
         self.post_list.handle_key(key_event).await;
-        self.post_content.handle_key(key_event);
+        self.post_content.handle_key(key_event).await;
+        self.copy_yanked();
         match key_event.code {
             KeyCode::Char('q') => self.exit(),
             KeyCode::Enter => {
@@ -83,28 +278,241 @@ impl App {
             KeyCode::Char('W') => self.post_list.unwatch_selected().await,
             KeyCode::Char('s') => self.post_list.star_selected().await,
             KeyCode::Char('S') => self.post_list.unstar_selected().await,
-            KeyCode::Char('o') => self.open_selected_post_in_browser(),
+            KeyCode::Char('r') => self
+                .recent_posts
+                .open(self.state.recently_viewed_in(&self.team_name)),
+            KeyCode::Char('/') => self.search.open(),
+            KeyCode::Char('o') if !key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.open_selected_post_in_browser()
+            }
+            KeyCode::Char('[') => self.resize_list(self.list_percent.saturating_sub(RESIZE_STEP)),
+            KeyCode::Char(']') => self.resize_list(self.list_percent.saturating_add(RESIZE_STEP)),
+            KeyCode::Char('F') => self.zoomed = !self.zoomed,
+            KeyCode::Char('e') => self.export_current_post(),
+            KeyCode::Char('E') => self.export_current_view(),
+            _ => {}
+        }
+    }
+
+    async fn handle_mouse_event(&mut self, mouse_event: MouseEvent) {
+        if self.recent_posts.is_open() || self.search.is_open() {
+            return;
+        }
+        let position = if self.stacked {
+            mouse_event.row
+        } else {
+            mouse_event.column
+        };
+        match mouse_event.kind {
+            // the divider is the border of the post content facing the list and the cell before it
+            MouseEventKind::Down(MouseButton::Left)
+                if !self.zoomed
+                    && self
+                        .main_area
+                        .contains((mouse_event.column, mouse_event.row).into())
+                    && (self.divider.saturating_sub(1)..=self.divider).contains(&position) =>
+            {
+                self.dragging_divider = true;
+                return;
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.dragging_divider => {
+                self.drag_divider(position);
+                return;
+            }
+            MouseEventKind::Up(MouseButton::Left) if self.dragging_divider => {
+                self.dragging_divider = false;
+                return;
+            }
             _ => {}
         }
+        // the list keeps its last areas while hidden
+        if !self.zoomed
+            && self.post_list.handle_mouse(mouse_event).await
+            && let Some(selected_post) = self.post_list.selected_post()
+            && let Err(e) = self.post_content.show_post(selected_post).await
+        {
+            eprintln!("failed to show post: {}", e);
+        }
+        self.post_content.handle_mouse(mouse_event).await;
+    }
+
+    fn drag_divider(&mut self, position: u16) {
+        let (start, length) = if self.stacked {
+            (self.main_area.y, self.main_area.height)
+        } else {
+            (self.main_area.x, self.main_area.width)
+        };
+        if length == 0 {
+            return;
+        }
+        let offset = u32::from(position.saturating_sub(start));
+        self.resize_list((offset * 100 / u32::from(length)) as u16);
+    }
+
+    /// Sets the share of the post list, remembering it for the next session.
+    fn resize_list(&mut self, percent: u16) {
+        self.list_percent = percent.clamp(MIN_LIST_PERCENT, MAX_LIST_PERCENT);
+        self.state.list_percent = Some(self.list_percent);
+    }
+
+    /// Searches esa, or the posts on disk, and shows what was found in the popup. The posts on
+    /// disk are searched on a blocking task, the hits arriving through `search_results`.
+    async fn run_search(&mut self, query: String, scope: widgets::SearchScope) {
+        let hits = match scope {
+            widgets::SearchScope::Esa => match self
+                .api
+                .fetch_posts(Some(query.clone()), None, None, Some(SEARCH_PER_PAGE), 1)
+                .await
+            {
+                Ok(page) => page
+                    .posts
+                    .into_iter()
+                    .map(|post| SearchHit {
+                        post,
+                        score: 0,
+                        snippet: None,
+                    })
+                    .collect(),
+                Err(e) => {
+                    self.status_bar.show(widgets::StatusMessage::Error(format!(
+                        "Failed to search: {}",
+                        e
+                    )));
+                    return;
+                }
+            },
+            widgets::SearchScope::Local => {
+                let pattern = match Pattern::parse(&query) {
+                    Ok(pattern) => pattern,
+                    Err(e) => {
+                        self.status_bar.show(widgets::StatusMessage::Error(format!(
+                            "Invalid pattern: {}",
+                            e
+                        )));
+                        return;
+                    }
+                };
+                self.status_bar.show(widgets::StatusMessage::Info(
+                    "Searching the posts on disk…".to_string(),
+                ));
+                let local_search = self.local_search.clone();
+                let sender = self.search_sender.clone();
+                tokio::task::spawn_blocking(move || {
+                    let hits = local_search.blocking_lock().search(&pattern);
+                    let _ = sender.send((query, hits));
+                });
+                return;
+            }
+        };
+        self.search.show_results(query, scope, hits);
+    }
+
+    /// Writes the post being shown to an HTML file.
+    fn export_current_post(&mut self) {
+        let Some(content) = &self.post_content.content else {
+            return;
+        };
+        let message = match self
+            .html_export
+            .export_post(&content.post, &content.body.body_md)
+        {
+            Ok(path) => widgets::StatusMessage::Info(format!("Exported to {}", path.display())),
+            Err(e) => widgets::StatusMessage::Error(format!("Failed to export: {}", e)),
+        };
+        self.status_bar.show(message);
+    }
+
+    /// Writes every post matching the current view to HTML files with an index page, in the
+    /// background, reporting progress in the status bar.
+    fn export_current_view(&mut self) {
+        let view = self.post_list.current_view();
+        let title = view.map_or("All posts".to_string(), |view| view.title.clone());
+        let query = view.and_then(|view| view.query.clone());
+        // the posts go in the order the tab shows them
+        let sort = view.and_then(|view| view.sort).unwrap_or_default();
+        let order = view.and_then(|view| view.order).unwrap_or_default();
+        let html_export = self.html_export.clone();
+        let api = self.api.clone();
+        let sender = self.export_sender.clone();
+        self.status_bar.show(widgets::StatusMessage::Info(format!(
+            "Exporting {}…",
+            title
+        )));
+        tokio::spawn(async move {
+            let progress = |exported| {
+                let _ = sender.send(widgets::StatusMessage::Info(format!(
+                    "Exporting {}: {} posts",
+                    title, exported
+                )));
+            };
+            let message = match html_export
+                .export_view(api.as_ref(), &title, query, sort, order, progress)
+                .await
+            {
+                Ok((exported, index)) => widgets::StatusMessage::Info(format!(
+                    "Exported {} posts to {}",
+                    exported,
+                    index.display()
+                )),
+                Err(e) => widgets::StatusMessage::Error(format!("Failed to export: {}", e)),
+            };
+            let _ = sender.send(message);
+        });
     }
 
     fn exit(&mut self) {
         self.exit = true;
     }
 
+    async fn open_recently_viewed(&mut self, entry: RecentlyViewed) {
+        let post_number = PostNumber::from(entry.post_number);
+        let Some(post) = self.api.fetch_post(&post_number).await else {
+            eprintln!("failed to fetch post: {}", post_number);
+            return;
+        };
+        if let Err(e) = self.post_content.show_post(&post).await {
+            eprintln!("failed to show post: {}", e);
+            return;
+        }
+        self.post_content.scroll = entry.scroll;
+    }
+
+    fn record_current_post(&mut self) {
+        let Some((post, scroll)) = self.post_content.current_post() else {
+            return;
+        };
+        let viewed = RecentlyViewed {
+            team_name: self.team_name.clone(),
+            post_number: post.post_number.to_i32(),
+            full_name: post.full_name.clone(),
+            scroll,
+        };
+        if self.state.recently_viewed.first() != Some(&viewed) {
+            self.state.record_view(viewed);
+        }
+    }
+
+    fn copy_yanked(&mut self) {
+        let Some(item) = self.post_content.take_yanked() else {
+            return;
+        };
+        let message = match clipboard::copy(&item.text, &self.clipboard) {
+            Ok(CopyMethod::Osc52) => {
+                widgets::StatusMessage::Info(format!("Copied {} (OSC 52)", item.kind))
+            }
+            Ok(CopyMethod::Command(program)) => {
+                widgets::StatusMessage::Info(format!("Copied {} with {}", item.kind, program))
+            }
+            Err(e) => widgets::StatusMessage::Error(format!("Failed to copy {}: {}", item.kind, e)),
+        };
+        self.status_bar.show(message);
+    }
+
     fn open_selected_post_in_browser(&self) {
         let Some(selected_post) = self.post_list.selected_post() else {
             return;
         };
-        let url = selected_post.url.as_str();
-        #[cfg(target_os = "macos")]
-        let result = Command::new("open").arg(url).status();
-        #[cfg(target_os = "linux")]
-        let result = Command::new("xdg-open").arg(url).status();
-        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
-        let result: Result<std::process::ExitStatus, std::io::Error> =
-            Err(std::io::Error::new(std::io::ErrorKind::Other, "unsupported OS"));
-        if let Err(e) = result {
+        if let Err(e) = browser::open_url(selected_post.url.as_str()) {
             eprintln!("failed to open browser: {}", e);
         }
     }
@@ -0,0 +1,15 @@
+use std::process::Command;
+
+/// Opens `url` in the OS default browser.
+pub fn open_url(url: &str) -> std::io::Result<()> {
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg(url).status();
+    #[cfg(target_os = "linux")]
+    let result = Command::new("xdg-open").arg(url).status();
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    let result: Result<std::process::ExitStatus, std::io::Error> = Err(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        "unsupported OS",
+    ));
+    result.map(|_| ())
+}
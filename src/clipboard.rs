@@ -0,0 +1,89 @@
+use std::{
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+use crate::domains::ClipboardConfig;
+
+/// Terminals cap the length of an OSC 52 sequence, so longer text goes through the command.
+const OSC52_MAX_ENCODED_BYTES: usize = 100_000;
+
+/// How the text reached the clipboard.
+pub enum CopyMethod {
+    Osc52,
+    Command(String),
+}
+
+/// Copies with OSC 52, or pipes the text to the configured command when that isn't available.
+pub fn copy(text: &str, config: &ClipboardConfig) -> anyhow::Result<CopyMethod> {
+    if config.osc52 && io::stdout().is_terminal() {
+        let sequence = osc52_sequence(text, std::env::var_os("TMUX").is_some());
+        if let Some(sequence) = sequence
+            && write_to_terminal(&sequence).is_ok()
+        {
+            return Ok(CopyMethod::Osc52);
+        }
+    }
+    let Some((program, args)) = config.command.split_first() else {
+        bail!("set clipboard.command to copy without OSC 52");
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("failed to run {}", program))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{} exited with {}", program, status);
+    }
+    Ok(CopyMethod::Command(program.clone()))
+}
+
+/// Inside tmux the sequence is wrapped in a DCS passthrough so it reaches the outer terminal.
+fn osc52_sequence(text: &str, in_tmux: bool) -> Option<String> {
+    let encoded = STANDARD.encode(text);
+    if encoded.len() > OSC52_MAX_ENCODED_BYTES {
+        return None;
+    }
+    let sequence = format!("\x1b]52;c;{}\x07", encoded);
+    if in_tmux {
+        Some(format!(
+            "\x1bPtmux;{}\x1b\\",
+            sequence.replace('\x1b', "\x1b\x1b")
+        ))
+    } else {
+        Some(sequence)
+    }
+}
+
+fn write_to_terminal(sequence: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(sequence.as_bytes())?;
+    stdout.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(false, Some("\x1b]52;c;ZWNobyBoaQ==\x07"))]
+    #[case(true, Some("\x1bPtmux;\x1b\x1b]52;c;ZWNobyBoaQ==\x07\x1b\\"))]
+    fn test_osc52_sequence(#[case] in_tmux: bool, #[case] expected: Option<&str>) {
+        assert_eq!(osc52_sequence("echo hi", in_tmux).as_deref(), expected);
+    }
+
+    #[test]
+    fn test_osc52_sequence_too_long() {
+        assert_eq!(osc52_sequence(&"a".repeat(80_000), false), None);
+    }
+}
@@ -0,0 +1,71 @@
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// The category path of a post, e.g. `dev/ops/2024`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Category(String);
+
+impl Category {
+    /// `None` for posts at the top level, which esa sends as a missing or empty category.
+    pub fn parse(path: &str) -> Option<Self> {
+        let path = path.trim_matches('/');
+        (!path.is_empty()).then(|| Category(path.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The categories from the top, e.g. `dev`, `ops` and `2024`.
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.0.split('/')
+    }
+
+    /// The innermost category, e.g. `2024` for `dev/ops/2024`.
+    pub fn leaf(&self) -> &str {
+        self.segments().last().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Whether a post is a stock document kept up to date, or a dated flow post such as a daily report.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostKind {
+    #[default]
+    Stock,
+    Flow,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("dev/ops/2024", Some("dev/ops/2024"))]
+    #[case("/日報/2026/05/", Some("日報/2026/05"))]
+    #[case("", None)]
+    fn test_parse(#[case] path: &str, #[case] expected: Option<&str>) {
+        assert_eq!(
+            Category::parse(path).as_ref().map(Category::as_str),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_segments() {
+        let category = Category::parse("dev/ops/2024").unwrap();
+        assert_eq!(
+            category.segments().collect::<Vec<_>>(),
+            ["dev", "ops", "2024"]
+        );
+        assert_eq!(category.leaf(), "2024");
+    }
+}
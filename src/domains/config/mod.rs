@@ -1,6 +1,6 @@
 use std::{collections::BTreeMap, sync::LazyLock};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -8,6 +8,16 @@ pub struct Config {
     pub workspaces: BTreeMap<String, WorkspaceConfig>,
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub themes: BTreeMap<String, ThemeConfig>,
+    #[serde(default)]
+    pub display: DisplayConfig,
+    #[serde(default)]
+    pub clipboard: ClipboardConfig,
+    #[serde(default)]
+    pub layout: LayoutConfig,
+    #[serde(default)]
+    pub list: ListConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
 }
 
 impl Config {
@@ -48,6 +58,115 @@ pub struct WorkspaceConfig {
 pub struct PostViewConfig {
     pub title: String,
     pub query: Option<String>,
+    /// Overrides `list.row_format` for this view.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_view_row_format"
+    )]
+    pub row_format: Option<Vec<String>>,
+    /// Sent as the API's `sort` parameter; esa sorts by `updated` without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<PostSort>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<SortOrder>,
+    /// Posts fetched at a time, from 1 to 100; esa sends 20 without one.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_per_page"
+    )]
+    pub per_page: Option<u32>,
+}
+
+impl PostViewConfig {
+    /// The title shown in the tab, followed by the sort when one is chosen, e.g.
+    /// `All Posts (stars ↓)`.
+    pub fn tab_title(&self) -> String {
+        if self.sort.is_none() && self.order.is_none() {
+            return self.title.clone();
+        }
+        let arrow = match self.order.unwrap_or_default() {
+            SortOrder::Asc => "↑",
+            SortOrder::Desc => "↓",
+        };
+        format!(
+            "{} ({} {})",
+            self.title,
+            self.sort.unwrap_or_default().as_str(),
+            arrow
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostSort {
+    #[default]
+    Updated,
+    Created,
+    Number,
+    Stars,
+    Watches,
+    Comments,
+    /// How well posts match the query.
+    BestMatch,
+}
+
+impl PostSort {
+    const ALL: [PostSort; 7] = [
+        PostSort::Updated,
+        PostSort::Created,
+        PostSort::Number,
+        PostSort::Stars,
+        PostSort::Watches,
+        PostSort::Comments,
+        PostSort::BestMatch,
+    ];
+
+    /// The value of the API's `sort` parameter.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PostSort::Updated => "updated",
+            PostSort::Created => "created",
+            PostSort::Number => "number",
+            PostSort::Stars => "stars",
+            PostSort::Watches => "watches",
+            PostSort::Comments => "comments",
+            PostSort::BestMatch => "best_match",
+        }
+    }
+
+    /// The sort after this one, going back to the first after the last.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|sort| *sort == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    /// The value of the API's `order` parameter.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+
+    pub fn reversed(self) -> Self {
+        match self {
+            SortOrder::Asc => SortOrder::Desc,
+            SortOrder::Desc => SortOrder::Asc,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -69,6 +188,173 @@ pub struct ThemeConfig {
     pub link: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DisplayConfig {
+    /// How the team's custom emojis are shown; `{name}` is replaced with the emoji code.
+    #[serde(default = "default_custom_emoji")]
+    pub custom_emoji: String,
+    /// Whether to download attached images and draw them inside the post content.
+    #[serde(default = "default_inline_images")]
+    pub inline_images: bool,
+    /// Upper bound of the rows an inline image may take.
+    #[serde(default = "default_image_max_height")]
+    pub image_max_height: u16,
+    /// Images larger than this many bytes are not downloaded.
+    #[serde(default = "default_image_max_bytes")]
+    pub image_max_bytes: u64,
+    /// Whether fenced code blocks show line numbers.
+    #[serde(default)]
+    pub code_line_numbers: bool,
+    /// IANA time zone post times are shown in, e.g. `Asia/Tokyo`; the system's zone by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// Whether post times are shown relative to now, e.g. `3h ago`.
+    #[serde(default)]
+    pub relative_times: bool,
+    /// Whether the header above the post body starts expanded, rather than showing only the title.
+    #[serde(default = "default_header_expanded")]
+    pub header_expanded: bool,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            custom_emoji: default_custom_emoji(),
+            inline_images: default_inline_images(),
+            image_max_height: default_image_max_height(),
+            image_max_bytes: default_image_max_bytes(),
+            code_line_numbers: false,
+            timezone: None,
+            relative_times: false,
+            header_expanded: default_header_expanded(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ClipboardConfig {
+    /// Whether to ask the terminal to copy with the OSC 52 escape sequence, which also works over SSH.
+    #[serde(default = "default_osc52")]
+    pub osc52: bool,
+    /// Command that receives the text on stdin when OSC 52 is off or can't carry the text,
+    /// e.g. `["pbcopy"]` or `["xclip", "-selection", "clipboard"]`.
+    #[serde(default)]
+    pub command: Vec<String>,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            osc52: default_osc52(),
+            command: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CacheConfig {
+    /// Whether lists and bodies are kept on disk and shown before the API answers.
+    #[serde(default = "default_cache_enabled")]
+    pub enabled: bool,
+    /// Cached lists and bodies are trimmed to this size, oldest first.
+    #[serde(default = "default_cache_max_megabytes")]
+    pub max_megabytes: u64,
+    /// Downloaded images are trimmed to this size, oldest first.
+    #[serde(default = "default_cache_image_max_megabytes")]
+    pub image_max_megabytes: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_cache_enabled(),
+            max_megabytes: default_cache_max_megabytes(),
+            image_max_megabytes: default_cache_image_max_megabytes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct LayoutConfig {
+    /// Share of the post list in percent, the rest going to the post content.
+    #[serde(default = "default_list_percent")]
+    pub list_percent: u16,
+    #[serde(default)]
+    pub orientation: LayoutOrientation,
+    /// With `orientation = "auto"`, terminals narrower than this stack the panes.
+    #[serde(default = "default_stack_below_width")]
+    pub stack_below_width: u16,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            list_percent: default_list_percent(),
+            orientation: LayoutOrientation::default(),
+            stack_below_width: default_stack_below_width(),
+        }
+    }
+}
+
+impl LayoutConfig {
+    /// Whether the post list goes above the post content rather than beside it.
+    pub fn is_stacked(&self, width: u16) -> bool {
+        match self.orientation {
+            LayoutOrientation::Horizontal => false,
+            LayoutOrientation::Vertical => true,
+            LayoutOrientation::Auto => width < self.stack_below_width,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutOrientation {
+    /// The list on the left and the content on the right.
+    Horizontal,
+    /// The list above the content.
+    Vertical,
+    /// Side by side, stacking when the terminal is narrower than `stack_below_width`.
+    #[default]
+    Auto,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ListConfig {
+    /// The lines shown for each post, with `{field}` or `{field:width}` replaced by the post's
+    /// values; `{field:>width}` aligns to the right.
+    #[serde(
+        default = "default_row_format",
+        deserialize_with = "deserialize_row_format"
+    )]
+    pub row_format: Vec<String>,
+    /// Whether to show one line per post, formatted with `compact_row_format`.
+    #[serde(default)]
+    pub compact: bool,
+    #[serde(default = "default_compact_row_format")]
+    pub compact_row_format: String,
+    /// The next page is fetched once the selection is this many posts from the end; `0` only
+    /// fetches it from the last row.
+    #[serde(default = "default_prefetch_rows")]
+    pub prefetch_rows: usize,
+}
+
+impl Default for ListConfig {
+    fn default() -> Self {
+        Self {
+            row_format: default_row_format(),
+            compact: false,
+            compact_row_format: default_compact_row_format(),
+            prefetch_rows: default_prefetch_rows(),
+        }
+    }
+}
+
 static THEME_CONFIG_DARK: LazyLock<ThemeConfig> = LazyLock::new(|| ThemeConfig {
     primary: Some("#E2E8F0".to_string()),
     muted: Some("#94A3B8".to_string()),
@@ -115,6 +401,97 @@ fn default_endpoint() -> String {
     "https://api.esa.io".to_string()
 }
 
+fn default_custom_emoji() -> String {
+    "[{name}]".to_string()
+}
+
+fn default_inline_images() -> bool {
+    true
+}
+
+fn default_image_max_height() -> u16 {
+    20
+}
+
+fn default_image_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_header_expanded() -> bool {
+    true
+}
+
+fn default_cache_enabled() -> bool {
+    true
+}
+
+fn default_cache_max_megabytes() -> u64 {
+    50
+}
+
+fn default_cache_image_max_megabytes() -> u64 {
+    100
+}
+
+/// A row needs at least one line, or the list would draw posts with no height.
+fn deserialize_row_format<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    let lines = Vec::<String>::deserialize(deserializer)?;
+    if lines.is_empty() {
+        return Err(D::Error::custom("row_format needs at least one line"));
+    }
+    Ok(lines)
+}
+
+fn deserialize_view_row_format<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<String>>, D::Error> {
+    deserialize_row_format(deserializer).map(Some)
+}
+
+/// esa answers at most 100 posts a page.
+fn deserialize_per_page<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u32>, D::Error> {
+    let per_page = u32::deserialize(deserializer)?;
+    if !(1..=100).contains(&per_page) {
+        return Err(D::Error::custom(format!(
+            "per_page must be between 1 and 100, got {}",
+            per_page
+        )));
+    }
+    Ok(Some(per_page))
+}
+
+fn default_osc52() -> bool {
+    true
+}
+
+fn default_row_format() -> Vec<String> {
+    vec![
+        "{kind} {number} {wip} {full_name} {tags}".to_string(),
+        "\u{f007} @{author}  {updated}".to_string(),
+        "{stars} {watches} {comments} {tasks}".to_string(),
+    ]
+}
+
+fn default_compact_row_format() -> String {
+    "{number:>6} {name:40} {author:12} {updated_relative:>8} {wip}".to_string()
+}
+
+fn default_prefetch_rows() -> usize {
+    5
+}
+
+fn default_list_percent() -> u16 {
+    50
+}
+
+fn default_stack_below_width() -> u16 {
+    100
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -130,13 +507,30 @@ mod tests {
                     team_name: "my_team".to_string(),
                     api_endpoint: "https://api.esa.io".to_string(),
                     token: "my_token".to_string(),
-                    post_views: BTreeMap::from([(
-                        "all".to_string(),
-                        PostViewConfig {
-                            title: "All Posts".to_string(),
-                            query: Some("sort:updated".to_string()),
-                        },
-                    )]),
+                    post_views: BTreeMap::from([
+                        (
+                            "all".to_string(),
+                            PostViewConfig {
+                                title: "All Posts".to_string(),
+                                query: Some("sort:updated".to_string()),
+                                row_format: None,
+                                sort: None,
+                                order: None,
+                                per_page: None,
+                            },
+                        ),
+                        (
+                            "popular".to_string(),
+                            PostViewConfig {
+                                title: "Popular".to_string(),
+                                query: None,
+                                row_format: None,
+                                sort: Some(PostSort::Stars),
+                                order: Some(SortOrder::Desc),
+                                per_page: Some(50),
+                            },
+                        ),
+                    ]),
                     theme: Some("dark".to_string()),
                 },
             )]),
@@ -144,6 +538,11 @@ mod tests {
                 ("dark".to_string(), THEME_CONFIG_DARK.clone()),
                 ("light".to_string(), THEME_CONFIG_LIGHT.clone()),
             ]),
+            display: DisplayConfig::default(),
+            clipboard: ClipboardConfig::default(),
+            layout: LayoutConfig::default(),
+            list: ListConfig::default(),
+            cache: CacheConfig::default(),
         }
     }
 
@@ -152,4 +551,85 @@ mod tests {
         let toml_str = toml::to_string(&config).unwrap();
         assert_snapshot!(toml_str);
     }
+
+    #[rstest::rstest]
+    #[case(LayoutOrientation::Auto, 99, true)]
+    #[case(LayoutOrientation::Auto, 100, false)]
+    #[case(LayoutOrientation::Horizontal, 40, false)]
+    #[case(LayoutOrientation::Vertical, 200, true)]
+    fn test_is_stacked(
+        #[case] orientation: LayoutOrientation,
+        #[case] width: u16,
+        #[case] stacked: bool,
+    ) {
+        let layout = LayoutConfig {
+            orientation,
+            ..LayoutConfig::default()
+        };
+        assert_eq!(layout.is_stacked(width), stacked);
+    }
+
+    #[rstest::rstest]
+    #[case(None, None, "All Posts")]
+    #[case(Some(PostSort::Stars), None, "All Posts (stars ↓)")]
+    #[case(None, Some(SortOrder::Asc), "All Posts (updated ↑)")]
+    #[case(
+        Some(PostSort::BestMatch),
+        Some(SortOrder::Desc),
+        "All Posts (best_match ↓)"
+    )]
+    fn test_tab_title(
+        #[case] sort: Option<PostSort>,
+        #[case] order: Option<SortOrder>,
+        #[case] expected: &str,
+    ) {
+        let view = PostViewConfig {
+            title: "All Posts".to_string(),
+            query: None,
+            row_format: None,
+            sort,
+            order,
+            per_page: None,
+        };
+        assert_eq!(view.tab_title(), expected);
+    }
+
+    #[test]
+    fn test_empty_row_format_is_rejected() {
+        let list = toml::from_str::<ListConfig>("row_format = []").unwrap_err();
+        assert_eq!(list.message(), "row_format needs at least one line");
+        let view = toml::from_str::<PostViewConfig>("title = \"All\"\nrow_format = []");
+        assert_eq!(
+            view.unwrap_err().message(),
+            "row_format needs at least one line"
+        );
+        let view = toml::from_str::<PostViewConfig>("title = \"All\"\nrow_format = [\"{name}\"]");
+        assert_eq!(view.unwrap().row_format, Some(vec!["{name}".to_string()]));
+    }
+
+    #[rstest::rstest]
+    #[case("per_page = 0", Err("per_page must be between 1 and 100, got 0"))]
+    #[case("per_page = 1", Ok(Some(1)))]
+    #[case("per_page = 100", Ok(Some(100)))]
+    #[case("per_page = 101", Err("per_page must be between 1 and 100, got 101"))]
+    #[case("", Ok(None))]
+    fn test_per_page(#[case] line: &str, #[case] expected: Result<Option<u32>, &str>) {
+        let view = toml::from_str::<PostViewConfig>(&format!("title = \"All\"\n{}", line));
+        assert_eq!(
+            view.as_ref()
+                .map(|view| view.per_page)
+                .map_err(|e| e.message()),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_sort_cycles() {
+        let mut sort = PostSort::Updated;
+        for _ in 0..PostSort::ALL.len() {
+            sort = sort.next();
+        }
+        assert_eq!(sort, PostSort::Updated);
+        assert_eq!(PostSort::Comments.next(), PostSort::BestMatch);
+    }
 }
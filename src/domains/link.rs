@@ -0,0 +1,99 @@
+use url::Url;
+
+use crate::domains::PostNumber;
+
+/// Where a link found in a post body points to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LinkTarget {
+    /// A post of the current team, opened inside esa-reader.
+    Post(PostNumber),
+    /// Anything else, opened in the browser.
+    External(Url),
+}
+
+impl LinkTarget {
+    pub fn parse(raw: &str, team_name: &str) -> Option<Self> {
+        let raw = raw.trim();
+        if let Some(path) = raw.strip_prefix('/') {
+            return post_number_from_path(path).map(LinkTarget::Post);
+        }
+        let url = Url::parse(raw).ok()?;
+        if url.host_str() == Some(&team_host(team_name))
+            && let Some(post_number) = post_number_from_path(url.path().trim_start_matches('/'))
+        {
+            return Some(LinkTarget::Post(post_number));
+        }
+        match url.scheme() {
+            "http" | "https" | "mailto" => Some(LinkTarget::External(url)),
+            _ => None,
+        }
+    }
+}
+
+pub fn post_url(team_name: &str, post_number: &PostNumber) -> String {
+    format!(
+        "https://{}/posts/{}",
+        team_host(team_name),
+        post_number.to_i32()
+    )
+}
+
+/// A markdown link to the post in the `[#123 title](url)` form esa renders as a post card.
+pub fn post_reference(post_number: &PostNumber, title: &str, url: &str) -> String {
+    let title = title.replace('[', "\\[").replace(']', "\\]");
+    format!("[#{} {}]({})", post_number.to_i32(), title, url)
+}
+
+fn team_host(team_name: &str) -> String {
+    format!("{}.esa.io", team_name)
+}
+
+fn post_number_from_path(path: &str) -> Option<PostNumber> {
+    let mut segments = path.split(['/', '#', '?']);
+    if segments.next()? != "posts" {
+        return None;
+    }
+    segments.next()?.parse::<i32>().ok().map(PostNumber::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        "https://my_team.esa.io/posts/123",
+        Some(LinkTarget::Post(PostNumber::from(123)))
+    )]
+    #[case(
+        "https://my_team.esa.io/posts/123#comment-1",
+        Some(LinkTarget::Post(PostNumber::from(123)))
+    )]
+    #[case("/posts/42", Some(LinkTarget::Post(PostNumber::from(42))))]
+    #[case(
+        "https://other.esa.io/posts/123",
+        Some(LinkTarget::External(Url::parse("https://other.esa.io/posts/123").unwrap()))
+    )]
+    #[case(
+        "https://github.com/feedforce/omni-hub/pull/5675",
+        Some(LinkTarget::External(Url::parse("https://github.com/feedforce/omni-hub/pull/5675").unwrap()))
+    )]
+    #[case("#heading", None)]
+    #[case("javascript:alert(1)", None)]
+    fn test_parse_link_target(#[case] raw: &str, #[case] expected: Option<LinkTarget>) {
+        assert_eq!(LinkTarget::parse(raw, "my_team"), expected);
+    }
+
+    #[rstest]
+    #[case("Runbook", "[#42 Runbook](https://my_team.esa.io/posts/42)")]
+    #[case(
+        "[WIP] Runbook",
+        "[#42 \\[WIP\\] Runbook](https://my_team.esa.io/posts/42)"
+    )]
+    fn test_post_reference(#[case] title: &str, #[case] expected: &str) {
+        let post_number = PostNumber::from(42);
+        let url = post_url("my_team", &post_number);
+        assert_eq!(post_reference(&post_number, title, &url), expected);
+    }
+}
@@ -1,27 +1,42 @@
+mod category;
 mod config;
+mod link;
+mod query;
+mod search;
+mod state;
+mod task_list;
 mod theme;
+mod time;
 
 use chrono::{DateTime, Utc};
+pub use category::{Category, PostKind};
 pub use config::*;
+pub use link::{LinkTarget, post_reference, post_url};
+pub use query::{LocalQuery, sort_posts};
+pub use search::{Document, Pattern, SearchHit, SearchIndex, Snippet, rank};
+pub use state::{RecentlyViewed, State};
+pub use task_list::{task_items, toggle_task};
 pub use theme::Theme;
+pub use time::TimeDisplay;
 use core::fmt;
+use serde::{Deserialize, Serialize};
 use url::Url;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UserId(pub String);
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct User {
     pub name: String,
     pub id: UserId,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Tag {
     pub label: String,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PostNumber(i32);
 
 impl From<i32> for PostNumber {
@@ -42,11 +57,19 @@ impl fmt::Display for PostNumber {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Post {
     pub post_number: PostNumber,
     pub name: String,
     pub full_name: String,
+    /// `None` for posts at the top level.
+    pub category: Option<Category>,
+    pub wip: bool,
+    pub kind: PostKind,
+    /// The revision the list or post was fetched at.
+    pub revision_number: i32,
+    /// The change message of that revision, empty when none was given.
+    pub message: String,
     pub stars: u32,
     pub starred: bool,
     pub tags: Vec<Tag>,
@@ -57,4 +80,66 @@ pub struct Post {
     pub created_by: User,
     pub updated_by: User,
     pub url: Url,
+    pub tasks_count: u32,
+    pub done_tasks_count: u32,
+    pub comments_count: u32,
+}
+
+/// The markdown body of a post together with the revision it was read at.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PostBody {
+    pub body_md: String,
+    pub revision_number: i32,
+    /// The user who made that revision.
+    pub revised_by: UserId,
+    /// Read from the cache while esa couldn't be reached, so there may be newer revisions.
+    #[serde(skip)]
+    pub stale: bool,
+    /// Saved while someone else was editing, so esa merged the two and the result needs a look.
+    #[serde(skip)]
+    pub conflicted: bool,
+}
+
+/// A comment on a post, kept by `sync` so it can be searched locally.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Comment {
+    pub body_md: String,
+    pub created_by: User,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A post with every field set, for tests that show posts.
+#[cfg(test)]
+pub fn sample_post() -> Post {
+    use chrono::TimeZone;
+
+    let user = User {
+        name: "Taro".to_string(),
+        id: UserId("taro".to_string()),
+    };
+    Post {
+        post_number: PostNumber::from(42),
+        name: "デプロイ手順のまとめ".to_string(),
+        full_name: "dev/ops/デプロイ手順のまとめ #infra".to_string(),
+        category: Category::parse("dev/ops"),
+        wip: true,
+        kind: PostKind::Stock,
+        revision_number: 7,
+        message: "手順を更新".to_string(),
+        stars: 3,
+        starred: true,
+        tags: vec![Tag {
+            label: "infra".to_string(),
+        }],
+        watches: 5,
+        watched: false,
+        created_at: Utc.with_ymd_and_hms(2026, 4, 1, 9, 0, 0).unwrap(),
+        updated_at: Utc.with_ymd_and_hms(2026, 5, 1, 9, 0, 0).unwrap(),
+        created_by: user.clone(),
+        updated_by: user,
+        url: "https://my_team.esa.io/posts/42".parse().unwrap(),
+        tasks_count: 4,
+        done_tasks_count: 1,
+        comments_count: 2,
+    }
 }
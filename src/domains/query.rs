@@ -0,0 +1,266 @@
+use super::{Post, PostKind, PostSort, SortOrder};
+
+/// The part of esa's search syntax that can be checked against cached posts while offline.
+///
+/// Keywords are looked for in the title, category and tags; qualifiers we can't evaluate,
+/// such as `comment:` or `stars:>3`, are left out, so they narrow nothing even when negated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalQuery {
+    terms: Vec<Term>,
+    sort: Option<(PostSort, Option<SortOrder>)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Term {
+    negated: bool,
+    condition: Condition,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Condition {
+    Keyword(String),
+    Title(String),
+    /// `category:`, matching any part of the path.
+    Category(String),
+    /// `in:`, the category and those below it.
+    In(String),
+    /// `on:`, exactly the category.
+    On(String),
+    Tag(String),
+    User(String),
+    UpdatedBy(String),
+    Wip(bool),
+    Kind(PostKind),
+    Starred(bool),
+    Watched(bool),
+}
+
+impl LocalQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut terms = vec![];
+        let mut sort = None;
+        for word in split_words(query) {
+            let (negated, word) = match word.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, word.as_str()),
+            };
+            if let Some(value) = word.strip_prefix("sort:") {
+                sort = parse_sort(value);
+                continue;
+            }
+            if let Some(condition) = parse_condition(word) {
+                terms.push(Term { negated, condition });
+            }
+        }
+        Self { terms, sort }
+    }
+
+    /// The order given with `sort:` in the query, e.g. `sort:stars-asc`.
+    pub fn sort(&self) -> Option<(PostSort, Option<SortOrder>)> {
+        self.sort
+    }
+
+    pub fn matches(&self, post: &Post) -> bool {
+        self.terms
+            .iter()
+            .all(|term| term.condition.matches(post) != term.negated)
+    }
+}
+
+impl Condition {
+    fn matches(&self, post: &Post) -> bool {
+        let category = post
+            .category
+            .as_ref()
+            .map(|category| category.as_str())
+            .unwrap_or_default();
+        match self {
+            Condition::Keyword(keyword) => {
+                contains(&post.full_name, keyword)
+                    || post.tags.iter().any(|tag| contains(&tag.label, keyword))
+            }
+            Condition::Title(title) => contains(&post.name, title),
+            Condition::Category(part) => contains(category, part),
+            Condition::In(path) => category == path || category.starts_with(&format!("{}/", path)),
+            Condition::On(path) => category == path,
+            Condition::Tag(label) => post
+                .tags
+                .iter()
+                .any(|tag| tag.label.eq_ignore_ascii_case(label)),
+            Condition::User(id) => post.created_by.id.0 == *id,
+            Condition::UpdatedBy(id) => post.updated_by.id.0 == *id,
+            Condition::Wip(wip) => post.wip == *wip,
+            Condition::Kind(kind) => post.kind == *kind,
+            Condition::Starred(starred) => post.starred == *starred,
+            Condition::Watched(watched) => post.watched == *watched,
+        }
+    }
+}
+
+/// Orders posts the way the API would for `sort` and `order`; `best_match` falls back to
+/// the update time.
+pub fn sort_posts(posts: &mut [Post], sort: PostSort, order: SortOrder) {
+    posts.sort_by(|a, b| {
+        let ordering = match sort {
+            PostSort::Updated | PostSort::BestMatch => a.updated_at.cmp(&b.updated_at),
+            PostSort::Created => a.created_at.cmp(&b.created_at),
+            PostSort::Number => a.post_number.to_i32().cmp(&b.post_number.to_i32()),
+            PostSort::Stars => a.stars.cmp(&b.stars),
+            PostSort::Watches => a.watches.cmp(&b.watches),
+            PostSort::Comments => a.comments_count.cmp(&b.comments_count),
+        };
+        let ordering = match order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        };
+        // newer posts first among ties, as esa does
+        ordering.then_with(|| b.post_number.to_i32().cmp(&a.post_number.to_i32()))
+    });
+}
+
+/// Splits on whitespace, keeping `"quoted phrases"` together without their quotes.
+fn split_words(query: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// `None` for qualifiers that can't be checked against a cached post.
+fn parse_condition(word: &str) -> Option<Condition> {
+    if let Some(tag) = word.strip_prefix('#') {
+        return Some(Condition::Tag(tag.to_string()));
+    }
+    if let Some(user) = word.strip_prefix('@') {
+        return Some(Condition::User(user.to_string()));
+    }
+    let Some((qualifier, value)) = word.split_once(':') else {
+        return Some(Condition::Keyword(word.to_string()));
+    };
+    let value = value.trim_matches('/').to_string();
+    let condition = match qualifier {
+        "title" | "name" => Condition::Title(value),
+        "category" => Condition::Category(value),
+        "in" => Condition::In(value),
+        "on" => Condition::On(value),
+        "tag" => Condition::Tag(value),
+        "user" => Condition::User(value),
+        "updated_by" => Condition::UpdatedBy(value),
+        "wip" => Condition::Wip(parse_bool(&value)?),
+        "kind" => match value.as_str() {
+            "stock" => Condition::Kind(PostKind::Stock),
+            "flow" => Condition::Kind(PostKind::Flow),
+            _ => return None,
+        },
+        "starred" => Condition::Starred(parse_bool(&value)?),
+        "watched" => Condition::Watched(parse_bool(&value)?),
+        _ => return None,
+    };
+    Some(condition)
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_sort(value: &str) -> Option<(PostSort, Option<SortOrder>)> {
+    let (field, order) = match value.rsplit_once('-') {
+        Some((field, "asc")) => (field, Some(SortOrder::Asc)),
+        Some((field, "desc")) => (field, Some(SortOrder::Desc)),
+        _ => (value, None),
+    };
+    let sort = match field {
+        "updated" => PostSort::Updated,
+        "created" => PostSort::Created,
+        "number" => PostSort::Number,
+        "stars" => PostSort::Stars,
+        "watches" => PostSort::Watches,
+        "comments" => PostSort::Comments,
+        "best_match" => PostSort::BestMatch,
+        _ => return None,
+    };
+    Some((sort, order))
+}
+
+fn contains(text: &str, keyword: &str) -> bool {
+    text.to_lowercase().contains(&keyword.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::sample_post;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn post() -> Post {
+        sample_post()
+    }
+
+    #[rstest]
+    #[case("", true)]
+    #[case("デプロイ", true)]
+    #[case("\"手順 の\"", false)]
+    #[case("INFRA", true)]
+    #[case("title:手順", true)]
+    #[case("category:ops", true)]
+    #[case("in:dev", true)]
+    #[case("in:de", false)]
+    #[case("on:dev", false)]
+    #[case("on:dev/ops", true)]
+    #[case("#infra", true)]
+    #[case("tag:design", false)]
+    #[case("@taro", true)]
+    #[case("user:hanako", false)]
+    #[case("wip:true kind:stock", true)]
+    #[case("-wip:true", false)]
+    #[case("starred:true watched:false", true)]
+    #[case("stars:>10 comment:foo", true)]
+    #[case("-stars:>3", true)]
+    #[case("-comment:foo", true)]
+    #[case("-wip:maybe デプロイ", true)]
+    #[case("sort:updated -kind:flow", true)]
+    fn test_matches(post: Post, #[case] query: &str, #[case] expected: bool) {
+        assert_eq!(LocalQuery::parse(query).matches(&post), expected);
+    }
+
+    #[rstest]
+    #[case("sort:stars", Some((PostSort::Stars, None)))]
+    #[case("wip:false sort:created-asc", Some((PostSort::Created, Some(SortOrder::Asc))))]
+    #[case("sort:best_match-desc", Some((PostSort::BestMatch, Some(SortOrder::Desc))))]
+    #[case("sort:unknown", None)]
+    fn test_sort(#[case] query: &str, #[case] expected: Option<(PostSort, Option<SortOrder>)>) {
+        assert_eq!(LocalQuery::parse(query).sort(), expected);
+    }
+
+    #[rstest]
+    fn test_sort_posts(post: Post) {
+        let with_stars = |number: i32, stars: u32| Post {
+            post_number: number.into(),
+            stars,
+            ..post.clone()
+        };
+        let mut posts = vec![with_stars(1, 5), with_stars(2, 9), with_stars(3, 5)];
+        sort_posts(&mut posts, PostSort::Stars, SortOrder::Desc);
+        let numbers: Vec<i32> = posts.iter().map(|post| post.post_number.to_i32()).collect();
+        assert_eq!(numbers, vec![2, 3, 1]);
+    }
+}
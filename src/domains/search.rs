@@ -0,0 +1,502 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::Post;
+
+/// Matches counted per field, so a long post repeating a word doesn't outrank every title.
+const MAX_COUNTED: usize = 10;
+/// Characters kept before and after a match in a snippet.
+const SNIPPET_BEFORE: usize = 30;
+const SNIPPET_AFTER: usize = 60;
+
+/// Where in a post a match was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Category,
+    Tags,
+    Body,
+    Comments,
+}
+
+impl Field {
+    /// The order snippets are looked for in, the body first since titles are shown anyway.
+    const SNIPPET_ORDER: [Field; 5] = [
+        Field::Body,
+        Field::Comments,
+        Field::Title,
+        Field::Category,
+        Field::Tags,
+    ];
+
+    fn weight(self) -> u32 {
+        match self {
+            Field::Title => 8,
+            Field::Category | Field::Tags => 4,
+            Field::Body | Field::Comments => 1,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Title => "タイトル",
+            Field::Category => "カテゴリ",
+            Field::Tags => "タグ",
+            Field::Body => "本文",
+            Field::Comments => "コメント",
+        }
+    }
+}
+
+/// A post with the text to search in it.
+#[derive(Clone, Debug)]
+pub struct Document {
+    pub post: Post,
+    /// `None` when the body hasn't been read yet.
+    pub body_md: Option<String>,
+    pub comments: Vec<String>,
+}
+
+impl Document {
+    fn text(&self, field: Field) -> String {
+        match field {
+            Field::Title => self.post.name.clone(),
+            Field::Category => self
+                .post
+                .category
+                .as_ref()
+                .map(|category| category.as_str().to_string())
+                .unwrap_or_default(),
+            Field::Tags => self
+                .post
+                .tags
+                .iter()
+                .map(|tag| tag.label.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            Field::Body => self.body_md.clone().unwrap_or_default(),
+            Field::Comments => self.comments.join("\n"),
+        }
+    }
+}
+
+/// A regular expression to search for, ignoring case.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    regex: Regex,
+    /// The pattern itself when it has no special characters, so the index can narrow down
+    /// the posts to look in.
+    literal: Option<String>,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Self, regex::Error> {
+        let regex = RegexBuilder::new(pattern).case_insensitive(true).build()?;
+        let literal =
+            (!pattern.contains(|c| "\\.+*?()|[]{}^$".contains(c))).then(|| pattern.to_lowercase());
+        Ok(Self { regex, literal })
+    }
+}
+
+/// A post that matched, with the first match to show.
+#[derive(Clone, Debug)]
+pub struct SearchHit {
+    pub post: Post,
+    pub score: u32,
+    pub snippet: Option<Snippet>,
+}
+
+/// The text around a match, on one line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snippet {
+    pub field: Field,
+    pub text: String,
+    /// Where the match is in `text`, in bytes.
+    pub highlight: Range<usize>,
+}
+
+/// An inverted index from each pair of adjacent characters to the posts containing it.
+///
+/// Pairs of characters find words in Japanese, which has no spaces to split on, as well as
+/// in English; posts holding every pair of a pattern are then checked for the whole of it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SearchIndex {
+    /// Indexed posts by id, `None` where a post was dropped or re-added under a new id.
+    posts: Vec<Option<IndexedPost>>,
+    /// The ids of the posts holding each pair, in ascending order.
+    grams: HashMap<String, Vec<u32>>,
+}
+
+/// What an indexed post looked like, to tell when the index is out of date.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct IndexedPost {
+    post_number: i32,
+    revision_number: i32,
+    comments_count: u32,
+    has_body: bool,
+}
+
+impl IndexedPost {
+    fn of(post: &Post, has_body: bool) -> Self {
+        Self {
+            post_number: post.post_number.to_i32(),
+            revision_number: post.revision_number,
+            comments_count: post.comments_count,
+            has_body,
+        }
+    }
+}
+
+impl SearchIndex {
+    /// Brings the index up to `posts` at their current revisions, with a body for each post
+    /// numbered in `bodies`. Only posts that are new or changed are passed to `document` to
+    /// be read and indexed again. Returns whether anything changed.
+    pub fn update(
+        &mut self,
+        posts: &[Post],
+        bodies: &HashSet<i32>,
+        mut document: impl FnMut(&Post) -> Document,
+    ) -> bool {
+        let current: HashSet<IndexedPost> = posts
+            .iter()
+            .map(|post| IndexedPost::of(post, bodies.contains(&post.post_number.to_i32())))
+            .collect();
+        let mut dropped = HashSet::new();
+        let mut kept = HashSet::new();
+        for (id, slot) in self.posts.iter_mut().enumerate() {
+            match slot {
+                Some(indexed) if current.contains(indexed) => {
+                    kept.insert(indexed.post_number);
+                }
+                Some(_) => {
+                    *slot = None;
+                    dropped.insert(id as u32);
+                }
+                None => {}
+            }
+        }
+        if !dropped.is_empty() {
+            self.grams.retain(|_, ids| {
+                ids.retain(|id| !dropped.contains(id));
+                !ids.is_empty()
+            });
+        }
+        let mut added = false;
+        for post in posts {
+            if !kept.contains(&post.post_number.to_i32()) {
+                self.add(&document(post));
+                added = true;
+            }
+        }
+        if self.posts.len() > 2 * posts.len().max(1) {
+            self.compact();
+        }
+        added || !dropped.is_empty()
+    }
+
+    /// Gives the next id to `document`, which keeps every list of ids in ascending order.
+    fn add(&mut self, document: &Document) {
+        let id = self.posts.len() as u32;
+        let mut seen = HashSet::new();
+        for field in Field::SNIPPET_ORDER {
+            seen.extend(bigrams(&document.text(field)));
+        }
+        for gram in seen {
+            self.grams.entry(gram).or_default().push(id);
+        }
+        self.posts.push(Some(IndexedPost::of(
+            &document.post,
+            document.body_md.is_some(),
+        )));
+    }
+
+    /// Renumbers the posts to close the gaps left by dropped ones. Ids keep their order, so
+    /// the lists stay sorted.
+    fn compact(&mut self) {
+        let mut ids = HashMap::new();
+        let mut posts = vec![];
+        for (id, slot) in self.posts.drain(..).enumerate() {
+            if let Some(indexed) = slot {
+                ids.insert(id as u32, posts.len() as u32);
+                posts.push(Some(indexed));
+            }
+        }
+        self.posts = posts;
+        for list in self.grams.values_mut() {
+            for id in list.iter_mut() {
+                *id = ids[id];
+            }
+        }
+    }
+
+    /// The numbers of the posts that may match, to be checked with [`rank`].
+    pub fn candidates(&self, pattern: &Pattern) -> Vec<i32> {
+        let all = || {
+            self.posts
+                .iter()
+                .flatten()
+                .map(|post| post.post_number)
+                .collect()
+        };
+        let Some(literal) = &pattern.literal else {
+            return all();
+        };
+        let mut lists = vec![];
+        for gram in bigrams(literal) {
+            match self.grams.get(&gram) {
+                Some(ids) => lists.push(ids),
+                None => return vec![],
+            }
+        }
+        let Some(shortest) = lists.iter().min_by_key(|ids| ids.len()) else {
+            return all();
+        };
+        shortest
+            .iter()
+            .filter(|&&id| lists.iter().all(|ids| ids.binary_search(&id).is_ok()))
+            .filter_map(|&id| self.posts.get(id as usize)?.as_ref())
+            .map(|post| post.post_number)
+            .collect()
+    }
+}
+
+/// Scores the documents matching `pattern`, best first, with titles weighing more than
+/// bodies; ties go to the most recently updated.
+pub fn rank(documents: impl IntoIterator<Item = Document>, pattern: &Pattern) -> Vec<SearchHit> {
+    let mut hits: Vec<SearchHit> = documents
+        .into_iter()
+        .filter_map(|document| score(document, pattern))
+        .collect();
+    hits.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| b.post.updated_at.cmp(&a.post.updated_at))
+    });
+    hits
+}
+
+fn score(document: Document, pattern: &Pattern) -> Option<SearchHit> {
+    let mut score = 0;
+    let mut snippet = None;
+    for field in Field::SNIPPET_ORDER {
+        let text = document.text(field);
+        let mut matches = pattern.regex.find_iter(&text).take(MAX_COUNTED).peekable();
+        if let Some(first) = matches.peek()
+            && snippet.is_none()
+        {
+            snippet = Some(snippet_around(field, &text, first.range()));
+        }
+        score += field.weight() * matches.count() as u32;
+    }
+    (score > 0).then_some(SearchHit {
+        post: document.post,
+        score,
+        snippet,
+    })
+}
+
+/// The line holding `range`, shortened around it with `…` where cut.
+fn snippet_around(field: Field, text: &str, range: Range<usize>) -> Snippet {
+    let line_start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[range.end..]
+        .find('\n')
+        .map_or(text.len(), |i| range.end + i);
+    let before: Vec<(usize, char)> = text[line_start..range.start].char_indices().collect();
+    let start = before
+        .len()
+        .checked_sub(SNIPPET_BEFORE)
+        .map_or(line_start, |skip| line_start + before[skip].0);
+    let end = text[range.end..line_end]
+        .char_indices()
+        .nth(SNIPPET_AFTER)
+        .map_or(line_end, |(i, _)| range.end + i);
+
+    let mut snippet = String::new();
+    if start > line_start {
+        snippet.push('…');
+    }
+    let leading = text[start..range.start].trim_start();
+    snippet.push_str(leading);
+    let highlight_start = snippet.len();
+    snippet.push_str(&text[range.clone()]);
+    let highlight = highlight_start..snippet.len();
+    snippet.push_str(text[range.end..end].trim_end());
+    if end < line_end {
+        snippet.push('…');
+    }
+    Snippet {
+        field,
+        text: snippet,
+        highlight,
+    }
+}
+
+/// Each pair of adjacent characters, lowercased, leaving out pairs with whitespace.
+fn bigrams(text: &str) -> HashSet<String> {
+    let chars: Vec<char> = text.to_lowercase().chars().collect();
+    chars
+        .windows(2)
+        .filter(|pair| !pair.iter().any(|c| c.is_whitespace()))
+        .map(|pair| pair.iter().collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::sample_post;
+    use rstest::rstest;
+
+    fn post(number: i32, name: &str) -> Post {
+        Post {
+            post_number: number.into(),
+            name: name.to_string(),
+            full_name: name.to_string(),
+            category: None,
+            tags: vec![],
+            ..sample_post()
+        }
+    }
+
+    fn document(number: i32, name: &str, body_md: &str) -> Document {
+        Document {
+            post: post(number, name),
+            body_md: Some(body_md.to_string()),
+            comments: vec!["ロールバックも書いてほしい".to_string()],
+        }
+    }
+
+    fn documents() -> Vec<Document> {
+        vec![
+            document(1, "議事録", "リリースの日程を決めた"),
+            document(2, "デプロイ手順", "Kubernetes に deploy する"),
+            document(3, "リリース手順", "デプロイしてからリリースする"),
+        ]
+    }
+
+    fn build(documents: &[Document]) -> SearchIndex {
+        let posts: Vec<Post> = documents.iter().map(|d| d.post.clone()).collect();
+        let bodies = posts.iter().map(|post| post.post_number.to_i32()).collect();
+        let mut index = SearchIndex::default();
+        index.update(&posts, &bodies, |post| {
+            documents
+                .iter()
+                .find(|d| d.post.post_number == post.post_number)
+                .unwrap()
+                .clone()
+        });
+        index
+    }
+
+    fn numbers(hits: &[SearchHit]) -> Vec<i32> {
+        hits.iter()
+            .map(|hit| hit.post.post_number.to_i32())
+            .collect()
+    }
+
+    #[rstest]
+    #[case("デプロイ", vec![2, 3])]
+    #[case("リリース", vec![1, 3])]
+    #[case("KUBERNETES", vec![2])]
+    #[case("ロールバック", vec![1, 2, 3])]
+    #[case("デプロイ手順書", vec![])]
+    #[case("d", vec![1, 2, 3])]
+    #[case("deplo[iy]", vec![1, 2, 3])]
+    fn test_candidates(#[case] pattern: &str, #[case] expected: Vec<i32>) {
+        let index = build(&documents());
+        let mut candidates = index.candidates(&Pattern::parse(pattern).unwrap());
+        candidates.sort();
+        assert_eq!(candidates, expected);
+    }
+
+    #[test]
+    fn test_rank_prefers_titles() {
+        let hits = rank(documents(), &Pattern::parse("リリース").unwrap());
+        // a match in the title outweighs one in the body
+        assert_eq!(numbers(&hits), vec![3, 1]);
+        assert_eq!(hits[0].score, 8 + 1);
+        let snippet = hits[0].snippet.as_ref().unwrap();
+        assert_eq!(snippet.field, Field::Body);
+        assert_eq!(&snippet.text[snippet.highlight.clone()], "リリース");
+    }
+
+    #[test]
+    fn test_rank_regex() {
+        let hits = rank(
+            documents(),
+            &Pattern::parse(r"deploy|デプロイ\S+て").unwrap(),
+        );
+        assert_eq!(numbers(&hits), vec![2, 3]);
+        let snippet = hits[1].snippet.as_ref().unwrap();
+        assert_eq!(&snippet.text[snippet.highlight.clone()], "デプロイして");
+    }
+
+    #[test]
+    fn test_snippet_is_cut_around_the_match() {
+        let text = format!(
+            "前の行\n{}見つけた{}\n次の行",
+            "あ".repeat(40),
+            "い".repeat(70)
+        );
+        let start = text.find("見つけた").unwrap();
+        let snippet = snippet_around(Field::Body, &text, start..start + "見つけた".len());
+        assert_eq!(
+            snippet.text,
+            format!("…{}見つけた{}…", "あ".repeat(30), "い".repeat(60))
+        );
+        assert_eq!(&snippet.text[snippet.highlight], "見つけた");
+    }
+
+    #[test]
+    fn test_update() {
+        let mut index = build(&documents());
+        let mut posts: Vec<Post> = documents().into_iter().map(|d| d.post).collect();
+        let bodies = HashSet::from([1, 2, 3, 4]);
+        let candidates = |index: &SearchIndex, pattern: &str| {
+            let mut candidates = index.candidates(&Pattern::parse(pattern).unwrap());
+            candidates.sort();
+            candidates
+        };
+        assert!(!index.update(&posts, &bodies, |_| unreachable!("nothing changed")));
+
+        // post 1 edited, post 2 deleted and post 4 added
+        posts[0].revision_number += 1;
+        posts.remove(1);
+        posts.push(post(4, "デプロイの振り返り"));
+        let mut read = vec![];
+        assert!(index.update(&posts, &bodies, |post| {
+            read.push(post.post_number.to_i32());
+            let body_md = match post.post_number.to_i32() {
+                1 => "ロールバックした",
+                _ => "問題なし",
+            };
+            Document {
+                post: post.clone(),
+                ..document(0, "", body_md)
+            }
+        }));
+        assert_eq!(read, vec![1, 4]);
+        assert_eq!(candidates(&index, "デプロイ"), vec![3, 4]);
+        assert_eq!(candidates(&index, "リリース"), vec![3]);
+        assert_eq!(candidates(&index, "Kubernetes"), Vec::<i32>::new());
+        assert_eq!(candidates(&index, "d"), vec![1, 3, 4]);
+        assert!(!index.update(&posts, &bodies, |_| unreachable!("nothing changed")));
+
+        // dropped ids are closed up once they outnumber the posts
+        for _ in 0..2 {
+            posts[2].revision_number += 1;
+            index.update(&posts, &bodies, |post| Document {
+                post: post.clone(),
+                ..document(0, "", "問題なし")
+            });
+        }
+        assert!(index.posts.iter().all(Option::is_some));
+        assert_eq!(candidates(&index, "デプロイ"), vec![3, 4]);
+        assert_eq!(candidates(&index, "問題"), vec![4]);
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+const MAX_RECENTLY_VIEWED: usize = 50;
+
+/// Data remembered across sessions, stored in `state.toml` next to the config file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct State {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recently_viewed: Vec<RecentlyViewed>,
+    /// The post list share last chosen with the keys or the mouse, over the configured one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list_percent: Option<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct RecentlyViewed {
+    pub team_name: String,
+    pub post_number: i32,
+    pub full_name: String,
+    #[serde(default)]
+    pub scroll: u16,
+}
+
+impl State {
+    /// Moves the post to the top of the recently viewed list, keeping its latest scroll position.
+    pub fn record_view(&mut self, viewed: RecentlyViewed) {
+        self.recently_viewed
+            .retain(|v| !(v.team_name == viewed.team_name && v.post_number == viewed.post_number));
+        self.recently_viewed.insert(0, viewed);
+        self.recently_viewed.truncate(MAX_RECENTLY_VIEWED);
+    }
+
+    pub fn recently_viewed_in(&self, team_name: &str) -> Vec<RecentlyViewed> {
+        self.recently_viewed
+            .iter()
+            .filter(|v| v.team_name == team_name)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewed(post_number: i32, scroll: u16) -> RecentlyViewed {
+        RecentlyViewed {
+            team_name: "my_team".to_string(),
+            post_number,
+            full_name: format!("post {}", post_number),
+            scroll,
+        }
+    }
+
+    #[test]
+    fn test_record_view_moves_post_to_top() {
+        let mut state = State::default();
+        state.record_view(viewed(1, 0));
+        state.record_view(viewed(2, 0));
+        state.record_view(viewed(1, 10));
+        assert_eq!(state.recently_viewed, vec![viewed(1, 10), viewed(2, 0)]);
+    }
+}
@@ -0,0 +1,132 @@
+/// A `- [ ]` / `- [x]` item of a markdown task list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskItem {
+    /// Line index within the body.
+    pub line: usize,
+    pub done: bool,
+    pub text: String,
+}
+
+/// Lists the task items of `body_md`, skipping fenced code blocks.
+pub fn task_items(body_md: &str) -> Vec<TaskItem> {
+    let mut in_fence = false;
+    body_md
+        .lines()
+        .enumerate()
+        .filter_map(|(line, content)| {
+            if content.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+                return None;
+            }
+            if in_fence {
+                return None;
+            }
+            let (_, marker, text) = split_task(content)?;
+            Some(TaskItem {
+                line,
+                done: marker != ' ',
+                text: text.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Flips the checkbox of the task on `line`, keeping the rest of the body byte for byte.
+pub fn toggle_task(body_md: &str, line: usize) -> Option<String> {
+    let mut toggled = false;
+    let body = body_md
+        .split_inclusive('\n')
+        .enumerate()
+        .map(|(i, content)| {
+            if i != line {
+                return content.to_string();
+            }
+            match split_task(content) {
+                Some((prefix, marker, rest)) => {
+                    toggled = true;
+                    let marker = if marker == ' ' { 'x' } else { ' ' };
+                    format!("{}[{}]{}", prefix, marker, rest)
+                }
+                None => content.to_string(),
+            }
+        })
+        .collect();
+    toggled.then_some(body)
+}
+
+/// Splits `  - [x] text` into the list prefix, the checkbox marker and the text after `]`.
+fn split_task(line: &str) -> Option<(&str, char, &str)> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let bullet_len = if rest.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        if digits == 0 || !rest[digits..].starts_with(['.', ')']) {
+            return None;
+        }
+        digits + 1
+    };
+    let after_bullet = rest[bullet_len..].strip_prefix(' ')?;
+    let prefix_len = line.len() - after_bullet.len();
+    let mut chars = after_bullet.chars();
+    let (Some('['), Some(marker), Some(']')) = (chars.next(), chars.next(), chars.next()) else {
+        return None;
+    };
+    if !matches!(marker, ' ' | 'x' | 'X') {
+        return None;
+    }
+    let text = &after_bullet[3..];
+    if !(text.is_empty() || text.starts_with(char::is_whitespace)) {
+        return None;
+    }
+    Some((&line[..prefix_len], marker, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const BODY: &str = "# TODO\n- [ ] write report\n  - [x] nested\n1. [X] numbered\n- [link](https://example.com)\n```\n- [ ] in code\n```\n";
+
+    #[test]
+    fn test_task_items() {
+        let items = task_items(BODY);
+        let summary: Vec<_> = items
+            .iter()
+            .map(|item| (item.line, item.done, item.text.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, false, "write report"),
+                (2, true, "nested"),
+                (3, true, "numbered")
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(1, Some("- [x] write report\n"))]
+    #[case(2, Some("  - [ ] nested\n"))]
+    #[case(3, Some("1. [ ] numbered\n"))]
+    #[case(4, None)]
+    fn test_toggle_task(#[case] line: usize, #[case] expected_line: Option<&str>) {
+        let toggled = toggle_task(BODY, line);
+        let toggled_line = toggled
+            .as_deref()
+            .map(|body| body.split_inclusive('\n').nth(line).unwrap());
+        assert_eq!(toggled_line, expected_line);
+        if let Some(body) = toggled {
+            let others = |body: &str| {
+                body.split_inclusive('\n')
+                    .enumerate()
+                    .filter(|(i, _)| *i != line)
+                    .map(|(_, l)| l.to_string())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(others(&body), others(BODY));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+
+/// How post times are shown: in which time zone, and whether relative to now.
+#[derive(Clone, Debug)]
+pub struct TimeDisplay {
+    zone: Zone,
+    relative: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Zone {
+    Local,
+    Named(Tz),
+}
+
+impl TimeDisplay {
+    /// `timezone` is an IANA name such as `Asia/Tokyo`; without one, or with an unknown one,
+    /// the system's zone is used.
+    pub fn new(timezone: Option<&str>, relative: bool) -> Self {
+        let zone = match timezone.map(str::parse::<Tz>) {
+            Some(Ok(tz)) => Zone::Named(tz),
+            Some(Err(e)) => {
+                eprintln!("failed to parse timezone: {}", e);
+                Zone::Local
+            }
+            None => Zone::Local,
+        };
+        Self { zone, relative }
+    }
+
+    /// Relative or absolute, as configured.
+    pub fn format(&self, time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+        if self.relative {
+            self.relative(time, now)
+        } else {
+            self.absolute(time)
+        }
+    }
+
+    pub fn absolute(&self, time: DateTime<Utc>) -> String {
+        self.local(time).format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    /// How long ago `time` was, e.g. `5m ago` or `昨日 18:30`, falling back to the date
+    /// after a week.
+    pub fn relative(&self, time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+        let elapsed = now.signed_duration_since(time);
+        let (local, local_now) = (self.local(time), self.local(now));
+        let days = local_now
+            .date()
+            .signed_duration_since(local.date())
+            .num_days();
+        if elapsed.num_minutes() < 1 {
+            "just now".to_string()
+        } else if elapsed.num_hours() < 1 {
+            format!("{}m ago", elapsed.num_minutes())
+        } else if days == 0 {
+            format!("{}h ago", elapsed.num_hours())
+        } else if days == 1 {
+            local.format("昨日 %H:%M").to_string()
+        } else if days < 7 {
+            format!("{}d ago", days)
+        } else {
+            local.format("%Y-%m-%d").to_string()
+        }
+    }
+
+    fn local(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        match self.zone {
+            Zone::Local => time.with_timezone(&Local).naive_local(),
+            Zone::Named(tz) => time.with_timezone(&tz).naive_local(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(30, "just now")]
+    #[case(5 * 60, "5m ago")]
+    #[case(3 * 3600 + 59, "3h ago")]
+    // now is 21:00 in Tokyo, so 22 hours before is the previous day there
+    #[case(22 * 3600, "昨日 23:00")]
+    #[case(2 * 86400, "2d ago")]
+    #[case(40 * 86400, "2026-03-22")]
+    fn test_relative(#[case] seconds_ago: i64, #[case] expected: &str) {
+        let display = TimeDisplay::new(Some("Asia/Tokyo"), true);
+        let now = Utc.with_ymd_and_hms(2026, 5, 1, 12, 0, 0).unwrap();
+        let time = now - chrono::Duration::seconds(seconds_ago);
+        assert_eq!(display.format(time, now), expected);
+    }
+
+    #[test]
+    fn test_absolute_in_timezone() {
+        let display = TimeDisplay::new(Some("Asia/Tokyo"), false);
+        let time = Utc.with_ymd_and_hms(2026, 5, 1, 20, 30, 0).unwrap();
+        assert_eq!(display.format(time, time), "2026-05-02 05:30");
+    }
+}
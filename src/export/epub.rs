@@ -0,0 +1,476 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::{BufWriter, Seek, Write},
+    path::Path,
+};
+
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use super::html::{self, HtmlRenderer, escape};
+use crate::domains::Post;
+
+/// Image types EPUB readers display as they are.
+const IMAGE_TYPES: [&str; 5] = [
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/svg+xml",
+    "image/webp",
+];
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles>
+</container>
+"#;
+
+/// Leaves colors to the reader instead of the theme, and wraps code since readers can't
+/// scroll it sideways.
+const STYLE: &str = "body { line-height: 1.7; }
+.post-header { border-bottom: 1px solid #999999; margin-bottom: 1em; }
+.category, .meta { color: #666666; font-size: 0.85em; }
+.tags { list-style: none; padding: 0; }
+.tags li { display: inline; margin-right: 0.6em; }
+.wip { font-size: 0.7em; border: 1px solid #999999; padding: 0 0.3em; }
+pre { white-space: pre-wrap; font-size: 0.85em; border: 1px solid #CCCCCC; padding: 0.5em; }
+.code-name { color: #666666; font-size: 0.8em; }
+blockquote { margin-left: 0; padding-left: 1em; border-left: 3px solid #CCCCCC; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #999999; padding: 0.2em 0.5em; }
+img { max-width: 100%; }
+li.task { list-style: none; }
+";
+
+/// Writes the posts and their bodies to `path` as one EPUB, in category and number order. The
+/// table of contents follows the category tree, and only cached images go in the book.
+pub fn write(
+    path: &Path,
+    title: &str,
+    posts: Vec<(Post, String)>,
+    renderer: &HtmlRenderer,
+) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent()
+        && !dir.as_os_str().is_empty()
+    {
+        fs::create_dir_all(dir)?;
+    }
+    let file = BufWriter::new(File::create(path)?);
+    build(file, title, posts, renderer)?;
+    Ok(())
+}
+
+fn build<W: Write + Seek>(
+    out: W,
+    title: &str,
+    mut posts: Vec<(Post, String)>,
+    renderer: &HtmlRenderer,
+) -> anyhow::Result<W> {
+    if posts.is_empty() {
+        anyhow::bail!("no posts to put in the book");
+    }
+    posts.sort_by_key(|(post, _)| order(post));
+    let images = Images::new(renderer);
+    let mut zip = ZipWriter::new(out);
+    let mut add = |name: &str, method, data: &[u8]| -> anyhow::Result<()> {
+        zip.start_file(
+            name,
+            SimpleFileOptions::default().compression_method(method),
+        )?;
+        zip.write_all(data)?;
+        Ok(())
+    };
+    // readers look for the type, uncompressed, at the very start of the file
+    add(
+        "mimetype",
+        CompressionMethod::Stored,
+        b"application/epub+zip",
+    )?;
+    let deflated = CompressionMethod::Deflated;
+    add("META-INF/container.xml", deflated, CONTAINER.as_bytes())?;
+    add("OEBPS/style.css", deflated, STYLE.as_bytes())?;
+    for (post, body_md) in &posts {
+        let chapter = chapter(post, body_md, renderer, &images);
+        add(
+            &format!("OEBPS/{}", chapter_href(post)),
+            deflated,
+            chapter.as_bytes(),
+        )?;
+    }
+    let images = images.added.into_inner();
+    for image in &images {
+        // images are compressed already
+        add(
+            &format!("OEBPS/{}", image.href),
+            CompressionMethod::Stored,
+            &image.bytes,
+        )?;
+    }
+    let id = book_id(title, &posts);
+    let toc = TocNode::build(&posts);
+    add("OEBPS/nav.xhtml", deflated, nav(title, &toc).as_bytes())?;
+    add("OEBPS/toc.ncx", deflated, ncx(&id, title, &toc).as_bytes())?;
+    add(
+        "OEBPS/content.opf",
+        deflated,
+        package(&id, title, &posts, &images).as_bytes(),
+    )?;
+    Ok(zip.finish()?)
+}
+
+/// By category path, then number. Posts without a category come first.
+fn order(post: &Post) -> (Vec<String>, i32) {
+    let segments = post
+        .category
+        .iter()
+        .flat_map(|category| category.segments())
+        .map(str::to_string)
+        .collect();
+    (segments, post.post_number.to_i32())
+}
+
+fn chapter_href(post: &Post) -> String {
+    format!("posts/{}.xhtml", post.post_number.to_i32())
+}
+
+fn chapter(post: &Post, body_md: &str, renderer: &HtmlRenderer, images: &Images) -> String {
+    let body = html::to_xhtml(body_md, &|url| {
+        images.add(url).map(|href| format!("../{}", href))
+    });
+    xhtml(
+        &post.full_name,
+        "../style.css",
+        &format!(
+            "{}<article>\n{}</article>\n",
+            renderer.post_header(post, None),
+            body
+        ),
+    )
+}
+
+fn xhtml(title: &str, style: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" xml:lang=\"ja\" lang=\"ja\">\n<head>\n<meta charset=\"utf-8\" />\n<title>{}</title>\n<link rel=\"stylesheet\" type=\"text/css\" href=\"{}\" />\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        style,
+        body
+    )
+}
+
+struct Image {
+    href: String,
+    media_type: &'static str,
+    bytes: Vec<u8>,
+}
+
+/// Images found while writing the chapters, each added once.
+struct Images<'a> {
+    renderer: &'a HtmlRenderer,
+    /// The path in the book for each URL, `None` when it isn't cached.
+    hrefs: RefCell<HashMap<String, Option<String>>>,
+    added: RefCell<Vec<Image>>,
+}
+
+impl<'a> Images<'a> {
+    fn new(renderer: &'a HtmlRenderer) -> Self {
+        Self {
+            renderer,
+            hrefs: RefCell::new(HashMap::new()),
+            added: RefCell::new(vec![]),
+        }
+    }
+
+    /// Adds a cached image to the book and returns its path.
+    fn add(&self, url: &str) -> Option<String> {
+        if let Some(href) = self.hrefs.borrow().get(url) {
+            return href.clone();
+        }
+        let href = self.renderer.cached_image(url).and_then(|bytes| {
+            let media_type = html::image_mime(&bytes).filter(|mime| IMAGE_TYPES.contains(mime))?;
+            let mut added = self.added.borrow_mut();
+            let extension = match media_type {
+                "image/jpeg" => "jpg",
+                "image/svg+xml" => "svg",
+                other => other.trim_start_matches("image/"),
+            };
+            let href = format!("images/{}.{}", added.len() + 1, extension);
+            added.push(Image {
+                href: href.clone(),
+                media_type,
+                bytes,
+            });
+            Some(href)
+        });
+        self.hrefs
+            .borrow_mut()
+            .insert(url.to_string(), href.clone());
+        href
+    }
+}
+
+/// One level of the table of contents. Posts come before subcategories, which are sorted by
+/// name, matching the order of the book.
+#[derive(Default)]
+struct TocNode {
+    posts: Vec<(String, String)>,
+    children: BTreeMap<String, TocNode>,
+}
+
+impl TocNode {
+    fn build(posts: &[(Post, String)]) -> Self {
+        let mut root = Self::default();
+        for (post, _) in posts {
+            let mut node = &mut root;
+            for segment in post
+                .category
+                .iter()
+                .flat_map(|category| category.segments())
+            {
+                node = node.children.entry(segment.to_string()).or_default();
+            }
+            node.posts.push((post.name.clone(), chapter_href(post)));
+        }
+        root
+    }
+
+    /// The first post under this level, opened when it's picked in the table of contents.
+    fn first_href(&self) -> &str {
+        match self.posts.first() {
+            Some((_, href)) => href,
+            None => self
+                .children
+                .values()
+                .next()
+                .map_or("", |child| child.first_href()),
+        }
+    }
+}
+
+fn nav(title: &str, toc: &TocNode) -> String {
+    let mut body = format!(
+        "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n",
+        escape(title)
+    );
+    nav_list(toc, &mut body);
+    body.push_str("</nav>\n");
+    xhtml(title, "style.css", &body)
+}
+
+fn nav_list(node: &TocNode, out: &mut String) {
+    out.push_str("<ol>\n");
+    for (name, href) in &node.posts {
+        out.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            href,
+            escape(name)
+        ));
+    }
+    for (name, child) in &node.children {
+        out.push_str(&format!("<li><span>{}</span>\n", escape(name)));
+        nav_list(child, out);
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ol>\n");
+}
+
+/// The table of contents for EPUB 2 readers.
+fn ncx(id: &str, title: &str, toc: &TocNode) -> String {
+    let mut points = String::new();
+    nav_points(toc, &mut 0, &mut points);
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<ncx xmlns=\"http://www.daisy.org/z3986/2005/ncx/\" version=\"2005-1\">\n<head>\n<meta name=\"dtb:uid\" content=\"{}\" />\n</head>\n<docTitle><text>{}</text></docTitle>\n<navMap>\n{}</navMap>\n</ncx>\n",
+        escape(id),
+        escape(title),
+        points
+    )
+}
+
+fn nav_points(node: &TocNode, play_order: &mut usize, out: &mut String) {
+    for (name, href) in &node.posts {
+        nav_point(name, href, play_order, out);
+        out.push_str("</navPoint>\n");
+    }
+    for (name, child) in &node.children {
+        nav_point(name, child.first_href(), play_order, out);
+        out.push('\n');
+        nav_points(child, play_order, out);
+        out.push_str("</navPoint>\n");
+    }
+}
+
+/// Opens a `navPoint`, which is closed after the levels below it are written.
+fn nav_point(label: &str, href: &str, play_order: &mut usize, out: &mut String) {
+    *play_order += 1;
+    out.push_str(&format!(
+        "<navPoint id=\"nav-{0}\" playOrder=\"{0}\"><navLabel><text>{1}</text></navLabel><content src=\"{2}\" />",
+        play_order,
+        escape(label),
+        href
+    ));
+}
+
+fn package(id: &str, title: &str, posts: &[(Post, String)], images: &[Image]) -> String {
+    let modified = posts
+        .iter()
+        .map(|(post, _)| post.updated_at)
+        .max()
+        .unwrap_or_default();
+    let mut manifest = String::from(
+        "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\" />\n\
+        <item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\" />\n\
+        <item id=\"style\" href=\"style.css\" media-type=\"text/css\" />\n",
+    );
+    let mut spine = String::from("<itemref idref=\"nav\" />\n");
+    for (post, _) in posts {
+        let number = post.post_number.to_i32();
+        manifest.push_str(&format!(
+            "<item id=\"post-{}\" href=\"{}\" media-type=\"application/xhtml+xml\" />\n",
+            number,
+            chapter_href(post)
+        ));
+        spine.push_str(&format!("<itemref idref=\"post-{}\" />\n", number));
+    }
+    for (i, image) in images.iter().enumerate() {
+        manifest.push_str(&format!(
+            "<item id=\"image-{}\" href=\"{}\" media-type=\"{}\" />\n",
+            i + 1,
+            image.href,
+            image.media_type
+        ));
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\" xml:lang=\"ja\">\n<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n<dc:identifier id=\"book-id\">{}</dc:identifier>\n<dc:title>{}</dc:title>\n<dc:language>ja</dc:language>\n<meta property=\"dcterms:modified\">{}</meta>\n</metadata>\n<manifest>\n{}</manifest>\n<spine toc=\"ncx\">\n{}</spine>\n</package>\n",
+        escape(id),
+        escape(title),
+        modified.format("%Y-%m-%dT%H:%M:%SZ"),
+        manifest,
+        spine
+    )
+}
+
+/// Built from the team and title, so re-exporting the same view gives the same book.
+fn book_id(title: &str, posts: &[(Post, String)]) -> String {
+    let team = posts
+        .first()
+        .and_then(|(post, _)| post.url.host_str().map(str::to_string))
+        .unwrap_or_default();
+    format!("esa-reader:{}:{}", team, title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domains::{Category, PostNumber, Theme, ThemeConfig, TimeDisplay, sample_post},
+        image_cache::ImageCache,
+    };
+    use insta::assert_snapshot;
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+
+    fn post(number: i32, category: Option<&str>, name: &str) -> (Post, String) {
+        let post = Post {
+            post_number: PostNumber::from(number),
+            name: name.to_string(),
+            full_name: match category {
+                Some(category) => format!("{}/{}", category, name),
+                None => name.to_string(),
+            },
+            category: category.and_then(Category::parse),
+            url: format!("https://my_team.esa.io/posts/{}", number)
+                .parse()
+                .unwrap(),
+            ..sample_post()
+        };
+        (
+            post,
+            format!("# {}\n\n![図](https://files.esa.io/{}.gif)\n", name, number),
+        )
+    }
+
+    fn posts() -> Vec<(Post, String)> {
+        vec![
+            post(12, Some("onboarding/tools"), "エディタ"),
+            post(3, Some("onboarding"), "はじめに"),
+            post(40, None, "用語集"),
+            post(5, Some("onboarding/tools"), "Git"),
+            post(7, Some("onboarding"), "チーム紹介"),
+        ]
+    }
+
+    #[test]
+    fn test_toc() {
+        let mut posts = posts();
+        posts.sort_by_key(|(post, _)| order(post));
+        let numbers: Vec<i32> = posts
+            .iter()
+            .map(|(post, _)| post.post_number.to_i32())
+            .collect();
+        assert_eq!(numbers, [40, 3, 7, 5, 12]);
+
+        let toc = TocNode::build(&posts);
+        let mut nav = String::new();
+        nav_list(&toc, &mut nav);
+        assert_snapshot!(nav);
+    }
+
+    #[test]
+    fn test_book() {
+        let dir = tempfile::tempdir().unwrap();
+        let images = ImageCache::next_to(&dir.path().join("config.toml"), 100);
+        // only the first post's image was shown in the reader
+        images
+            .store(
+                "https://files.esa.io/3.gif",
+                b"GIF89a\x01\x00\x01\x00\x00\x00\x00;",
+            )
+            .unwrap();
+        let renderer = HtmlRenderer::new(
+            Theme::from_config(&ThemeConfig::default()),
+            TimeDisplay::new(Some("Asia/Tokyo"), false),
+            images,
+        );
+        let out = build(Cursor::new(vec![]), "オンボーディング", posts(), &renderer).unwrap();
+
+        let mut archive = ZipArchive::new(out).unwrap();
+        let names: Vec<&str> = archive.file_names().collect();
+        assert_eq!(
+            names,
+            [
+                "mimetype",
+                "META-INF/container.xml",
+                "OEBPS/style.css",
+                "OEBPS/posts/40.xhtml",
+                "OEBPS/posts/3.xhtml",
+                "OEBPS/posts/7.xhtml",
+                "OEBPS/posts/5.xhtml",
+                "OEBPS/posts/12.xhtml",
+                "OEBPS/images/1.gif",
+                "OEBPS/nav.xhtml",
+                "OEBPS/toc.ncx",
+                "OEBPS/content.opf",
+            ]
+        );
+        let mimetype = archive.by_index(0).unwrap();
+        assert_eq!(mimetype.compression(), CompressionMethod::Stored);
+        drop(mimetype);
+        let mut text = |name: &str| {
+            let mut text = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut text)
+                .unwrap();
+            text
+        };
+        let first = text("OEBPS/posts/3.xhtml");
+        assert!(first.contains("<img src=\"../images/1.gif\" alt=\"図\" />"));
+        let missing = text("OEBPS/posts/7.xhtml");
+        assert!(missing.contains("<a href=\"https://files.esa.io/7.gif\">図</a>"));
+        assert_snapshot!("content_opf", text("OEBPS/content.opf"));
+        assert_snapshot!("toc_ncx", text("OEBPS/toc.ncx"));
+    }
+}
@@ -0,0 +1,888 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+static HEADING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(#{1,6})(?:[ \t]+(.*?))?(?:[ \t]+#+)?[ \t]*$").expect("valid regex")
+});
+static LIST_MARKER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^( *)([-*+]|(\d{1,9})[.)])( +|$)").expect("valid regex"));
+static HTML_BLOCK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:<!--|</?[A-Za-z][A-Za-z0-9-]*(?:\s|/?>|$))").expect("valid regex")
+});
+static INLINE_HTML: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^(?:<!--[\s\S]*?-->|</?[A-Za-z][A-Za-z0-9-]*(?:\s+[A-Za-z_:][\w.:-]*(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'=<>`]+))?)*\s*/?>)"#)
+        .expect("valid regex")
+});
+static IMG_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?i)<img(?:\s+[A-Za-z_:][\w.:-]*(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'=<>`]+))?)*\s*/?>"#,
+    )
+    .expect("valid regex")
+});
+static ATTRIBUTE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\s([A-Za-z_:][\w.:-]*)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#)
+        .expect("valid regex")
+});
+static AUTOLINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^<(https?://[^\s<>]+)>").expect("valid regex"));
+static BARE_URL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^https?://[^\s<]+").expect("valid regex"));
+static SHORTCODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^:([a-z0-9_+-]+):").expect("valid regex"));
+
+/// Renders a post body as HTML. `image_src` gives the `src` of each image from its URL;
+/// images it has none for are linked instead.
+///
+/// Covers what esa posts use: headings, paragraphs where each line break is kept, lists
+/// with task items, quotes, tables, fenced code, raw HTML, links, images, emphasis and
+/// emoji shortcodes.
+pub fn to_html(body_md: &str, image_src: &dyn Fn(&str) -> Option<String>) -> String {
+    render(body_md, image_src, false)
+}
+
+/// Like [`to_html`], but well-formed XML for EPUB. Raw HTML can't be trusted to be, so
+/// its tags are dropped and only its text is kept, other than images and line breaks.
+pub fn to_xhtml(body_md: &str, image_src: &dyn Fn(&str) -> Option<String>) -> String {
+    render(body_md, image_src, true)
+}
+
+fn render(body_md: &str, image_src: &dyn Fn(&str) -> Option<String>, xhtml: bool) -> String {
+    let lines: Vec<String> = body_md.lines().map(expand_leading_tabs).collect();
+    let mut out = String::new();
+    Renderer { image_src, xhtml }.blocks(&lines, &mut out);
+    out
+}
+
+struct Renderer<'a> {
+    image_src: &'a dyn Fn(&str) -> Option<String>,
+    xhtml: bool,
+}
+
+impl Renderer<'_> {
+    /// An element without content, such as `<br>`, closed as XHTML needs it.
+    fn void(&self, tag: &str) -> String {
+        if self.xhtml {
+            format!("<{} />", tag)
+        } else {
+            format!("<{}>", tag)
+        }
+    }
+
+    fn blocks(&self, lines: &[String], out: &mut String) {
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i].as_str();
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() {
+                i += 1;
+            } else if fence(trimmed).is_some() {
+                i = self.code_block(lines, i, out);
+            } else if let Some((level, text)) = heading(trimmed) {
+                out.push_str(&format!("<h{0}>{1}</h{0}>\n", level, self.inline(text)));
+                i += 1;
+            } else if is_rule(trimmed) {
+                out.push_str(&self.void("hr"));
+                out.push('\n');
+                i += 1;
+            } else if trimmed.starts_with('>') {
+                let quoted: Vec<String> = lines[i..]
+                    .iter()
+                    .map(|line| line.trim_start())
+                    .take_while(|line| line.starts_with('>'))
+                    .map(|line| {
+                        let line = &line[1..];
+                        line.strip_prefix(' ').unwrap_or(line).to_string()
+                    })
+                    .collect();
+                i += quoted.len();
+                out.push_str("<blockquote>\n");
+                self.blocks(&quoted, out);
+                out.push_str("</blockquote>\n");
+            } else if list_marker(line).is_some() {
+                i = self.list(lines, i, out);
+            } else if i + 1 < lines.len() && line.contains('|') && is_delimiter_row(&lines[i + 1]) {
+                i = self.table(lines, i, out);
+            } else if HTML_BLOCK.is_match(trimmed) {
+                let mut text = vec![];
+                while i < lines.len() && !lines[i].trim().is_empty() {
+                    if self.xhtml {
+                        let line = self.inline(lines[i].trim());
+                        if !line.is_empty() {
+                            text.push(line);
+                        }
+                    } else {
+                        let line = IMG_TAG.replace_all(&lines[i], |caps: &regex::Captures| {
+                            self.image_tag(&caps[0])
+                        });
+                        out.push_str(&line);
+                        out.push('\n');
+                    }
+                    i += 1;
+                }
+                if !text.is_empty() {
+                    out.push_str(&format!("<p>{}</p>\n", text.join("<br />\n")));
+                }
+            } else {
+                i = self.paragraph(lines, i, out);
+            }
+        }
+    }
+
+    fn paragraph(&self, lines: &[String], start: usize, out: &mut String) -> usize {
+        let mut end = start + 1;
+        while end < lines.len() && !lines[end].trim().is_empty() && !starts_block(&lines[end]) {
+            end += 1;
+        }
+        let text = lines[start..end]
+            .iter()
+            .map(|line| line.trim())
+            .collect::<Vec<_>>()
+            .join("\n");
+        // `Title` over `===` or `---` is a heading
+        if end < lines.len() {
+            let underline = lines[end].trim();
+            let level = if !underline.is_empty() && underline.chars().all(|c| c == '=') {
+                Some(1)
+            } else if underline.len() >= 2 && underline.chars().all(|c| c == '-') {
+                Some(2)
+            } else {
+                None
+            };
+            if let Some(level) = level {
+                out.push_str(&format!("<h{0}>{1}</h{0}>\n", level, self.inline(&text)));
+                return end + 1;
+            }
+        }
+        out.push_str(&format!("<p>{}</p>\n", self.inline(&text)));
+        end
+    }
+
+    fn code_block(&self, lines: &[String], start: usize, out: &mut String) -> usize {
+        let (marker, info) = fence(lines[start].trim_start()).expect("starts with a fence");
+        let mut end = start + 1;
+        while end < lines.len() {
+            let line = lines[end].trim();
+            if line.starts_with(marker) && line.chars().all(|c| marker.starts_with(c)) {
+                break;
+            }
+            end += 1;
+        }
+        // esa writes the file name after the language, as in `rust:main.rs`
+        let (language, file_name) = info.split_once(':').unwrap_or((info, ""));
+        if !file_name.is_empty() {
+            out.push_str(&format!(
+                "<div class=\"code-name\">{}</div>\n",
+                escape(file_name)
+            ));
+        }
+        if language.is_empty() {
+            out.push_str("<pre><code>");
+        } else {
+            out.push_str(&format!(
+                "<pre><code class=\"language-{}\">",
+                escape(language)
+            ));
+        }
+        for line in &lines[start + 1..end] {
+            out.push_str(&escape(line));
+            out.push('\n');
+        }
+        out.push_str("</code></pre>\n");
+        end + 1
+    }
+
+    /// A list starting at `start`, with the lines indented past a marker as the content of
+    /// its item. Returns the line after the list.
+    fn list(&self, lines: &[String], start: usize, out: &mut String) -> usize {
+        let first = list_marker(&lines[start]).expect("starts with a marker");
+        match first.start {
+            Some(1) => out.push_str("<ol>\n"),
+            Some(n) => out.push_str(&format!("<ol start=\"{}\">\n", n)),
+            None => out.push_str("<ul>\n"),
+        }
+        let mut i = start;
+        while let Some(marker) = lines.get(i).and_then(|line| list_marker(line)) {
+            if marker.indent >= first.indent + 2 || marker.start.is_some() != first.start.is_some()
+            {
+                break;
+            }
+            let mut item = vec![lines[i][marker.content..].to_string()];
+            i += 1;
+            while i < lines.len() {
+                let line = &lines[i];
+                let indent = line.len() - line.trim_start().len();
+                if line.trim().is_empty() {
+                    // a blank line ends the item unless indented lines follow it
+                    let next = lines[i..].iter().find(|line| !line.trim().is_empty());
+                    match next {
+                        Some(next) if next.len() - next.trim_start().len() >= first.indent + 2 => {
+                            item.push(String::new());
+                            i += 1;
+                        }
+                        _ => break,
+                    }
+                } else if indent >= first.indent + 2 {
+                    item.push(line[indent.min(marker.content)..].to_string());
+                    i += 1;
+                } else if list_marker(line).is_none() && !starts_block(line) {
+                    item.push(line.trim_start().to_string());
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            self.list_item(&item, out);
+            // blank lines between items keep the list going
+            let next = lines[i..].iter().position(|line| !line.trim().is_empty());
+            match next {
+                Some(skip)
+                    if list_marker(&lines[i + skip]).is_some_and(|next| {
+                        next.indent < first.indent + 2
+                            && next.start.is_some() == first.start.is_some()
+                    }) =>
+                {
+                    i += skip
+                }
+                _ => break,
+            }
+        }
+        out.push_str(if first.start.is_some() {
+            "</ol>\n"
+        } else {
+            "</ul>\n"
+        });
+        i
+    }
+
+    /// Items are drawn tight: the first lines as text, and what follows as blocks.
+    fn list_item(&self, item: &[String], out: &mut String) {
+        let text_end = item
+            .iter()
+            .skip(1)
+            .position(|line| line.trim().is_empty() || starts_block(line))
+            .map_or(item.len(), |position| position + 1);
+        let text = item[..text_end]
+            .iter()
+            .map(|line| line.trim())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let task = [("[ ] ", false), ("[x] ", true), ("[X] ", true)]
+            .into_iter()
+            .find_map(|(prefix, done)| Some((text.strip_prefix(prefix)?, done)));
+        match task {
+            Some((text, done)) => {
+                let checkbox = match (self.xhtml, done) {
+                    (false, false) => "input type=\"checkbox\" disabled",
+                    (false, true) => "input type=\"checkbox\" disabled checked",
+                    (true, false) => "input type=\"checkbox\" disabled=\"disabled\"",
+                    (true, true) => {
+                        "input type=\"checkbox\" disabled=\"disabled\" checked=\"checked\""
+                    }
+                };
+                out.push_str(&format!(
+                    "<li class=\"task\">{} {}",
+                    self.void(checkbox),
+                    self.inline(text)
+                ))
+            }
+            None => out.push_str(&format!("<li>{}", self.inline(&text))),
+        }
+        if text_end < item.len() {
+            out.push('\n');
+            self.blocks(&item[text_end..], out);
+        }
+        out.push_str("</li>\n");
+    }
+
+    fn table(&self, lines: &[String], start: usize, out: &mut String) -> usize {
+        let aligns: Vec<&str> = cells(&lines[start + 1])
+            .iter()
+            .map(|cell| match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => " style=\"text-align: center\"",
+                (false, true) => " style=\"text-align: right\"",
+                (true, false) => " style=\"text-align: left\"",
+                (false, false) => "",
+            })
+            .collect();
+        out.push_str("<table>\n<thead>\n");
+        self.table_row(&lines[start], "th", &aligns, out);
+        out.push_str("</thead>\n<tbody>\n");
+        let mut i = start + 2;
+        while i < lines.len() && lines[i].contains('|') && !lines[i].trim().is_empty() {
+            self.table_row(&lines[i], "td", &aligns, out);
+            i += 1;
+        }
+        out.push_str("</tbody>\n</table>\n");
+        i
+    }
+
+    fn table_row(&self, line: &str, tag: &str, aligns: &[&str], out: &mut String) {
+        out.push_str("<tr>");
+        for (i, cell) in cells(line).iter().enumerate() {
+            out.push_str(&format!(
+                "<{0}{1}>{2}</{0}>",
+                tag,
+                aligns.get(i).copied().unwrap_or_default(),
+                self.inline(cell)
+            ));
+        }
+        out.push_str("</tr>\n");
+    }
+
+    fn inline(&self, text: &str) -> String {
+        let mut out = String::new();
+        let mut i = 0;
+        while let Some(c) = text[i..].chars().next() {
+            let rest = &text[i..];
+            let previous = text[..i].chars().next_back();
+            let consumed = match c {
+                '\\' => rest[1..]
+                    .chars()
+                    .next()
+                    .filter(char::is_ascii_punctuation)
+                    .map(|escaped| {
+                        out.push_str(&escape(&escaped.to_string()));
+                        2
+                    }),
+                '`' => code_span(rest).map(|(code, len)| {
+                    out.push_str(&format!("<code>{}</code>", escape(code)));
+                    len
+                }),
+                '!' => link(&rest[1..]).map(|link| {
+                    out.push_str(&self.image(
+                        &link.destination,
+                        &link.text,
+                        title_attribute(link.title.as_deref()),
+                    ));
+                    link.len + 1
+                }),
+                '[' => link(rest).map(|link| {
+                    out.push_str(&format!(
+                        "<a href=\"{}\"{}>{}</a>",
+                        escape(&safe_href(&link.destination)),
+                        title_attribute(link.title.as_deref()),
+                        self.inline(&link.text)
+                    ));
+                    link.len
+                }),
+                '<' => {
+                    if let Some(caps) = AUTOLINK.captures(rest) {
+                        let url = &caps[1];
+                        out.push_str(&format!("<a href=\"{0}\">{0}</a>", escape(url)));
+                        Some(caps[0].len())
+                    } else {
+                        INLINE_HTML.find(rest).map(|tag| {
+                            if is_image(tag.as_str()) {
+                                out.push_str(&self.image_tag(tag.as_str()));
+                            } else if !self.xhtml {
+                                out.push_str(tag.as_str());
+                            } else if is_line_break(tag.as_str()) {
+                                out.push_str(&self.void("br"));
+                            }
+                            tag.len()
+                        })
+                    }
+                }
+                '*' | '_' | '~' => self.emphasis(rest, previous, &mut out),
+                'h' if !previous.is_some_and(char::is_alphanumeric) => {
+                    BARE_URL.find(rest).map(|url| {
+                        let url = trim_url(url.as_str());
+                        out.push_str(&format!("<a href=\"{0}\">{0}</a>", escape(url)));
+                        url.len()
+                    })
+                }
+                ':' => SHORTCODE.captures(rest).and_then(|caps| {
+                    let emoji = emojis::get_by_shortcode(&caps[1])?;
+                    out.push_str(emoji.as_str());
+                    Some(caps[0].len())
+                }),
+                '\n' => {
+                    out.push_str(&self.void("br"));
+                    out.push('\n');
+                    Some(1)
+                }
+                _ => None,
+            };
+            match consumed {
+                Some(len) => i += len,
+                None => {
+                    out.push_str(&escape(&c.to_string()));
+                    i += c.len_utf8();
+                }
+            }
+        }
+        out
+    }
+
+    /// An image from `image_src`, or a link to `url` when it has none.
+    fn image(&self, url: &str, alt: &str, attributes: String) -> String {
+        match (self.image_src)(url) {
+            Some(src) => self.void(&format!(
+                "img src=\"{}\" alt=\"{}\"{}",
+                escape(&src),
+                escape(alt),
+                attributes
+            )),
+            None => format!(
+                "<a href=\"{}\">{}</a>",
+                escape(&safe_href(url)),
+                escape(if alt.is_empty() { url } else { alt })
+            ),
+        }
+    }
+
+    /// An `<img>` written as HTML, which is how esa's editor inserts pasted images, drawn
+    /// like a Markdown image. Only the attributes that describe the image are kept.
+    fn image_tag(&self, tag: &str) -> String {
+        let mut src = None;
+        let mut alt = String::new();
+        let mut attributes = String::new();
+        for caps in ATTRIBUTE.captures_iter(tag) {
+            let value = caps
+                .get(2)
+                .or(caps.get(3))
+                .or(caps.get(4))
+                .map_or(String::new(), |value| unescape(value.as_str()));
+            match caps[1].to_ascii_lowercase().as_str() {
+                "src" => src = Some(value),
+                "alt" => alt = value,
+                name @ ("width" | "height" | "title") => {
+                    attributes.push_str(&format!(" {}=\"{}\"", name, escape(&value)))
+                }
+                _ => {}
+            }
+        }
+        match src {
+            Some(src) => self.image(&src, &alt, attributes),
+            None => String::new(),
+        }
+    }
+
+    /// `**strong**`, `*em*` and `~~del~~`, or `None` when `rest` doesn't open one. `_` only
+    /// counts at the start of a word so names like `snake_case` are kept.
+    fn emphasis(&self, rest: &str, previous: Option<char>, out: &mut String) -> Option<usize> {
+        let delimiter = rest.chars().next()?;
+        if delimiter == '_' && previous.is_some_and(char::is_alphanumeric) {
+            return None;
+        }
+        let run = rest.len() - rest.trim_start_matches(delimiter).len();
+        let candidates: &[(usize, &str)] = match delimiter {
+            '~' => &[(2, "del")],
+            _ => &[(2, "strong"), (1, "em")],
+        };
+        for &(width, tag) in candidates {
+            if run < width {
+                continue;
+            }
+            let marker = &rest[..width];
+            let Some(end) = closing(&rest[width..], marker, delimiter) else {
+                continue;
+            };
+            let inner = &rest[width..width + end];
+            out.push_str(&format!("<{0}>{1}</{0}>", tag, self.inline(inner)));
+            return Some(width + end + width);
+        }
+        None
+    }
+}
+
+/// Where `marker` closes emphasis in `rest`: after some text that neither starts nor ends
+/// with a space, and at the end of a run of the delimiter so `***a***` closes on the last two.
+fn closing(rest: &str, marker: &str, delimiter: char) -> Option<usize> {
+    if rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let mut from = 0;
+    while let Some(found) = rest[from..].find(marker) {
+        let run_start = from + found;
+        let run = rest[run_start..].len() - rest[run_start..].trim_start_matches(delimiter).len();
+        let end = run_start + run - marker.len();
+        let after = rest[end + marker.len()..].chars().next();
+        if end > 0
+            && !rest[..end].ends_with(char::is_whitespace)
+            && !(delimiter == '_' && after.is_some_and(char::is_alphanumeric))
+        {
+            return Some(end);
+        }
+        from = run_start + run;
+    }
+    None
+}
+
+/// A code span opening `rest` and its length, spaces around the code dropped.
+fn code_span(rest: &str) -> Option<(&str, usize)> {
+    let ticks = rest.len() - rest.trim_start_matches('`').len();
+    let marker = &rest[..ticks];
+    let mut from = ticks;
+    while let Some(found) = rest[from..].find(marker) {
+        let end = from + found;
+        let run = rest[end..].len() - rest[end..].trim_start_matches('`').len();
+        if run == ticks {
+            let code = &rest[ticks..end];
+            let code = if code.len() >= 2 && code.starts_with(' ') && code.ends_with(' ') {
+                &code[1..code.len() - 1]
+            } else {
+                code
+            };
+            return Some((code, end + ticks));
+        }
+        from = end + run;
+    }
+    None
+}
+
+struct Link {
+    text: String,
+    destination: String,
+    title: Option<String>,
+    /// Bytes taken from `[` to `)`.
+    len: usize,
+}
+
+/// `[text](destination "title")` at the start of `rest`.
+fn link(rest: &str) -> Option<Link> {
+    let rest_after_open = rest.strip_prefix('[')?;
+    let mut depth = 0;
+    let mut text_end = None;
+    let mut chars = rest_after_open.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => depth += 1,
+            ']' if depth == 0 => {
+                text_end = Some(i);
+                break;
+            }
+            ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    let text_end = text_end?;
+    let after = rest_after_open[text_end + 1..].strip_prefix('(')?;
+    let mut depth = 0;
+    let mut destination_end = None;
+    for (i, c) in after.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => {
+                destination_end = Some(i);
+                break;
+            }
+            ')' => depth -= 1,
+            '\n' => return None,
+            _ => {}
+        }
+    }
+    let destination_end = destination_end?;
+    let inside = after[..destination_end].trim();
+    let (destination, title) = match inside.split_once([' ', '\t']) {
+        Some((destination, title)) => {
+            let title = title.trim();
+            let unquoted = title
+                .strip_prefix('"')
+                .and_then(|title| title.strip_suffix('"'))
+                .or_else(|| {
+                    title
+                        .strip_prefix('\'')
+                        .and_then(|title| title.strip_suffix('\''))
+                });
+            (destination, unquoted.map(str::to_string))
+        }
+        None => (inside, None),
+    };
+    let destination = destination
+        .strip_prefix('<')
+        .and_then(|destination| destination.strip_suffix('>'))
+        .unwrap_or(destination);
+    Some(Link {
+        text: rest_after_open[..text_end].to_string(),
+        destination: destination.to_string(),
+        title,
+        len: 1 + text_end + 2 + destination_end + 1,
+    })
+}
+
+fn is_line_break(tag: &str) -> bool {
+    let name = tag.trim_start_matches('<').trim_start_matches('/');
+    name.get(..2)
+        .is_some_and(|name| name.eq_ignore_ascii_case("br"))
+        && !name[2..].starts_with(|c: char| c.is_ascii_alphanumeric())
+}
+
+fn is_image(tag: &str) -> bool {
+    IMG_TAG
+        .find(tag)
+        .is_some_and(|image| image.len() == tag.len())
+}
+
+fn title_attribute(title: Option<&str>) -> String {
+    title.map_or(String::new(), |title| {
+        format!(" title=\"{}\"", escape(title))
+    })
+}
+
+/// Scripts can't be run from links in the exported page.
+fn safe_href(destination: &str) -> String {
+    let scheme = destination.trim_start().to_ascii_lowercase();
+    if scheme.starts_with("javascript:") || scheme.starts_with("vbscript:") {
+        "#".to_string()
+    } else {
+        destination.to_string()
+    }
+}
+
+/// Drops punctuation ending a sentence after a bare URL, and a `)` the URL didn't open.
+fn trim_url(url: &str) -> &str {
+    let mut url = url.trim_end_matches(['.', ',', ':', ';', '!', '?', '*', '_', '~']);
+    while url.ends_with(')') && url.matches(')').count() > url.matches('(').count() {
+        url = &url[..url.len() - 1];
+    }
+    url
+}
+
+struct ListMarker {
+    indent: usize,
+    /// The number of an ordered item, `None` for bullets.
+    start: Option<u32>,
+    /// Where the item's text starts.
+    content: usize,
+}
+
+fn list_marker(line: &str) -> Option<ListMarker> {
+    let caps = LIST_MARKER.captures(line)?;
+    if is_rule(line.trim_start()) {
+        return None;
+    }
+    Some(ListMarker {
+        indent: caps[1].len(),
+        start: caps.get(3).and_then(|number| number.as_str().parse().ok()),
+        content: caps[0].len(),
+    })
+}
+
+/// The fence character run and the info string after it.
+fn fence(line: &str) -> Option<(&str, &str)> {
+    let marker_char = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let run = line.len() - line.trim_start_matches(marker_char).len();
+    (run >= 3).then(|| (&line[..run], line[run..].trim()))
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let caps = HEADING.captures(line)?;
+    Some((caps[1].len(), caps.get(2).map_or("", |text| text.as_str())))
+}
+
+fn is_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|marker| compact.chars().all(|c| c == *marker))
+}
+
+fn is_delimiter_row(line: &str) -> bool {
+    let cells = cells(line);
+    line.contains('-')
+        && !cells.is_empty()
+        && cells.iter().all(|cell| {
+            let cell = cell.trim_start_matches(':').trim_end_matches(':');
+            !cell.is_empty() && cell.chars().all(|c| c == '-')
+        })
+}
+
+/// The cells of a table row, splitting on `|` other than escaped ones.
+fn cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = if line.ends_with('|') && !line.ends_with("\\|") {
+        &line[..line.len() - 1]
+    } else {
+        line
+    };
+    let mut cells = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cells.last_mut().expect("one cell").push('|');
+                chars.next();
+            }
+            '|' => cells.push(String::new()),
+            _ => cells.last_mut().expect("one cell").push(c),
+        }
+    }
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+fn starts_block(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    fence(trimmed).is_some()
+        || heading(trimmed).is_some()
+        || is_rule(trimmed)
+        || trimmed.starts_with('>')
+        || list_marker(line).is_some()
+        || HTML_BLOCK.is_match(trimmed)
+}
+
+fn expand_leading_tabs(line: &str) -> String {
+    let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+    let expanded = line[..indent].replace('\t', "    ");
+    format!("{}{}", expanded, &line[indent..])
+}
+
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The text of an attribute value, with the entities [`escape`] writes turned back.
+fn unescape(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+    use rstest::rstest;
+
+    fn render(body_md: &str) -> String {
+        to_html(body_md, &|url| Some(url.to_string()))
+    }
+
+    #[rstest]
+    #[case(
+        "**太字** と *斜体* と ~~取り消し~~",
+        "<p><strong>太字</strong> と <em>斜体</em> と <del>取り消し</del></p>\n"
+    )]
+    #[case("***両方***", "<p><strong><em>両方</em></strong></p>\n")]
+    #[case(
+        "snake_case_name と 2 * 3 * 4",
+        "<p>snake_case_name と 2 * 3 * 4</p>\n"
+    )]
+    #[case(
+        "`a <b>` と ``x ` y``",
+        "<p><code>a &lt;b&gt;</code> と <code>x ` y</code></p>\n"
+    )]
+    #[case(
+        "[esa](https://esa.io \"docs\") を見る",
+        "<p><a href=\"https://esa.io\" title=\"docs\">esa</a> を見る</p>\n"
+    )]
+    #[case(
+        "see https://esa.io/docs. :smile:",
+        "<p>see <a href=\"https://esa.io/docs\">https://esa.io/docs</a>. 😄</p>\n"
+    )]
+    #[case("[x](javascript:alert(1))", "<p><a href=\"#\">x</a></p>\n")]
+    #[case("1 行目\n2 行目<br>", "<p>1 行目<br>\n2 行目<br></p>\n")]
+    #[case("\\*そのまま\\* & <script", "<p>*そのまま* &amp; &lt;script</p>\n")]
+    fn test_inline(#[case] body_md: &str, #[case] expected: &str) {
+        assert_eq!(render(body_md), expected);
+    }
+
+    #[test]
+    fn test_image_src() {
+        let image_src = |url: &str| url.ends_with(".png").then(|| format!("local:{}", url));
+        let html = to_html(
+            "![図](https://files.esa.io/a.png) ![](https://files.esa.io/b.gif)",
+            &image_src,
+        );
+        assert_eq!(
+            html,
+            "<p><img src=\"local:https://files.esa.io/a.png\" alt=\"図\"> <a href=\"https://files.esa.io/b.gif\">https://files.esa.io/b.gif</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_pasted_image() {
+        let image_src = |url: &str| url.ends_with(".png").then(|| format!("local:{}", url));
+        let pasted = "<img width=\"1234\" alt=\"スクリーンショット 2026-05-01 12.00.00.png\" \
+            src=\"https://files.esa.io/uploads/production/attachments/1/2026/05/01/2/abc.png\">";
+        assert_eq!(
+            to_html(pasted, &image_src),
+            "<img src=\"local:https://files.esa.io/uploads/production/attachments/1/2026/05/01/2/abc.png\" \
+            alt=\"スクリーンショット 2026-05-01 12.00.00.png\" width=\"1234\">\n"
+        );
+        assert_eq!(
+            to_html(
+                "見て <IMG SRC='https://example.com/a.gif?x=1&amp;y=2' title=\"&quot;図&quot;\"> ね",
+                &image_src
+            ),
+            "<p>見て <a href=\"https://example.com/a.gif?x=1&amp;y=2\">https://example.com/a.gif?x=1&amp;y=2</a> ね</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        let body_md = "# 手順\n\
+            \n\
+            準備すること\n\
+            \n\
+            - [x] ブランチを切る\n\
+            - [ ] デプロイ\n  - staging\n  - production\n\
+            \n\
+            1. 確認\n\
+            2. 連絡\n\
+            \n\
+            > 注意\n> 本番は慎重に\n\
+            \n\
+            | 環境 | URL |\n|:--|--:|\n| prod | `a\\|b` |\n\
+            \n\
+            ```ruby:deploy.rb\nputs \"<ok>\"\n```\n\
+            \n\
+            <details><summary>詳細</summary>\n\
+            \n\
+            中身\n\
+            \n\
+            </details>\n\
+            \n\
+            まとめ\n---\n\
+            \n\
+            ***\n";
+        assert_snapshot!(render(body_md));
+    }
+
+    #[test]
+    fn test_xhtml_pasted_image() {
+        let pasted = "<img width=\"1234\" alt=\"スクリーンショット\" \
+            src=\"https://files.esa.io/uploads/production/attachments/1/2026/05/01/2/abc.png\">\n\
+            <br>見て <img src=\"https://example.com/a.gif\">";
+        assert_eq!(
+            to_xhtml(pasted, &|url| url
+                .ends_with(".png")
+                .then(|| "../images/1.png".to_string())),
+            "<p><img src=\"../images/1.png\" alt=\"スクリーンショット\" width=\"1234\" /><br />\n\
+            <br />見て <a href=\"https://example.com/a.gif\">https://example.com/a.gif</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_xhtml() {
+        let body_md = "- [x] 済み\n\n<kbd>Ctrl</kbd><br>押す\n\n1 行目\n2 行目\n\n---\n";
+        assert_eq!(
+            to_xhtml(body_md, &|_| None),
+            "<ul>\n\
+            <li class=\"task\"><input type=\"checkbox\" disabled=\"disabled\" checked=\"checked\" /> 済み</li>\n\
+            </ul>\n\
+            <p>Ctrl<br />押す</p>\n\
+            <p>1 行目<br />\n2 行目</p>\n\
+            <hr />\n"
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use std::{fs, path::Path};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, utf8_percent_encode};
+use ratatui::style::Color;
+
+use crate::{
+    domains::{Post, Theme, TimeDisplay},
+    image_cache::ImageCache,
+};
+
+mod body;
+
+pub use body::{escape, to_xhtml};
+
+/// Characters escaped in links to the exported files. Anything outside ASCII is always
+/// escaped.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`');
+
+/// Draws posts as standalone HTML pages styled with the theme's colors.
+#[derive(Clone)]
+pub struct HtmlRenderer {
+    theme: Theme,
+    time: TimeDisplay,
+    /// Images shown in the reader before, embedded so the page doesn't need esa.
+    images: ImageCache,
+}
+
+impl HtmlRenderer {
+    pub fn new(theme: Theme, time: TimeDisplay, images: ImageCache) -> Self {
+        Self {
+            theme,
+            time,
+            images,
+        }
+    }
+}
+
+impl HtmlRenderer {
+    /// The post's page, written to `page_dir`. Images are embedded when they are in the
+    /// image cache or, for relative links, next to the page; `index` links back to the index
+    /// page the post was exported with.
+    pub fn post_page(
+        &self,
+        post: &Post,
+        body_md: &str,
+        page_dir: &Path,
+        index: Option<&str>,
+    ) -> String {
+        let mut html = self.post_header(post, index);
+        html.push_str("<article>\n");
+        html.push_str(&body::to_html(body_md, &|src| {
+            Some(self.image_src(src, page_dir))
+        }));
+        html.push_str("</article>\n");
+        self.page(&post.full_name, &html)
+    }
+
+    /// What esa knows about the post, above its body. Well-formed XML, so EPUB uses it too.
+    pub fn post_header(&self, post: &Post, index: Option<&str>) -> String {
+        let mut html = String::from("<header class=\"post-header\">\n");
+        if let Some(index) = index {
+            html.push_str(&format!(
+                "<nav><a href=\"{}\">← Index</a></nav>\n",
+                escape(index)
+            ));
+        }
+        if let Some(category) = &post.category {
+            html.push_str(&format!(
+                "<div class=\"category\">{}/</div>\n",
+                escape(category.as_str())
+            ));
+        }
+        html.push_str(&format!("<h1>{}{}</h1>\n", escape(&post.name), wip(post)));
+        if !post.tags.is_empty() {
+            let tags: Vec<String> = post
+                .tags
+                .iter()
+                .map(|tag| format!("<li>#{}</li>", escape(&tag.label)))
+                .collect();
+            html.push_str(&format!("<ul class=\"tags\">{}</ul>\n", tags.join("")));
+        }
+        let mut authors = format!(
+            "#{} · created {} by @{}",
+            post.post_number.to_i32(),
+            self.time.absolute(post.created_at),
+            escape(&post.created_by.id.0)
+        );
+        if post.updated_at != post.created_at {
+            authors.push_str(&format!(
+                " · updated {} by @{}",
+                self.time.absolute(post.updated_at),
+                escape(&post.updated_by.id.0)
+            ));
+        }
+        authors.push_str(&format!(" · rev {}", post.revision_number));
+        html.push_str(&format!("<p class=\"meta\">{}</p>\n", authors));
+        html.push_str(&format!(
+            "<p class=\"meta\"><a href=\"{0}\">{0}</a></p>\n",
+            escape(post.url.as_str())
+        ));
+        html.push_str("</header>\n");
+        html
+    }
+
+    /// A page listing `posts`, each with the link to its page.
+    pub fn index_page(&self, title: &str, posts: &[(Post, String)]) -> String {
+        let mut html = format!(
+            "<header class=\"post-header\">\n<h1>{}</h1>\n<p class=\"meta\">{} posts</p>\n</header>\n<ul class=\"posts\">\n",
+            escape(title),
+            posts.len()
+        );
+        for (post, link) in posts {
+            let category = post
+                .category
+                .as_ref()
+                .map(|category| {
+                    format!(
+                        "<span class=\"category\">{}/</span>",
+                        escape(category.as_str())
+                    )
+                })
+                .unwrap_or_default();
+            html.push_str(&format!(
+                "<li>{}<a href=\"{}\">{}</a>{} <span class=\"meta\">updated {} by @{}</span></li>\n",
+                category,
+                escape(link),
+                escape(&post.name),
+                wip(post),
+                self.time.absolute(post.updated_at),
+                escape(&post.updated_by.id.0)
+            ));
+        }
+        html.push_str("</ul>\n");
+        self.page(title, &html)
+    }
+
+    fn page(&self, title: &str, body: &str) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n<main>\n{}</main>\n</body>\n</html>\n",
+            escape(title),
+            css(&self.theme),
+            body
+        )
+    }
+
+    /// The image if it was shown in the reader before.
+    pub fn cached_image(&self, url: &str) -> Option<Vec<u8>> {
+        self.images.load(url)
+    }
+
+    /// A data URI for images on disk, or the link as it was.
+    fn image_src(&self, src: &str, page_dir: &Path) -> String {
+        let bytes = if src.starts_with("http://") || src.starts_with("https://") {
+            self.images.load(src)
+        } else if src.contains(':') {
+            None
+        } else {
+            percent_decode_str(src)
+                .decode_utf8()
+                .ok()
+                .and_then(|path| fs::read(page_dir.join(path.as_ref())).ok())
+        };
+        bytes
+            .and_then(|bytes| data_uri(&bytes))
+            .unwrap_or_else(|| src.to_string())
+    }
+}
+
+/// A relative link to `path`, such as `dev/%E6%89%8B%E9%A0%86.html`.
+pub fn link_to(path: &Path) -> String {
+    path.components()
+        .map(|component| {
+            utf8_percent_encode(&component.as_os_str().to_string_lossy(), PATH_SEGMENT).to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn wip(post: &Post) -> &'static str {
+    if post.wip {
+        " <span class=\"wip\">WIP</span>"
+    } else {
+        ""
+    }
+}
+
+fn data_uri(bytes: &[u8]) -> Option<String> {
+    let mime = image_mime(bytes)?;
+    Some(format!("data:{};base64,{}", mime, STANDARD.encode(bytes)))
+}
+
+/// The media type of an image from its first bytes, `None` if it isn't one.
+pub fn image_mime(bytes: &[u8]) -> Option<&'static str> {
+    match image::guess_format(bytes) {
+        Ok(format) => Some(format.to_mime_type()),
+        Err(_) if is_svg(bytes) => Some("image/svg+xml"),
+        Err(_) => None,
+    }
+}
+
+fn is_svg(bytes: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(512)]);
+    head.contains("<svg")
+}
+
+/// Styles using the theme's colors, on a dark background when the text is light.
+fn css(theme: &Theme) -> String {
+    let primary = css_color(theme.primary, "#0F172A");
+    let background = if is_light(theme.primary) {
+        "#0F172A"
+    } else {
+        "#FFFFFF"
+    };
+    let muted = css_color(theme.muted, "#64748B");
+    let accent = css_color(theme.accent, "#0284C7");
+    let warning = css_color(theme.warning, "#D97706");
+    let link = css_color(theme.link, "#2563EB");
+    format!(
+        "body {{ margin: 0; background: {background}; color: {primary}; font-family: -apple-system, BlinkMacSystemFont, \"Hiragino Sans\", \"Noto Sans JP\", sans-serif; line-height: 1.7; }}
+main {{ max-width: 860px; margin: 0 auto; padding: 2rem 1.5rem; }}
+a {{ color: {link}; }}
+h1, h2 {{ color: {primary}; }}
+h2 {{ border-bottom: 1px solid {muted}; padding-bottom: 0.2em; }}
+h3, h4 {{ color: {accent}; }}
+h5, h6, .meta, .category {{ color: {muted}; }}
+.post-header {{ border-bottom: 1px solid {muted}; margin-bottom: 1.5rem; }}
+.post-header h1 {{ margin: 0.2rem 0; }}
+.meta {{ font-size: 0.9em; margin: 0.3rem 0; }}
+.tags {{ list-style: none; padding: 0; margin: 0.3rem 0; }}
+.tags li {{ display: inline; margin-right: 0.6em; color: {accent}; }}
+.wip {{ font-size: 0.6em; padding: 0.1em 0.4em; border-radius: 4px; background: {warning}; color: {background}; vertical-align: middle; }}
+.posts li {{ margin: 0.4rem 0; }}
+code {{ color: {accent}; font-family: ui-monospace, SFMono-Regular, Menlo, monospace; font-size: 0.9em; }}
+pre {{ padding: 0.8rem 1rem; border: 1px solid {muted}; border-radius: 6px; overflow-x: auto; }}
+pre code {{ color: {primary}; }}
+.code-name {{ color: {muted}; font-size: 0.85em; margin-bottom: -0.8rem; }}
+blockquote {{ margin: 0; padding-left: 1em; border-left: 4px solid {muted}; color: {muted}; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid {muted}; padding: 0.3em 0.8em; }}
+th {{ color: {accent}; }}
+img {{ max-width: 100%; }}
+li.task {{ list-style: none; }}
+hr {{ border: none; border-top: 1px solid {muted}; }}
+"
+    )
+}
+
+fn css_color(color: Color, fallback: &str) -> String {
+    match color {
+        Color::Rgb(r, g, b) => format!("#{:02X}{:02X}{:02X}", r, g, b),
+        _ => fallback.to_string(),
+    }
+}
+
+fn is_light(color: Color) -> bool {
+    match color {
+        Color::Rgb(r, g, b) => {
+            u32::from(r) * 299 + u32::from(g) * 587 + u32::from(b) * 114 > 128 * 1000
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::{ThemeConfig, sample_post};
+    use insta::assert_snapshot;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn post() -> Post {
+        sample_post()
+    }
+
+    fn renderer(images: &Path) -> HtmlRenderer {
+        HtmlRenderer::new(
+            Theme::from_config(&ThemeConfig::default()),
+            TimeDisplay::new(Some("Asia/Tokyo"), false),
+            ImageCache::next_to(&images.join("config.toml"), 100),
+        )
+    }
+
+    #[rstest]
+    fn test_post_page(post: Post) {
+        let dir = tempfile::tempdir().unwrap();
+        let html = renderer(dir.path()).post_page(
+            &post,
+            "## 手順\n\n1. ビルド\n2. デプロイ\n",
+            dir.path(),
+            Some("../../index.html"),
+        );
+        let body = html.split("</style>").nth(1).unwrap();
+        assert_snapshot!(body);
+    }
+
+    #[test]
+    fn test_embeds_images_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        // the smallest GIF
+        let gif = b"GIF89a\x01\x00\x01\x00\x00\x00\x00;";
+        let renderer = renderer(dir);
+        renderer
+            .images
+            .store("https://files.esa.io/uploads/a.gif", gif)
+            .unwrap();
+        fs::create_dir_all(dir.join("attachments")).unwrap();
+        fs::write(dir.join("attachments/b c.gif"), gif).unwrap();
+
+        let data = format!("data:image/gif;base64,{}", STANDARD.encode(gif));
+        assert_eq!(
+            renderer.image_src("https://files.esa.io/uploads/a.gif", dir),
+            data
+        );
+        assert_eq!(renderer.image_src("attachments/b%20c.gif", dir), data);
+        assert_eq!(
+            renderer.image_src("https://example.com/missing.png", dir),
+            "https://example.com/missing.png"
+        );
+    }
+
+    #[test]
+    fn test_css_follows_the_theme() {
+        let dark = css(&Theme::from_config(&ThemeConfig::default()));
+        assert!(dark.contains("background: #0F172A; color: #E2E8F0"));
+        let light = ThemeConfig {
+            primary: Some("#111111".to_string()),
+            ..ThemeConfig::default()
+        };
+        assert!(css(&Theme::from_config(&light)).contains("background: #FFFFFF; color: #111111"));
+    }
+
+    #[test]
+    fn test_link_to() {
+        assert_eq!(
+            link_to(Path::new("dev/手順 #1.html")),
+            "dev/%E6%89%8B%E9%A0%86%20%231.html"
+        );
+    }
+}
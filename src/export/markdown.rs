@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+
+use crate::domains::Post;
+
+/// `category/path/name.md`. Characters files can't be named with become `_`.
+pub fn post_path(post: &Post) -> PathBuf {
+    let mut path: PathBuf = post
+        .category
+        .iter()
+        .flat_map(|category| category.segments())
+        .map(sanitize)
+        .collect();
+    path.push(format!("{}.md", sanitize(&post.name)));
+    path
+}
+
+/// The body with YAML front matter holding what esa knows about the post.
+pub fn render(post: &Post, body_md: &str) -> String {
+    let mut out = String::from("---\n");
+    out.push_str(&format!("title: {}\n", quote(&post.name)));
+    out.push_str(&format!("number: {}\n", post.post_number.to_i32()));
+    if let Some(category) = &post.category {
+        out.push_str(&format!("category: {}\n", quote(category.as_str())));
+    }
+    let tags: Vec<String> = post.tags.iter().map(|tag| quote(&tag.label)).collect();
+    out.push_str(&format!("tags: [{}]\n", tags.join(", ")));
+    out.push_str(&format!("wip: {}\n", post.wip));
+    out.push_str(&format!("created_at: {}\n", post.created_at.to_rfc3339()));
+    out.push_str(&format!("created_by: {}\n", quote(&post.created_by.id.0)));
+    out.push_str(&format!("updated_at: {}\n", post.updated_at.to_rfc3339()));
+    out.push_str(&format!("updated_by: {}\n", quote(&post.updated_by.id.0)));
+    out.push_str(&format!("url: {}\n", quote(post.url.as_str())));
+    out.push_str(&format!("revision: {}\n", post.revision_number));
+    out.push_str("---\n\n");
+    out.push_str(body_md);
+    if !body_md.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// A double-quoted YAML string, which is written the same way as in JSON.
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+/// `segment` as a file name, with the characters files can't be named with as `_`.
+pub fn sanitize(segment: &str) -> String {
+    let sanitized: String = segment
+        .chars()
+        .map(|c| {
+            if c.is_control() || "/\\:*?\"<>|".contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let sanitized = sanitized.trim_end_matches(['.', ' ']).trim_start();
+    if sanitized.is_empty() || sanitized == ".." {
+        "_".to_string()
+    } else {
+        sanitized.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::{Category, sample_post};
+    use insta::assert_snapshot;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn post() -> Post {
+        sample_post()
+    }
+
+    #[rstest]
+    #[case(
+        Some("dev/ops"),
+        "デプロイ手順のまとめ",
+        "dev/ops/デプロイ手順のまとめ.md"
+    )]
+    #[case(None, "日報", "日報.md")]
+    #[case(Some("a:b/.."), "what? <draft>", "a_b/_/what_ _draft_.md")]
+    #[case(Some("notes"), "v1.", "notes/v1.md")]
+    fn test_post_path(
+        post: Post,
+        #[case] category: Option<&str>,
+        #[case] name: &str,
+        #[case] expected: &str,
+    ) {
+        let post = Post {
+            category: category.and_then(Category::parse),
+            name: name.to_string(),
+            ..post
+        };
+        assert_eq!(post_path(&post), PathBuf::from(expected));
+    }
+
+    #[rstest]
+    fn test_render(post: Post) {
+        assert_snapshot!(render(&post, "# 手順\n\"引用\" の例"));
+    }
+}
@@ -0,0 +1,541 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    iter::Peekable,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+
+use crate::{
+    domains::{Post, PostSort, PostViewConfig, SortOrder},
+    http_gateways::{EsaClientHttpGateway, MirroredPost},
+    sync::{self, PER_PAGE},
+};
+
+mod epub;
+mod html;
+mod markdown;
+
+pub use html::HtmlRenderer;
+
+/// The index page of an HTML export.
+const INDEX: &str = "index.html";
+
+/// The size limit for each attachment.
+const MAX_ATTACHMENT_BYTES: u64 = 100 * 1024 * 1024;
+
+/// URLs of files uploaded to esa.
+static ATTACHMENT_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"https://(?:files|img)\.esa\.io/[^\s)\]"'<>]+"#).expect("valid regex")
+});
+
+/// Arguments to `export`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// The esa search query. `None` exports every post.
+    pub query: Option<String>,
+    /// The title of the view picked with `--view`, used for the HTML index and EPUB title.
+    pub title: Option<String>,
+    /// The view's order with `--view`, number order otherwise.
+    pub sort: PostSort,
+    pub order: SortOrder,
+    /// The output directory, or the output file for EPUB.
+    pub out: PathBuf,
+    pub format: ExportFormat,
+    /// Downloads attachments too and points the body's links at the local files.
+    pub attachments: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Markdown with front matter.
+    #[default]
+    Markdown,
+    /// A page per post plus an index page.
+    Html,
+    /// One book holding every post.
+    Epub,
+}
+
+impl ExportOptions {
+    /// `--view` names a key of `views` and exports with its query.
+    pub fn parse(args: &[&str], views: &BTreeMap<String, PostViewConfig>) -> anyhow::Result<Self> {
+        let mut query = None;
+        let mut view = None;
+        let mut out = None;
+        let mut format = ExportFormat::default();
+        let mut attachments = false;
+        let mut args = args.iter().copied().peekable();
+        // so a `--query` missing its value isn't taken as exporting every post
+        let value = |args: &mut Peekable<_>, arg: &str| {
+            args.next_if(|value: &&str| !value.starts_with("--"))
+                .ok_or_else(|| anyhow::anyhow!("{} needs a value", arg))
+        };
+        while let Some(arg) = args.next() {
+            match arg {
+                "--query" => query = Some(value(&mut args, arg)?.to_string()),
+                "--view" => view = Some(value(&mut args, arg)?),
+                "--out" => out = Some(PathBuf::from(value(&mut args, arg)?)),
+                "--format" => {
+                    format = match value(&mut args, arg)? {
+                        "markdown" => ExportFormat::Markdown,
+                        "html" => ExportFormat::Html,
+                        "epub" => ExportFormat::Epub,
+                        other => anyhow::bail!(
+                            "unknown format for export: {} (markdown, html or epub)",
+                            other
+                        ),
+                    }
+                }
+                "--attachments" => attachments = true,
+                _ => anyhow::bail!("unknown option for export: {}", arg),
+            }
+        }
+        let Some(out) = out else {
+            anyhow::bail!("export needs --out <dir>, or --out <file> for epub");
+        };
+        if attachments && format == ExportFormat::Epub {
+            anyhow::bail!("--attachments can't be used with --format epub");
+        }
+        let mut title = None;
+        let (mut sort, mut order) = (PostSort::Number, SortOrder::Asc);
+        if let Some(name) = view {
+            if query.is_some() {
+                anyhow::bail!("export takes either --query or --view");
+            }
+            let Some(view) = views.get(name) else {
+                anyhow::bail!("unknown view: {}", name);
+            };
+            query = view.query.clone();
+            title = Some(view.title.clone());
+            sort = view.sort.unwrap_or_default();
+            order = view.order.unwrap_or_default();
+        }
+        Ok(Self {
+            query,
+            title,
+            sort,
+            order,
+            out,
+            format,
+            attachments,
+        })
+    }
+
+    fn index_title(&self) -> String {
+        self.title
+            .clone()
+            .or_else(|| self.query.clone())
+            .unwrap_or_else(|| "All posts".to_string())
+    }
+}
+
+/// Writes the posts matching the query as Markdown or HTML files, one directory per
+/// category. HTML also gets an `index.html` linking every post, and EPUB puts them all in one
+/// book.
+///
+/// When two posts in a category share a name, the later one gets its number appended.
+pub async fn export(
+    api: &dyn EsaClientHttpGateway,
+    options: &ExportOptions,
+    renderer: &HtmlRenderer,
+) -> anyhow::Result<()> {
+    let mut used = HashSet::from([INDEX.to_string()]);
+    let mut pages = vec![];
+    let mut chapters = vec![];
+    let mut exported = 0;
+    let mut next_page = Some(1);
+    while let Some(page) = next_page {
+        let fetched = sync::fetch_page(
+            api,
+            options.query.clone(),
+            options.sort,
+            options.order,
+            page,
+        )
+        .await?;
+        for MirroredPost { post, body, .. } in fetched.posts {
+            exported += 1;
+            let number = post.post_number.to_i32();
+            match options.format {
+                ExportFormat::Markdown => {
+                    let path = unique_path(markdown::post_path(&post), number, &mut used);
+                    let body_md = with_attachments(api, options, &path, body.body_md).await;
+                    write(&options.out.join(&path), markdown::render(&post, &body_md))?
+                }
+                ExportFormat::Html => {
+                    let path = unique_path(html_path(&post), number, &mut used);
+                    let body_md = with_attachments(api, options, &path, body.body_md).await;
+                    write_html_page(renderer, &options.out, &path, &post, &body_md)?;
+                    pages.push((post, path));
+                }
+                ExportFormat::Epub => chapters.push((post, body.body_md)),
+            }
+        }
+        let pages = fetched.total_count.map_or("?".to_string(), |count| {
+            count.div_ceil(PER_PAGE).max(1).to_string()
+        });
+        println!("page {}/{}: {} posts exported", page, pages, exported);
+        next_page = fetched.next_page;
+    }
+    match options.format {
+        ExportFormat::Markdown => {}
+        ExportFormat::Html => {
+            write_index(renderer, &options.out, &options.index_title(), &pages)?;
+        }
+        ExportFormat::Epub => {
+            epub::write(&options.out, &options.index_title(), chapters, renderer)?;
+        }
+    }
+    println!("exported {} posts to {}", exported, options.out.display());
+    Ok(())
+}
+
+/// Where and how HTML exports started from the UI are written.
+#[derive(Clone)]
+pub struct HtmlExport {
+    dir: PathBuf,
+    renderer: HtmlRenderer,
+}
+
+impl HtmlExport {
+    /// Writes to `exports/<team>` next to the config file.
+    pub fn next_to(config_path: &Path, team_name: &str, renderer: HtmlRenderer) -> Self {
+        Self {
+            dir: config_path.with_file_name("exports").join(team_name),
+            renderer,
+        }
+    }
+
+    /// Writes the post to `<category>/<name>.html` and returns the path.
+    pub fn export_post(&self, post: &Post, body_md: &str) -> anyhow::Result<PathBuf> {
+        let path = self.dir.join(html_path(post));
+        let page_dir = path.parent().unwrap_or(&self.dir);
+        write(
+            &path,
+            self.renderer.post_page(post, body_md, page_dir, None),
+        )?;
+        Ok(path)
+    }
+
+    /// Writes every post matching `query` under `<title>/` with an index page, in the view's
+    /// `sort` and `order`, and returns the count and the index path. `progress` gets the
+    /// count written so far after each page.
+    pub async fn export_view(
+        &self,
+        api: &dyn EsaClientHttpGateway,
+        title: &str,
+        query: Option<String>,
+        sort: PostSort,
+        order: SortOrder,
+        progress: impl Fn(usize),
+    ) -> anyhow::Result<(usize, PathBuf)> {
+        let out = self.dir.join(markdown::sanitize(title));
+        let mut used = HashSet::from([INDEX.to_string()]);
+        let mut pages = vec![];
+        let mut next_page = Some(1);
+        while let Some(page) = next_page {
+            let fetched = sync::fetch_page(api, query.clone(), sort, order, page).await?;
+            for MirroredPost { post, body, .. } in fetched.posts {
+                let path = unique_path(html_path(&post), post.post_number.to_i32(), &mut used);
+                write_html_page(&self.renderer, &out, &path, &post, &body.body_md)?;
+                pages.push((post, path));
+            }
+            progress(pages.len());
+            next_page = fetched.next_page;
+        }
+        let index = write_index(&self.renderer, &out, title, &pages)?;
+        Ok((pages.len(), index))
+    }
+}
+
+fn html_path(post: &Post) -> PathBuf {
+    markdown::post_path(post).with_extension("html")
+}
+
+/// Writes the post page to `path` under `out`, with a link back to the index.
+fn write_html_page(
+    renderer: &HtmlRenderer,
+    out: &Path,
+    path: &Path,
+    post: &Post,
+    body_md: &str,
+) -> anyhow::Result<()> {
+    let depth = path.components().count().saturating_sub(1);
+    let index = format!("{}{}", "../".repeat(depth), INDEX);
+    let target = out.join(path);
+    let page_dir = target.parent().unwrap_or(out);
+    write(
+        &target,
+        renderer.post_page(post, body_md, page_dir, Some(&index)),
+    )
+}
+
+fn write_index(
+    renderer: &HtmlRenderer,
+    out: &Path,
+    title: &str,
+    pages: &[(Post, PathBuf)],
+) -> anyhow::Result<PathBuf> {
+    let links: Vec<(Post, String)> = pages
+        .iter()
+        .map(|(post, path)| (post.clone(), html::link_to(path)))
+        .collect();
+    let index = out.join(INDEX);
+    write(&index, renderer.index_page(title, &links))?;
+    Ok(index)
+}
+
+/// `used` holds lowercased paths, since `Foo.md` and `foo.md` collide on macOS and Windows.
+fn unique_path(path: PathBuf, post_number: i32, used: &mut HashSet<String>) -> PathBuf {
+    let key = |path: &Path| path.to_string_lossy().to_lowercase();
+    let path = if used.contains(&key(&path)) {
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        path.with_file_name(format!("{}-{}.{}", stem, post_number, extension))
+    } else {
+        path
+    };
+    used.insert(key(&path));
+    path
+}
+
+/// With `--attachments`, downloads the attachments and returns the body with links rewritten.
+async fn with_attachments(
+    api: &dyn EsaClientHttpGateway,
+    options: &ExportOptions,
+    path: &Path,
+    body_md: String,
+) -> String {
+    if options.attachments {
+        download_attachments(api, &options.out, path, &body_md).await
+    } else {
+        body_md
+    }
+}
+
+/// Downloads the attachments the body links to into `attachments`, rewriting links only for
+/// those that succeeded. Files from an earlier export are reused.
+async fn download_attachments(
+    api: &dyn EsaClientHttpGateway,
+    out: &Path,
+    post_path: &Path,
+    body_md: &str,
+) -> String {
+    let mut local = HashMap::new();
+    for url in attachment_urls(body_md) {
+        let Some((path, link)) = attachment_path(&url) else {
+            continue;
+        };
+        let target = out.join(&path);
+        if !target.exists() {
+            let downloaded = match api.fetch_attachment(&url, MAX_ATTACHMENT_BYTES).await {
+                Ok(bytes) => write(&target, &bytes),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = downloaded {
+                eprintln!("failed to download {}: {}", url, e);
+                continue;
+            }
+        }
+        local.insert(url, relative_link(post_path, &link));
+    }
+    rewrite_links(body_md, &local)
+}
+
+fn attachment_urls(body_md: &str) -> Vec<String> {
+    let mut urls: Vec<String> = ATTACHMENT_URL
+        .find_iter(body_md)
+        .map(|url| url.as_str().to_string())
+        .collect();
+    urls.sort();
+    urls.dedup();
+    urls
+}
+
+/// The `attachments/<host>/<path>` to save to and the link to it. Upload paths carry a
+/// unique ID, so they don't collide. The file name is decoded; the link stays encoded.
+fn attachment_path(url: &str) -> Option<(PathBuf, String)> {
+    let url: url::Url = url.parse().ok()?;
+    let host = url.host_str()?;
+    let mut path = PathBuf::from("attachments").join(host);
+    for segment in url.path_segments()? {
+        let segment = percent_decode_str(segment).decode_utf8().ok()?;
+        if segment.is_empty() || segment == "." || segment == ".." || segment.contains('/') {
+            return None;
+        }
+        path.push(segment.as_ref());
+    }
+    Some((path, format!("attachments/{}{}", host, url.path())))
+}
+
+/// A relative link to `link` from the file at `from`, both relative to the output.
+fn relative_link(from: &Path, link: &str) -> String {
+    let depth = from.components().count().saturating_sub(1);
+    format!("{}{}", "../".repeat(depth), link)
+}
+
+fn rewrite_links(body_md: &str, local: &HashMap<String, String>) -> String {
+    ATTACHMENT_URL
+        .replace_all(body_md, |caps: &regex::Captures| {
+            local
+                .get(&caps[0])
+                .cloned()
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+fn write(path: &Path, contents: impl AsRef<[u8]>) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_options() {
+        let views = BTreeMap::new();
+        let options = ExportOptions::parse(
+            &[
+                "--query",
+                "in:dev wip:false",
+                "--out",
+                "backup",
+                "--attachments",
+            ],
+            &views,
+        )
+        .unwrap();
+        assert_eq!(
+            options,
+            ExportOptions {
+                query: Some("in:dev wip:false".to_string()),
+                title: None,
+                sort: PostSort::Number,
+                order: SortOrder::Asc,
+                out: PathBuf::from("backup"),
+                format: ExportFormat::Markdown,
+                attachments: true,
+            }
+        );
+        assert!(ExportOptions::parse(&["--query", "in:dev"], &views).is_err());
+        for missing in [
+            &["--out", "backup", "--query"][..],
+            &["--query", "--out", "backup"],
+            &["--out", "backup", "--view"],
+            &["--out"],
+            &["--out", "backup", "--format"],
+        ] {
+            assert!(ExportOptions::parse(missing, &views).is_err());
+        }
+        assert!(ExportOptions::parse(&["--out", "backup", "--zip"], &views).is_err());
+        assert!(ExportOptions::parse(&["--out", "backup", "--format", "pdf"], &views).is_err());
+        assert!(
+            ExportOptions::parse(
+                &["--out", "a.epub", "--format", "epub", "--attachments"],
+                &views
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_view() {
+        let view: PostViewConfig =
+            toml::from_str("title = \"開発\"\nquery = \"in:dev\"\nsort = \"stars\"").unwrap();
+        let views = BTreeMap::from([("dev".to_string(), view)]);
+        let options = ExportOptions::parse(
+            &["--view", "dev", "--format", "html", "--out", "site"],
+            &views,
+        )
+        .unwrap();
+        assert_eq!(options.query.as_deref(), Some("in:dev"));
+        assert_eq!(options.format, ExportFormat::Html);
+        // in the order the view shows
+        assert_eq!(
+            (options.sort, options.order),
+            (PostSort::Stars, SortOrder::Desc)
+        );
+        assert_eq!(options.index_title(), "開発");
+        assert!(ExportOptions::parse(&["--view", "ops", "--out", "site"], &views).is_err());
+        assert!(
+            ExportOptions::parse(
+                &["--view", "dev", "--query", "in:ops", "--out", "site"],
+                &views
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_unique_path() {
+        let mut used = HashSet::new();
+        let path = PathBuf::from("dev/日報.md");
+        assert_eq!(unique_path(path.clone(), 1, &mut used), path);
+        assert_eq!(
+            unique_path(path, 2, &mut used),
+            PathBuf::from("dev/日報-2.md")
+        );
+        assert_eq!(
+            unique_path(PathBuf::from("dev/Deploy.md"), 3, &mut used),
+            PathBuf::from("dev/Deploy.md")
+        );
+        assert_eq!(
+            unique_path(PathBuf::from("dev/deploy.md"), 4, &mut used),
+            PathBuf::from("dev/deploy-4.md")
+        );
+    }
+
+    #[test]
+    fn test_attachment_path() {
+        let (path, link) =
+            attachment_path("https://files.esa.io/uploads/1/%E8%B3%87%E6%96%99%201.pdf").unwrap();
+        assert_eq!(
+            path,
+            PathBuf::from("attachments/files.esa.io/uploads/1/資料 1.pdf")
+        );
+        assert_eq!(
+            link,
+            "attachments/files.esa.io/uploads/1/%E8%B3%87%E6%96%99%201.pdf"
+        );
+        assert!(attachment_path("https://files.esa.io/uploads/a%2F..%2F..%2Fx.png").is_none());
+    }
+
+    #[test]
+    fn test_rewrite_attachments() {
+        let body_md = "![shot.png](https://files.esa.io/uploads/production/attachments/1/2026/05/abc.png)\n\
+            [資料](https://img.esa.io/uploads/production/attachments/1/x.pdf \"title\")\n\
+            ![外部](https://example.com/a.png) ![失敗](https://files.esa.io/uploads/b.png)\n";
+        let urls = attachment_urls(body_md);
+        assert_eq!(urls.len(), 3);
+
+        let post_path = Path::new("dev/ops/手順.md");
+        let mut local = HashMap::new();
+        // the last one couldn't be downloaded
+        for url in urls.iter().filter(|url| !url.ends_with("/b.png")) {
+            let (_, link) = attachment_path(url).unwrap();
+            local.insert(url.clone(), relative_link(post_path, &link));
+        }
+        assert_eq!(
+            rewrite_links(body_md, &local),
+            "![shot.png](../../attachments/files.esa.io/uploads/production/attachments/1/2026/05/abc.png)\n\
+            [資料](../../attachments/img.esa.io/uploads/production/attachments/1/x.pdf \"title\")\n\
+            ![外部](https://example.com/a.png) ![失敗](https://files.esa.io/uploads/b.png)\n"
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    domains::{LocalQuery, Post, PostBody, PostNumber, PostSort, SortOrder, sort_posts},
+    post_cache::{PendingAction, PostCache},
+};
+
+use super::{EsaClientHttpGateway, FetchedPage, ListRequest, MirrorPage, PostListPage};
+
+/// Posts per page when a view doesn't say, matching the API's default.
+const DEFAULT_PER_PAGE: u32 = 20;
+
+/// Wraps a gateway so lists and bodies already seen come from the disk cache.
+///
+/// Cached list pages are returned at once and refetched in the background. The fresh page
+/// goes to `revalidated` for the list to swap in. Bodies are only served at the revision
+/// the latest list or post reported, so they never need refetching. When esa can't be
+/// reached, or when started with `--offline`, everything is read from the cache and marked
+/// stale, and stars and watches are queued until esa answers again.
+#[derive(Clone)]
+pub struct CachedClient<G> {
+    inner: G,
+    cache: PostCache,
+    /// Never contact esa, as with `--offline`.
+    offline: bool,
+    /// Where pages refetched after answering from the cache are sent.
+    revalidated: UnboundedSender<FetchedPage>,
+    /// Whether the last request reached esa, shared by the clones.
+    reachable: Arc<AtomicBool>,
+    /// Set while queued actions are being sent, so two clones don't send them twice.
+    replaying: Arc<AtomicBool>,
+}
+
+impl<G> CachedClient<G> {
+    pub fn new(
+        inner: G,
+        cache: PostCache,
+        offline: bool,
+        revalidated: UnboundedSender<FetchedPage>,
+    ) -> Self {
+        Self {
+            inner,
+            cache,
+            offline,
+            revalidated,
+            reachable: Arc::new(AtomicBool::new(true)),
+            replaying: Arc::default(),
+        }
+    }
+
+    fn is_offline(&self) -> bool {
+        self.offline || !self.reachable.load(Ordering::Relaxed)
+    }
+
+    /// Whether `e` means esa couldn't be reached, in which case the cache should answer.
+    fn went_offline(&self, e: &anyhow::Error) -> bool {
+        let unreachable = e.chain().any(|cause| {
+            cause
+                .downcast_ref::<reqwest::Error>()
+                .is_some_and(|e| e.is_connect() || e.is_timeout())
+        });
+        if unreachable {
+            self.reachable.store(false, Ordering::Relaxed);
+            eprintln!("failed to reach esa, reading from the cache: {}", e);
+        }
+        unreachable
+    }
+
+    /// The cached page if there is one, else the cached posts filtered with the query here.
+    fn offline_posts(&self, request: &ListRequest) -> PostListPage {
+        let mut result = self.cache.load_list(request).unwrap_or_else(|| {
+            local_page(
+                self.cache.cached_posts(),
+                &LocalQuery::parse(request.query.as_deref().unwrap_or_default()),
+                request.sort,
+                request.order,
+                request.per_page.unwrap_or(DEFAULT_PER_PAGE),
+                request.page,
+            )
+        });
+        for post in &mut result.posts {
+            self.cache.apply_pending(post);
+        }
+        result.stale = true;
+        result
+    }
+}
+
+impl<G> CachedClient<G>
+where
+    G: EsaClientHttpGateway + Clone + 'static,
+{
+    /// Notes that esa answered and sends the actions queued while it didn't.
+    async fn reconnected(&self) {
+        self.reachable.store(true, Ordering::Relaxed);
+        if self.replaying.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        for action in self.cache.load_pending() {
+            match send(&self.inner, action).await {
+                Ok(()) => {}
+                Err(e) if self.went_offline(&e) => break,
+                Err(e) => eprintln!("failed to send queued {:?}: {}", action, e),
+            }
+            if let Err(e) = self.cache.dequeue(action) {
+                eprintln!("failed to update queued actions: {}", e);
+            }
+        }
+        self.replaying.store(false, Ordering::Relaxed);
+    }
+
+    /// Sends a star or watch, or keeps it for later when esa can't be reached.
+    async fn send_or_queue(&self, action: PendingAction) -> anyhow::Result<()> {
+        if !self.offline {
+            match send(&self.inner, action).await {
+                Ok(()) => {
+                    self.reconnected().await;
+                    return Ok(());
+                }
+                Err(e) if !self.went_offline(&e) => return Err(e),
+                Err(_) => {}
+            }
+        }
+        self.cache.queue(action)
+    }
+}
+
+#[async_trait::async_trait]
+impl<G> EsaClientHttpGateway for CachedClient<G>
+where
+    G: EsaClientHttpGateway + Clone + 'static,
+{
+    async fn fetch_posts(
+        &self,
+        query: Option<String>,
+        sort: Option<PostSort>,
+        order: Option<SortOrder>,
+        per_page: Option<u32>,
+        page: i32,
+    ) -> anyhow::Result<PostListPage> {
+        let request = ListRequest {
+            query: query.clone(),
+            sort,
+            order,
+            per_page,
+            page,
+        };
+        if self.offline {
+            return Ok(self.offline_posts(&request));
+        }
+        if let Some(mut cached) = self.cache.load_list(&request) {
+            cached.stale = self.is_offline();
+            let client = self.clone();
+            tokio::spawn(async move {
+                match client
+                    .inner
+                    .fetch_posts(query, sort, order, per_page, page)
+                    .await
+                {
+                    Ok(fresh) => {
+                        if let Err(e) = client.cache.store_list(&request, &fresh) {
+                            eprintln!("failed to cache posts: {}", e);
+                        }
+                        client.reconnected().await;
+                        // the app has quit when nobody receives
+                        let _ = client.revalidated.send(FetchedPage {
+                            request,
+                            result: Ok(fresh),
+                        });
+                    }
+                    Err(e) if client.went_offline(&e) => {}
+                    Err(e) => eprintln!("failed to revalidate posts: {}", e),
+                }
+            });
+            return Ok(cached);
+        }
+        match self
+            .inner
+            .fetch_posts(query, sort, order, per_page, page)
+            .await
+        {
+            Ok(fresh) => {
+                if let Err(e) = self.cache.store_list(&request, &fresh) {
+                    eprintln!("failed to cache posts: {}", e);
+                }
+                self.reconnected().await;
+                Ok(fresh)
+            }
+            Err(e) if self.went_offline(&e) => Ok(self.offline_posts(&request)),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn fetch_posts_with_bodies(
+        &self,
+        query: Option<String>,
+        sort: PostSort,
+        order: SortOrder,
+        page: i32,
+    ) -> anyhow::Result<MirrorPage> {
+        self.inner
+            .fetch_posts_with_bodies(query, sort, order, page)
+            .await
+    }
+
+    async fn fetch_post(&self, post_number: &PostNumber) -> Option<Post> {
+        if !self.offline
+            && let Some(post) = self.inner.fetch_post(post_number).await
+        {
+            self.cache
+                .record_revision(&post.post_number, post.revision_number);
+            return Some(post);
+        }
+        let mut post = self
+            .cache
+            .cached_posts()
+            .into_iter()
+            .find(|post| post.post_number == *post_number)?;
+        self.cache.apply_pending(&mut post);
+        Some(post)
+    }
+
+    async fn fetch_post_content(&self, post_number: &PostNumber) -> anyhow::Result<PostBody> {
+        if let Some(mut body) = self.cache.load_body(post_number) {
+            body.stale = self.is_offline();
+            return Ok(body);
+        }
+        if !self.offline {
+            match self.inner.fetch_post_content(post_number).await {
+                Ok(body) => {
+                    if let Err(e) = self.cache.store_body(post_number, &body) {
+                        eprintln!("failed to cache post content: {}", e);
+                    }
+                    self.reachable.store(true, Ordering::Relaxed);
+                    return Ok(body);
+                }
+                Err(e) if !self.went_offline(&e) => return Err(e),
+                Err(_) => {}
+            }
+        }
+        let mut body = self
+            .cache
+            .load_latest_body(post_number)
+            .ok_or_else(|| anyhow::anyhow!("{} is not in the cache", post_number))?;
+        body.stale = true;
+        Ok(body)
+    }
+
+    async fn update_post_body(
+        &self,
+        post_number: &PostNumber,
+        original: &PostBody,
+        body_md: String,
+        message: &str,
+    ) -> anyhow::Result<PostBody> {
+        if self.offline {
+            anyhow::bail!("posts can't be edited offline");
+        }
+        let body = self
+            .inner
+            .update_post_body(post_number, original, body_md, message)
+            .await?;
+        if let Err(e) = self.cache.store_body(post_number, &body) {
+            eprintln!("failed to cache post content: {}", e);
+        }
+        Ok(body)
+    }
+
+    async fn watch_post(&self, post_number: &PostNumber) -> anyhow::Result<()> {
+        self.send_or_queue(PendingAction::Watch(post_number.to_i32()))
+            .await
+    }
+
+    async fn unwatch_post(&self, post_number: &PostNumber) -> anyhow::Result<()> {
+        self.send_or_queue(PendingAction::Unwatch(post_number.to_i32()))
+            .await
+    }
+
+    async fn star_post(&self, post_number: &PostNumber) -> anyhow::Result<()> {
+        self.send_or_queue(PendingAction::Star(post_number.to_i32()))
+            .await
+    }
+
+    async fn unstar_post(&self, post_number: &PostNumber) -> anyhow::Result<()> {
+        self.send_or_queue(PendingAction::Unstar(post_number.to_i32()))
+            .await
+    }
+
+    async fn fetch_custom_emojis(&self) -> anyhow::Result<Vec<String>> {
+        if self.offline {
+            return Ok(vec![]);
+        }
+        self.inner.fetch_custom_emojis().await
+    }
+
+    async fn fetch_attachment(&self, url: &str, max_bytes: u64) -> anyhow::Result<Vec<u8>> {
+        if self.offline {
+            anyhow::bail!("attachments can't be downloaded offline");
+        }
+        self.inner.fetch_attachment(url, max_bytes).await
+    }
+}
+
+async fn send(gateway: &impl EsaClientHttpGateway, action: PendingAction) -> anyhow::Result<()> {
+    let post_number = action.post_number();
+    match action {
+        PendingAction::Star(_) => gateway.star_post(&post_number).await,
+        PendingAction::Unstar(_) => gateway.unstar_post(&post_number).await,
+        PendingAction::Watch(_) => gateway.watch_post(&post_number).await,
+        PendingAction::Unwatch(_) => gateway.unwatch_post(&post_number).await,
+    }
+}
+
+/// One page of `posts` matching `query`, ordered by the view's sort or else the query's.
+fn local_page(
+    posts: Vec<Post>,
+    query: &LocalQuery,
+    sort: Option<PostSort>,
+    order: Option<SortOrder>,
+    per_page: u32,
+    page: i32,
+) -> PostListPage {
+    let mut posts: Vec<Post> = posts
+        .into_iter()
+        .filter(|post| query.matches(post))
+        .collect();
+    let (query_sort, query_order) = query.sort().unzip();
+    sort_posts(
+        &mut posts,
+        sort.or(query_sort).unwrap_or_default(),
+        order.or(query_order.flatten()).unwrap_or_default(),
+    );
+    let total = posts.len();
+    let per_page = per_page.max(1) as usize;
+    let start = (page.max(1) as usize - 1) * per_page;
+    PostListPage {
+        posts: posts.into_iter().skip(start).take(per_page).collect(),
+        next_page: (start + per_page < total).then_some(page + 1),
+        total_count: Some(total as u32),
+        stale: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::sample_post;
+
+    fn post(number: i32, wip: bool, stars: u32) -> Post {
+        Post {
+            post_number: number.into(),
+            wip,
+            stars,
+            ..sample_post()
+        }
+    }
+
+    #[test]
+    fn test_local_page() {
+        let posts = vec![
+            post(1, false, 2),
+            post(2, true, 8),
+            post(3, false, 5),
+            post(4, false, 9),
+        ];
+        let query = LocalQuery::parse("wip:false sort:stars-asc");
+
+        let first = local_page(posts.clone(), &query, None, None, 2, 1);
+        let numbers: Vec<i32> = first.posts.iter().map(|p| p.post_number.to_i32()).collect();
+        assert_eq!(numbers, vec![1, 3]);
+        assert_eq!((first.next_page, first.total_count), (Some(2), Some(3)));
+
+        // the view's own order wins over the query's
+        let second = local_page(posts, &query, None, Some(SortOrder::Desc), 2, 2);
+        let numbers: Vec<i32> = second
+            .posts
+            .iter()
+            .map(|p| p.post_number.to_i32())
+            .collect();
+        assert_eq!(numbers, vec![1]);
+        assert_eq!(second.next_page, None);
+    }
+}
@@ -1,15 +1,31 @@
-use crate::domains::{Post, PostNumber, Tag, User, UserId};
-use chrono::DateTime;
+use crate::domains::{
+    Category, Comment, Post, PostBody, PostKind, PostNumber, PostSort, SortOrder, Tag, User, UserId,
+};
+use chrono::{DateTime, Utc};
 use esa_api::apis::{
     configuration::Configuration,
     default_api::{
-        self, V1TeamsTeamNamePostsGetParams,
-        V1TeamsTeamNamePostsPostNumberStarDeleteParams,
+        self, V1TeamsTeamNameEmojisGetParams, V1TeamsTeamNamePostsGetParams,
+        V1TeamsTeamNamePostsPostNumberPatchParams, V1TeamsTeamNamePostsPostNumberStarDeleteParams,
         V1TeamsTeamNamePostsPostNumberStarPostParams,
         V1TeamsTeamNamePostsPostNumberWatchDeleteParams,
         V1TeamsTeamNamePostsPostNumberWatchPostParams,
     },
 };
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use url::Url;
+
+mod cached;
+
+pub use cached::CachedClient;
+
+const PRIVATE_FILES_HOST: &str = "files.esa.io";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Requests taking longer fail, so a stalled connection falls back to the cache.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Attachments may be large, so they get longer than API requests.
+const ATTACHMENT_TIMEOUT: Duration = Duration::from_secs(120);
 
 #[derive(Clone, Debug)]
 pub struct EsaClient {
@@ -17,15 +33,96 @@ pub struct EsaClient {
     conf: Configuration,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PostListPage {
     pub posts: Vec<Post>,
     pub next_page: Option<i32>,
+    /// Posts matching the query across all pages.
+    pub total_count: Option<u32>,
+    /// Read from the cache while esa couldn't be reached, so it may be out of date.
+    #[serde(skip)]
+    pub stale: bool,
+}
+
+/// The view and page a list page was asked for, so a page arriving later can be matched
+/// to what the list shows by then.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ListRequest {
+    pub query: Option<String>,
+    pub sort: Option<PostSort>,
+    pub order: Option<SortOrder>,
+    pub per_page: Option<u32>,
+    pub page: i32,
+}
+
+/// A page of the list fetched in the background.
+#[derive(Debug)]
+pub struct FetchedPage {
+    pub request: ListRequest,
+    pub result: anyhow::Result<PostListPage>,
+}
+
+/// A page of posts together with their bodies and comments, for mirroring the whole team.
+#[derive(Clone, Debug)]
+pub struct MirrorPage {
+    pub posts: Vec<MirroredPost>,
+    pub next_page: Option<i32>,
+    pub total_count: Option<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MirroredPost {
+    pub post: Post,
+    pub body: PostBody,
+    pub comments: Vec<Comment>,
+}
+
+/// esa refused the request because too many were made recently; try again later.
+#[derive(Debug, PartialEq)]
+pub struct RateLimited {
+    /// How long esa asked to wait, when it said.
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimited {
+    /// Reads the wait from `Retry-After`, in seconds or as a date, or else from
+    /// `X-RateLimit-Reset`, the time the limit is lifted in seconds since the epoch.
+    fn from_headers(headers: &reqwest::header::HeaderMap, now: DateTime<Utc>) -> Self {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let until = |at: DateTime<Utc>| (at - now).to_std().ok();
+        let retry_after = header("retry-after")
+            .and_then(|value| {
+                let value = value.trim();
+                value
+                    .parse()
+                    .ok()
+                    .map(Duration::from_secs)
+                    .or_else(|| until(DateTime::parse_from_rfc2822(value).ok()?.to_utc()))
+            })
+            .or_else(|| {
+                let reset = header("x-ratelimit-reset")?.trim().parse().ok()?;
+                until(DateTime::from_timestamp(reset, 0)?)
+            });
+        Self { retry_after }
+    }
 }
 
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("rate limited by esa")
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
 impl EsaClient {
     pub fn new(team_name: &str, api_token: &str) -> Self {
         let mut conf = Configuration::new();
+        conf.client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("failed to build the HTTP client");
         conf.api_key = Some(esa_api::apis::configuration::ApiKey {
             prefix: None,
             key: api_token.to_string(),
@@ -40,17 +137,44 @@ impl EsaClient {
 
 #[async_trait::async_trait]
 pub trait EsaClientHttpGateway: Send + Sync {
+    /// A page of posts without their bodies, failing with [`RateLimited`] when esa asks to
+    /// slow down.
     async fn fetch_posts(
         &self,
         query: Option<String>,
+        sort: Option<PostSort>,
+        order: Option<SortOrder>,
+        per_page: Option<u32>,
         page: i32,
     ) -> anyhow::Result<PostListPage>;
+    /// Posts with their bodies and comments, 100 at a time, failing with [`RateLimited`] when
+    /// esa asks to slow down.
+    async fn fetch_posts_with_bodies(
+        &self,
+        query: Option<String>,
+        sort: PostSort,
+        order: SortOrder,
+        page: i32,
+    ) -> anyhow::Result<MirrorPage>;
     async fn fetch_post(&self, post_number: &PostNumber) -> Option<Post>;
-    async fn fetch_post_content(&self, post_number: &PostNumber) -> anyhow::Result<String>;
+    async fn fetch_post_content(&self, post_number: &PostNumber) -> anyhow::Result<PostBody>;
+    /// Replaces the body, failing when the post has been revised since `original` was read.
+    /// If esa still had to merge it with a concurrent edit, the saved body is marked conflicted.
+    async fn update_post_body(
+        &self,
+        post_number: &PostNumber,
+        original: &PostBody,
+        body_md: String,
+        message: &str,
+    ) -> anyhow::Result<PostBody>;
     async fn watch_post(&self, post_number: &PostNumber) -> anyhow::Result<()>;
     async fn unwatch_post(&self, post_number: &PostNumber) -> anyhow::Result<()>;
     async fn star_post(&self, post_number: &PostNumber) -> anyhow::Result<()>;
     async fn unstar_post(&self, post_number: &PostNumber) -> anyhow::Result<()>;
+    /// Codes and aliases of the team's custom emojis.
+    async fn fetch_custom_emojis(&self) -> anyhow::Result<Vec<String>>;
+    /// Downloads an attached file, refusing files larger than `max_bytes`.
+    async fn fetch_attachment(&self, url: &str, max_bytes: u64) -> anyhow::Result<Vec<u8>>;
 }
 
 #[async_trait::async_trait]
@@ -58,18 +182,24 @@ impl EsaClientHttpGateway for EsaClient {
     async fn fetch_posts(
         &self,
         query: Option<String>,
+        sort: Option<PostSort>,
+        order: Option<SortOrder>,
+        per_page: Option<u32>,
         page: i32,
     ) -> anyhow::Result<PostListPage> {
         let params = V1TeamsTeamNamePostsGetParams {
             team_name: self.team_name.to_string(),
-            q: query.or_else(|| Some("sort:updated".to_string())),
+            q: query,
             include: None,
-            sort: None,
-            order: None,
+            sort: sort.map(|sort| sort.as_str().to_string()),
+            order: order.map(|order| order.as_str().to_string()),
             page: Some(page),
+            per_page: per_page.map(|per_page| per_page as i32),
         };
 
-        let response = default_api::v1_teams_team_name_posts_get(&self.conf, params).await?;
+        let response = default_api::v1_teams_team_name_posts_get(&self.conf, params)
+            .await
+            .map_err(rate_limited)?;
         let response_posts = response.posts.unwrap_or_default();
         let mut posts = vec![];
         for post in response_posts {
@@ -81,6 +211,41 @@ impl EsaClientHttpGateway for EsaClient {
         Ok(PostListPage {
             posts,
             next_page: response.next_page,
+            total_count: response.total_count.map(|count| count.max(0) as u32),
+            stale: false,
+        })
+    }
+
+    async fn fetch_posts_with_bodies(
+        &self,
+        query: Option<String>,
+        sort: PostSort,
+        order: SortOrder,
+        page: i32,
+    ) -> anyhow::Result<MirrorPage> {
+        let params = V1TeamsTeamNamePostsGetParams {
+            team_name: self.team_name.to_string(),
+            q: query,
+            include: Some("comments".to_string()),
+            sort: Some(sort.as_str().to_string()),
+            order: Some(order.as_str().to_string()),
+            page: Some(page),
+            per_page: Some(100),
+        };
+        let response = default_api::v1_teams_team_name_posts_get(&self.conf, params)
+            .await
+            .map_err(rate_limited)?;
+        let mut posts = vec![];
+        for post in response.posts.unwrap_or_default() {
+            match convert_mirrored_post(post) {
+                Ok(post) => posts.push(post),
+                Err(e) => eprintln!("failed to convert post: {}", e),
+            }
+        }
+        Ok(MirrorPage {
+            posts,
+            next_page: response.next_page,
+            total_count: response.total_count.map(|count| count.max(0) as u32),
         })
     }
 
@@ -99,7 +264,7 @@ impl EsaClientHttpGateway for EsaClient {
         convert_post(response).ok()
     }
 
-    async fn fetch_post_content(&self, post_number: &PostNumber) -> anyhow::Result<String> {
+    async fn fetch_post_content(&self, post_number: &PostNumber) -> anyhow::Result<PostBody> {
         let params = esa_api::apis::default_api::V1TeamsTeamNamePostsPostNumberGetParams {
             team_name: self.team_name.to_string(),
             post_number: post_number.to_i32(),
@@ -110,10 +275,47 @@ impl EsaClientHttpGateway for EsaClient {
             &self.conf, params,
         )
         .await?;
-        let content = response
-            .body_md
-            .ok_or_else(|| anyhow::anyhow!("missing body_md in post"))?;
-        Ok(content)
+        convert_post_body(response)
+    }
+
+    async fn update_post_body(
+        &self,
+        post_number: &PostNumber,
+        original: &PostBody,
+        body_md: String,
+        message: &str,
+    ) -> anyhow::Result<PostBody> {
+        // esa merges concurrent edits instead of rejecting them, so check the revision first
+        let current = self.fetch_post_content(post_number).await?;
+        if current.revision_number != original.revision_number {
+            anyhow::bail!(
+                "post {} was updated to revision {} after revision {} was loaded",
+                post_number,
+                current.revision_number,
+                original.revision_number
+            );
+        }
+
+        let mut original_revision = esa_api::models::OriginalRevision::new();
+        original_revision.body_md = Some(original.body_md.clone());
+        original_revision.number = Some(original.revision_number);
+        original_revision.user = Some(original.revised_by.0.clone());
+        let mut post = esa_api::models::UpdatePostRequestPost::new();
+        post.body_md = Some(body_md);
+        post.message = Some(message.to_string());
+        post.original_revision = Some(Box::new(original_revision));
+        let params = V1TeamsTeamNamePostsPostNumberPatchParams {
+            team_name: self.team_name.to_string(),
+            post_number: post_number.to_i32(),
+            update_post_request: esa_api::models::UpdatePostRequest::new(post),
+        };
+
+        let response =
+            default_api::v1_teams_team_name_posts_post_number_patch(&self.conf, params).await?;
+        let overlapped = response.overlapped == Some(true);
+        let mut body = convert_post_body(response)?;
+        body.conflicted = overlapped;
+        Ok(body)
     }
 
     async fn watch_post(&self, post_number: &PostNumber) -> anyhow::Result<()> {
@@ -156,6 +358,66 @@ impl EsaClientHttpGateway for EsaClient {
         default_api::v1_teams_team_name_posts_post_number_star_delete(&self.conf, params).await?;
         Ok(())
     }
+
+    async fn fetch_custom_emojis(&self) -> anyhow::Result<Vec<String>> {
+        let params = V1TeamsTeamNameEmojisGetParams {
+            team_name: self.team_name.to_string(),
+            include: None,
+        };
+
+        let response = default_api::v1_teams_team_name_emojis_get(&self.conf, params).await?;
+        let codes = response
+            .emojis
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|emoji| {
+                emoji
+                    .code
+                    .into_iter()
+                    .chain(emoji.aliases.unwrap_or_default())
+            })
+            .collect();
+        Ok(codes)
+    }
+
+    async fn fetch_attachment(&self, url: &str, max_bytes: u64) -> anyhow::Result<Vec<u8>> {
+        let parsed: Url = url.parse()?;
+        let mut request = self
+            .conf
+            .client
+            .get(parsed.as_str())
+            .timeout(ATTACHMENT_TIMEOUT);
+        // files.esa.io serves private attachments; other hosts never get the token
+        if parsed.host_str() == Some(PRIVATE_FILES_HOST)
+            && let Some(token) = &self.conf.bearer_access_token
+        {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await?.error_for_status()?;
+        if response.content_length().is_some_and(|len| len > max_bytes) {
+            anyhow::bail!("attachment is larger than {} bytes", max_bytes);
+        }
+        let bytes = response.bytes().await?;
+        if bytes.len() as u64 > max_bytes {
+            anyhow::bail!("attachment is larger than {} bytes", max_bytes);
+        }
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Turns a 429 into [`RateLimited`], so callers can wait as long as esa asks and retry.
+fn rate_limited<T>(e: esa_api::apis::Error<T>) -> anyhow::Error
+where
+    T: std::fmt::Debug + Send + Sync + 'static,
+{
+    match &e {
+        esa_api::apis::Error::ResponseError(content)
+            if content.status == reqwest::StatusCode::TOO_MANY_REQUESTS =>
+        {
+            anyhow::Error::new(RateLimited::from_headers(&content.headers, Utc::now()))
+        }
+        _ => e.into(),
+    }
 }
 
 fn convert_post(post: esa_api::models::Post) -> anyhow::Result<Post> {
@@ -163,6 +425,11 @@ fn convert_post(post: esa_api::models::Post) -> anyhow::Result<Post> {
         number: Some(post_number),
         name,
         full_name,
+        wip,
+        category,
+        kind,
+        revision_number,
+        message,
         created_at: Some(created_at),
         updated_at: Some(updated_at),
         tags,
@@ -173,6 +440,9 @@ fn convert_post(post: esa_api::models::Post) -> anyhow::Result<Post> {
         created_by: Some(created_by),
         updated_by: Some(updated_by),
         url: Some(url),
+        tasks_count,
+        done_tasks_count,
+        comments_count,
         ..
     } = post
     else {
@@ -196,11 +466,27 @@ fn convert_post(post: esa_api::models::Post) -> anyhow::Result<Post> {
     let created_by = convert_user(*created_by);
     let updated_by = convert_user(*updated_by);
     let url = url.parse()?;
+    let tasks_count = tasks_count.unwrap_or(0).max(0) as u32;
+    let done_tasks_count = done_tasks_count.unwrap_or(0).max(0) as u32;
+    let comments_count = comments_count.unwrap_or(0).max(0) as u32;
+    let wip = wip.unwrap_or(false);
+    let category = category.as_deref().and_then(Category::parse);
+    let kind = match kind {
+        Some(esa_api::models::post::Kind::Flow) => PostKind::Flow,
+        Some(esa_api::models::post::Kind::Stock) | None => PostKind::Stock,
+    };
+    let revision_number = revision_number.unwrap_or(0);
+    let message = message.unwrap_or_default();
 
     Ok(Post {
         post_number,
         name,
         full_name,
+        category,
+        wip,
+        kind,
+        revision_number,
+        message,
         stars,
         starred,
         tags,
@@ -211,6 +497,72 @@ fn convert_post(post: esa_api::models::Post) -> anyhow::Result<Post> {
         created_by,
         updated_by,
         url,
+        tasks_count,
+        done_tasks_count,
+        comments_count,
+    })
+}
+
+fn convert_post_body(post: esa_api::models::Post) -> anyhow::Result<PostBody> {
+    let esa_api::models::Post {
+        body_md: Some(body_md),
+        revision_number: Some(revision_number),
+        updated_by,
+        ..
+    } = post
+    else {
+        return Err(anyhow::anyhow!(
+            "missing body_md or revision_number in post"
+        ));
+    };
+    let revised_by = updated_by
+        .and_then(|user| user.screen_name)
+        .map(UserId)
+        .unwrap_or_else(|| UserId(String::new()));
+    Ok(PostBody {
+        body_md,
+        revision_number,
+        revised_by,
+        stale: false,
+        conflicted: false,
+    })
+}
+
+fn convert_mirrored_post(post: esa_api::models::Post) -> anyhow::Result<MirroredPost> {
+    let comments = post
+        .comments
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|comment| match convert_comment(comment) {
+            Ok(comment) => Some(comment),
+            Err(e) => {
+                eprintln!("failed to convert comment: {}", e);
+                None
+            }
+        })
+        .collect();
+    Ok(MirroredPost {
+        post: convert_post(post.clone())?,
+        body: convert_post_body(post)?,
+        comments,
+    })
+}
+
+fn convert_comment(comment: esa_api::models::Comment) -> anyhow::Result<Comment> {
+    let esa_api::models::Comment {
+        body_md: Some(body_md),
+        created_at: Some(created_at),
+        created_by: Some(created_by),
+        ..
+    } = comment
+    else {
+        return Err(anyhow::anyhow!("missing required fields in Comment"));
+    };
+    Ok(Comment {
+        body_md,
+        created_by: convert_user(*created_by),
+        created_at: DateTime::parse_from_rfc3339(&created_at)?.to_utc(),
     })
 }
 
@@ -223,3 +575,92 @@ fn convert_user(user_summary: esa_api::models::UserSummary) -> User {
         id: UserId(screen_name.unwrap_or_else(|| "(no id)".to_string())),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fixtures follow the shape of esa's `GET /v1/teams/:team_name/posts/:post_number`
+    // responses, with the team and people renamed.
+    fn fixture(json: &str) -> Post {
+        let post: esa_api::models::Post = serde_json::from_str(json).unwrap();
+        convert_post(post).unwrap()
+    }
+
+    #[test]
+    fn test_rate_limited_from_headers() {
+        use reqwest::header::{HeaderMap, HeaderValue};
+        let now = DateTime::parse_from_rfc3339("2026-05-01T12:00:00Z")
+            .unwrap()
+            .to_utc();
+        let wait = |pairs: &[(&'static str, &'static str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, HeaderValue::from_static(value));
+            }
+            RateLimited::from_headers(&headers, now).retry_after
+        };
+        assert_eq!(
+            wait(&[("retry-after", "120")]),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            wait(&[("retry-after", "Fri, 01 May 2026 12:05:00 GMT")]),
+            Some(Duration::from_secs(300))
+        );
+        assert_eq!(
+            wait(&[("x-ratelimit-reset", "1777637100")]),
+            Some(Duration::from_secs(300))
+        );
+        assert_eq!(
+            wait(&[("retry-after", "30"), ("x-ratelimit-reset", "1777637100")]),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(wait(&[("x-ratelimit-reset", "1777636000")]), None);
+        assert_eq!(wait(&[]), None);
+    }
+
+    #[test]
+    fn test_convert_stock_post() {
+        let post = fixture(include_str!("fixtures/post.json"));
+        assert_eq!(
+            post.category.as_ref().map(Category::as_str),
+            Some("dev/ops")
+        );
+        assert_eq!(post.kind, PostKind::Stock);
+        assert!(!post.wip);
+        assert_eq!(post.revision_number, 7);
+        assert_eq!(post.message, "手順を更新");
+        assert_eq!(post.comments_count, 2);
+        assert_eq!((post.done_tasks_count, post.tasks_count), (1, 2));
+        assert_eq!(post.updated_by.id.0, "hanako");
+    }
+
+    #[test]
+    fn test_convert_mirrored_post() {
+        let mut post: esa_api::models::Post =
+            serde_json::from_str(include_str!("fixtures/post.json")).unwrap();
+        // esa always sends the body, but one missing should only drop that comment
+        post.comments.as_mut().unwrap()[1].body_md = None;
+        let mirrored = convert_mirrored_post(post).unwrap();
+        assert_eq!(mirrored.post.post_number.to_i32(), 42);
+        assert_eq!(
+            mirrored.body.body_md,
+            "# 手順\n- [x] ビルド\n- [ ] リリース\n"
+        );
+        assert_eq!(mirrored.body.revised_by.0, "hanako");
+        assert_eq!(mirrored.comments.len(), 1);
+        assert_eq!(mirrored.comments[0].body_md, "ロールバックの手順も欲しい");
+        assert_eq!(mirrored.comments[0].created_by.id.0, "hanako");
+    }
+
+    #[test]
+    fn test_convert_wip_flow_post() {
+        let post = fixture(include_str!("fixtures/post_wip_flow.json"));
+        assert!(post.category.is_none());
+        assert_eq!(post.kind, PostKind::Flow);
+        assert!(post.wip);
+        assert_eq!(post.revision_number, 1);
+        assert_eq!(post.comments_count, 0);
+    }
+}
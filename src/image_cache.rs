@@ -0,0 +1,77 @@
+use std::{
+    fs,
+    hash::Hasher,
+    path::{Path, PathBuf},
+};
+
+use fnv::FnvHasher;
+
+/// Downloaded images, kept under `cache/images` next to the config file.
+#[derive(Debug, Clone)]
+pub struct ImageCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl ImageCache {
+    pub fn next_to(config_path: &Path, max_megabytes: u64) -> Self {
+        Self {
+            dir: config_path.with_file_name("cache").join("images"),
+            max_bytes: max_megabytes * 1024 * 1024,
+        }
+    }
+
+    pub fn load(&self, url: &str) -> Option<Vec<u8>> {
+        fs::read(self.path_for(url)).ok()
+    }
+
+    pub fn store(&self, url: &str, bytes: &[u8]) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path_for(url), bytes)?;
+        self.prune()
+    }
+
+    pub fn clear(&self) -> anyhow::Result<()> {
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the least recently modified images until the total fits the limit.
+    fn prune(&self) -> anyhow::Result<()> {
+        let mut files = vec![];
+        for entry in fs::read_dir(&self.dir)?.flatten() {
+            let metadata = entry.metadata()?;
+            files.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+        let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+        files.sort();
+        for (_, len, path) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= len;
+        }
+        Ok(())
+    }
+
+    /// Names the file after a hash of the URL and keeps the URL's extension.
+    /// FNV is unseeded, so the name stays the same across runs.
+    fn path_for(&self, url: &str) -> PathBuf {
+        let mut hasher = FnvHasher::default();
+        hasher.write(url.as_bytes());
+        let file_name = format!("{:016x}", hasher.finish());
+        let extension = url
+            .rsplit('/')
+            .next()
+            .and_then(|name| name.rsplit_once('.'))
+            .map(|(_, ext)| ext)
+            .filter(|ext| ext.len() <= 5 && ext.chars().all(|c| c.is_ascii_alphanumeric()));
+        match extension {
+            Some(ext) => self.dir.join(format!("{}.{}", file_name, ext)),
+            None => self.dir.join(file_name),
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use std::{fs, path::Path};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+/// `None` when the file is missing or can't be parsed.
+pub fn read<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let json = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json)
+        .inspect_err(|e| eprintln!("failed to parse {}: {}", path.display(), e))
+        .ok()
+}
+
+/// Creates the parent directory if needed.
+pub fn write(path: &Path, value: &impl Serialize) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string(value)?)?;
+    Ok(())
+}
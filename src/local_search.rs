@@ -0,0 +1,74 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    domains::{Document, Pattern, Post, SearchHit, SearchIndex, rank},
+    json_file,
+    post_cache::PostCache,
+};
+
+/// Full-text search over the posts in the cache and the mirror written by `sync`.
+///
+/// The index lives at `cache/teams/<team>/search_index.json`. On each search, posts that are
+/// new, have a new revision or comment count, or have a newly read body are reindexed.
+#[derive(Debug, Clone)]
+pub struct LocalSearch {
+    cache: PostCache,
+    /// Reused once loaded, with only the changed posts reindexed.
+    index: Option<SearchIndex>,
+}
+
+impl LocalSearch {
+    pub fn new(cache: PostCache) -> Self {
+        Self { cache, index: None }
+    }
+
+    /// Returns the matching posts, best score first.
+    pub fn search(&mut self, pattern: &Pattern) -> Vec<SearchHit> {
+        let posts = self.cache.cached_posts();
+        let bodies = self.cache.body_numbers();
+        let candidates = self.current_index(&posts, &bodies).candidates(pattern);
+        let mut posts: HashMap<i32, Post> = posts
+            .into_iter()
+            .map(|post| (post.post_number.to_i32(), post))
+            .collect();
+        let documents = candidates
+            .into_iter()
+            .filter_map(|number| posts.remove(&number))
+            .map(|post| self.document(post));
+        rank(documents, pattern)
+    }
+
+    fn current_index(&mut self, posts: &[Post], bodies: &HashSet<i32>) -> &SearchIndex {
+        let path = self.cache.search_index_path();
+        let mut index = self
+            .index
+            .take()
+            .or_else(|| json_file::read::<SearchIndex>(&path))
+            .unwrap_or_default();
+        if index.update(posts, bodies, |post| self.document(post.clone()))
+            && let Err(e) = json_file::write(&path, &index)
+        {
+            eprintln!("failed to save the search index: {}", e);
+        }
+        self.index.insert(index)
+    }
+
+    fn document(&self, post: Post) -> Document {
+        let body_md = self
+            .cache
+            .load_latest_body(&post.post_number)
+            .map(|body| body.body_md);
+        let comments = self
+            .cache
+            .mirror()
+            .comments(&post.post_number)
+            .into_iter()
+            .map(|comment| comment.body_md)
+            .collect();
+        Document {
+            post,
+            body_md,
+            comments,
+        }
+    }
+}
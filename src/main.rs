@@ -1,7 +1,17 @@
 mod app;
+mod browser;
+mod clipboard;
 mod domains;
+mod export;
 mod find_config;
 mod http_gateways;
+mod image_cache;
+mod json_file;
+mod local_search;
+mod mirror;
+mod post_cache;
+mod state_file;
+mod sync;
 mod widgets;
 
 #[cfg(test)]
@@ -13,24 +23,90 @@ extern crate insta;
 use app::App;
 use crossterm::{
     execute,
-    event::{KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags},
+    event::{
+        DisableMouseCapture, EnableMouseCapture, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
+use domains::Pattern;
+use export::{ExportOptions, HtmlExport, HtmlRenderer};
 use find_config::find_config_path;
+use http_gateways::EsaClient;
+use image_cache::ImageCache;
+use local_search::LocalSearch;
+use mirror::Mirror;
+use post_cache::PostCache;
 use ratatui::{DefaultTerminal, Terminal, backend::CrosstermBackend};
-use std::io;
+use ratatui_image::picker::Picker;
+use state_file::StateFile;
+use std::{
+    io::{self, IsTerminal},
+    path::PathBuf,
+};
 
-use crate::domains::{Config, Theme};
+use crate::{
+    domains::{Config, Theme, TimeDisplay, WorkspaceConfig},
+    widgets::InlineImages,
+};
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
-    let config = get_config().unwrap();
+    let (config, config_path) = get_config().unwrap();
     let (workspace_name, workspace) = config.current_workspace();
+    let post_cache = PostCache::next_to(
+        &config_path,
+        &workspace.team_name(),
+        config.cache.max_megabytes,
+    );
+    let image_cache = ImageCache::next_to(&config_path, config.cache.image_max_megabytes);
     let theme_config = config.get_theme(&workspace_name);
     let theme = Theme::from_config(&theme_config);
+    let html = HtmlRenderer::new(
+        theme.clone(),
+        TimeDisplay::new(config.display.timezone.as_deref(), false),
+        image_cache.clone(),
+    );
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let offline = args.iter().any(|arg| arg == "--offline");
+    args.retain(|arg| arg != "--offline");
+    if !args.is_empty() {
+        let command = Command {
+            workspace: &workspace,
+            post_cache: &post_cache,
+            image_cache: &image_cache,
+            client: EsaClient::new(&workspace.team_name(), &workspace.token()),
+            mirror: Mirror::next_to(&config_path, &workspace.team_name()),
+            html,
+            offline,
+        };
+        if let Err(e) = command.run(&args).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     theme.apply_to_md_tui();
     let mut terminal = init_terminal()?;
-    let res = App::new(&workspace, theme).run(&mut terminal).await;
+    let images = config.display.inline_images.then(|| {
+        InlineImages::new(
+            pick_image_protocol(),
+            image_cache,
+            &config.display,
+        )
+    });
+    let res = App::new(
+        &workspace,
+        &config,
+        images,
+        theme,
+        StateFile::next_to(&config_path),
+        post_cache,
+        HtmlExport::next_to(&config_path, &workspace.team_name(), html),
+        offline,
+    )
+    .run(&mut terminal)
+    .await;
     let restore_res = restore_terminal(&mut terminal);
     if let Err(err) = res {
         if let Err(restore_err) = restore_res {
@@ -42,7 +118,7 @@ async fn main() -> io::Result<()> {
     Ok(())
 }
 
-fn get_config() -> anyhow::Result<Config> {
+fn get_config() -> anyhow::Result<(Config, PathBuf)> {
     let res = find_config_path("esa-reader", "config.toml")?;
     if let Some(p) = &res.existing {
         println!("Using config file at: {}", p.display());
@@ -51,20 +127,97 @@ fn get_config() -> anyhow::Result<Config> {
         find_config::ensure_parent_dir(&res.recommended)?;
     }
     if let Some(config_path) = res.existing {
-        let config_str = std::fs::read_to_string(config_path)?;
+        let config_str = std::fs::read_to_string(&config_path)?;
         let config: domains::Config = toml::from_str(&config_str)?;
-        Ok(config)
+        Ok((config, config_path))
     } else {
         anyhow::bail!("config file not found");
     }
 }
 
+/// Runs subcommands without opening the UI.
+struct Command<'a> {
+    workspace: &'a WorkspaceConfig,
+    post_cache: &'a PostCache,
+    image_cache: &'a ImageCache,
+    client: EsaClient,
+    mirror: Mirror,
+    html: HtmlRenderer,
+    /// Set by `--offline`, which subcommands that go to esa refuse.
+    offline: bool,
+}
+
+impl Command<'_> {
+    async fn run(&self, args: &[String]) -> anyhow::Result<()> {
+        match args
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [command @ ("sync" | "export"), ..] if self.offline => {
+                anyhow::bail!("{} needs esa, so it can't be run with --offline", command)
+            }
+            ["cache", "clear"] => {
+                self.post_cache.clear()?;
+                self.image_cache.clear()?;
+                println!("cleared the cache");
+                Ok(())
+            }
+            ["sync"] => sync::sync(&self.client, &self.mirror, false).await,
+            ["sync", "--full"] => sync::sync(&self.client, &self.mirror, true).await,
+            ["grep", pattern] => self.grep(pattern),
+            ["export", options @ ..] => {
+                let options = ExportOptions::parse(options, &self.workspace.post_views)?;
+                export::export(&self.client, &options, &self.html).await
+            }
+            _ => anyhow::bail!(
+                "unknown command: {}\nusage: esa-reader [--offline] [cache clear | sync [--full] | grep <pattern> | export [--query <q> | --view <name>] --out <dir> [--format markdown|html|epub] [--attachments]]",
+                args.join(" ")
+            ),
+        }
+    }
+
+    /// Searches the cache and mirror for a regex and prints the best matches with context.
+    fn grep(&self, pattern: &str) -> anyhow::Result<()> {
+        const LIMIT: usize = 30;
+        let pattern = Pattern::parse(pattern)?;
+        let hits = LocalSearch::new(self.post_cache.clone()).search(&pattern);
+        let highlight = io::stdout().is_terminal();
+        for hit in hits.iter().take(LIMIT) {
+            println!("{} {}", hit.post.post_number, hit.post.full_name);
+            if let Some(snippet) = &hit.snippet {
+                let (before, rest) = snippet.text.split_at(snippet.highlight.start);
+                let (matched, after) = rest.split_at(snippet.highlight.len());
+                if highlight {
+                    println!(
+                        "    {}: {}\x1b[1;31m{}\x1b[0m{}",
+                        snippet.field.label(),
+                        before,
+                        matched,
+                        after
+                    );
+                } else {
+                    println!("    {}: {}", snippet.field.label(), snippet.text);
+                }
+            }
+        }
+        match hits.len() {
+            0 => println!("no posts matched"),
+            n if n > LIMIT => println!("… and {} more", n - LIMIT),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
 fn init_terminal() -> io::Result<DefaultTerminal> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(
         stdout,
         EnterAlternateScreen,
+        EnableMouseCapture,
         PushKeyboardEnhancementFlags(
             KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
                 | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES
@@ -78,11 +231,20 @@ fn init_terminal() -> io::Result<DefaultTerminal> {
     Ok(terminal)
 }
 
+/// Asks the terminal which image protocol it supports, falling back to half-blocks.
+fn pick_image_protocol() -> Picker {
+    Picker::from_query_stdio().unwrap_or_else(|e| {
+        eprintln!("failed to query terminal graphics support: {}", e);
+        Picker::halfblocks()
+    })
+}
+
 fn restore_terminal(terminal: &mut DefaultTerminal) -> io::Result<()> {
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         PopKeyboardEnhancementFlags,
+        DisableMouseCapture,
         LeaveAlternateScreen
     )?;
     terminal.show_cursor()?;
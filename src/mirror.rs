@@ -0,0 +1,146 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domains::{Comment, Post, PostBody, PostNumber},
+    json_file,
+};
+
+/// Every post of the team as fetched by `sync`, kept under `mirror/<team>` next to the config
+/// file.
+///
+/// Bodies are stored as plain Markdown in `posts/<number>.md`, comments in
+/// `comments/<number>.json`, and the posts themselves in `index.json`, keyed by number.
+#[derive(Debug, Clone)]
+pub struct Mirror {
+    dir: PathBuf,
+    /// The UI keeps using the `index.json` it read first.
+    index: Arc<OnceLock<BTreeMap<i32, Post>>>,
+}
+
+/// Sync progress, so an interrupted `sync` resumes from the page it stopped at.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncState {
+    /// When the last completed sync started.
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub in_progress: Option<SyncRun>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncRun {
+    pub started_at: DateTime<Utc>,
+    /// Narrows the sync to posts updated since the last one. `None` fetches every post.
+    pub query: Option<String>,
+    pub next_page: i32,
+}
+
+impl Mirror {
+    pub fn next_to(config_path: &Path, team_name: &str) -> Self {
+        Self {
+            dir: config_path.with_file_name("mirror").join(team_name),
+            index: Arc::default(),
+        }
+    }
+
+    pub fn posts(&self) -> &BTreeMap<i32, Post> {
+        self.index.get_or_init(|| self.load_index())
+    }
+
+    pub fn body(&self, post_number: &PostNumber) -> Option<PostBody> {
+        let post = self.posts().get(&post_number.to_i32())?;
+        let body_md = fs::read_to_string(self.body_path(post_number)).ok()?;
+        Some(PostBody {
+            body_md,
+            revision_number: post.revision_number,
+            revised_by: post.updated_by.id.clone(),
+            stale: false,
+            conflicted: false,
+        })
+    }
+
+    /// Reads the file every time, for callers that write it back.
+    pub fn load_index(&self) -> BTreeMap<i32, Post> {
+        json_file::read(&self.dir.join("index.json")).unwrap_or_default()
+    }
+
+    pub fn store_index(&self, index: &BTreeMap<i32, Post>) -> anyhow::Result<()> {
+        json_file::write(&self.dir.join("index.json"), index)
+    }
+
+    pub fn has_body(&self, post_number: &PostNumber) -> bool {
+        self.body_path(post_number).exists()
+    }
+
+    pub fn body_numbers(&self) -> HashSet<i32> {
+        let Ok(entries) = fs::read_dir(self.dir.join("posts")) else {
+            return HashSet::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                name.to_string_lossy().strip_suffix(".md")?.parse().ok()
+            })
+            .collect()
+    }
+
+    pub fn store_body(&self, post_number: &PostNumber, body_md: &str) -> anyhow::Result<()> {
+        let path = self.body_path(post_number);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, body_md)?;
+        Ok(())
+    }
+
+    pub fn comments(&self, post_number: &PostNumber) -> Vec<Comment> {
+        json_file::read(&self.comments_path(post_number)).unwrap_or_default()
+    }
+
+    pub fn store_comments(
+        &self,
+        post_number: &PostNumber,
+        comments: &[Comment],
+    ) -> anyhow::Result<()> {
+        json_file::write(&self.comments_path(post_number), &comments)
+    }
+
+    /// Deletes the body and comments of a post deleted on esa. The caller drops it from
+    /// `index.json`.
+    pub fn remove(&self, post_number: &PostNumber) -> anyhow::Result<()> {
+        for path in [self.body_path(post_number), self.comments_path(post_number)] {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load_state(&self) -> SyncState {
+        json_file::read(&self.dir.join("sync.json")).unwrap_or_default()
+    }
+
+    pub fn store_state(&self, state: &SyncState) -> anyhow::Result<()> {
+        json_file::write(&self.dir.join("sync.json"), state)
+    }
+
+    fn comments_path(&self, post_number: &PostNumber) -> PathBuf {
+        self.dir
+            .join("comments")
+            .join(format!("{}.json", post_number.to_i32()))
+    }
+
+    fn body_path(&self, post_number: &PostNumber) -> PathBuf {
+        self.dir
+            .join("posts")
+            .join(format!("{}.md", post_number.to_i32()))
+    }
+}
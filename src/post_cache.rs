@@ -0,0 +1,433 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    hash::Hasher,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use fnv::FnvHasher;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domains::{Post, PostBody, PostNumber},
+    http_gateways::PostListPage,
+    json_file,
+    mirror::Mirror,
+};
+
+/// Post bodies and list pages, cached under `cache/teams/<team>` next to the config file.
+///
+/// Bodies live at `posts/<number>-<revision>.json` and list pages in `lists`, named after a
+/// hash of their query. Stars and watches made offline queue up in `pending.json`. Posts
+/// missing from the cache are looked up in the mirror written by `sync`.
+#[derive(Debug, Clone)]
+pub struct PostCache {
+    dir: PathBuf,
+    mirror: Mirror,
+    max_bytes: u64,
+    /// The latest revision seen for each post, shared between clones.
+    revisions: Arc<Mutex<HashMap<i32, i32>>>,
+}
+
+impl PostCache {
+    pub fn next_to(config_path: &Path, team_name: &str, max_megabytes: u64) -> Self {
+        Self {
+            dir: config_path
+                .with_file_name("cache")
+                .join("teams")
+                .join(team_name),
+            mirror: Mirror::next_to(config_path, team_name),
+            max_bytes: max_megabytes * 1024 * 1024,
+            revisions: Arc::default(),
+        }
+    }
+
+    pub fn mirror(&self) -> &Mirror {
+        &self.mirror
+    }
+
+    /// Kept in the cache directory so clearing the cache drops the index too.
+    pub fn search_index_path(&self) -> PathBuf {
+        self.dir.join("search_index.json")
+    }
+
+    /// The page may be stale, so its revisions aren't recorded.
+    pub fn load_list(&self, key: &impl Serialize) -> Option<PostListPage> {
+        json_file::read(&self.list_path(key))
+    }
+
+    /// Stores a page fresh from esa and records its revisions as the latest.
+    pub fn store_list(&self, key: &impl Serialize, page: &PostListPage) -> anyhow::Result<()> {
+        self.record_revisions(&page.posts);
+        json_file::write(&self.list_path(key), page)?;
+        self.prune()
+    }
+
+    /// Returns the body only when its revision is known to be the latest.
+    pub fn load_body(&self, post_number: &PostNumber) -> Option<PostBody> {
+        let revision = *self.revisions.lock().ok()?.get(&post_number.to_i32())?;
+        json_file::read(&self.body_path(post_number, revision)).or_else(|| {
+            self.mirror
+                .body(post_number)
+                .filter(|body| body.revision_number == revision)
+        })
+    }
+
+    /// Returns whatever body is on disk regardless of revision, for offline use.
+    pub fn load_latest_body(&self, post_number: &PostNumber) -> Option<PostBody> {
+        let prefix = format!("{}-", post_number.to_i32());
+        let cached = fs::read_dir(self.dir.join("posts"))
+            .into_iter()
+            .flat_map(|entries| entries.flatten())
+            .map(|entry| entry.path())
+            .find(|path| {
+                path.file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(&prefix))
+            })
+            .and_then(|path| json_file::read::<PostBody>(&path));
+        let mirrored = self.mirror.body(post_number);
+        match (cached, mirrored) {
+            (Some(cached), Some(mirrored)) if mirrored.revision_number > cached.revision_number => {
+                Some(mirrored)
+            }
+            (cached, mirrored) => cached.or(mirrored),
+        }
+    }
+
+    /// Numbers of the posts with a body in the cache or the mirror.
+    pub fn body_numbers(&self) -> HashSet<i32> {
+        let mut numbers = self.mirror.body_numbers();
+        if let Ok(entries) = fs::read_dir(self.dir.join("posts")) {
+            numbers.extend(entries.flatten().filter_map(|entry| {
+                let name = entry.file_name();
+                name.to_string_lossy()
+                    .split_once('-')?
+                    .0
+                    .parse::<i32>()
+                    .ok()
+            }));
+        }
+        numbers
+    }
+
+    /// Posts in the cached lists and the mirror, keeping the newest revision of each.
+    pub fn cached_posts(&self) -> Vec<Post> {
+        let mut posts: HashMap<i32, Post> = HashMap::new();
+        let pages = fs::read_dir(self.dir.join("lists"))
+            .into_iter()
+            .flat_map(|entries| entries.flatten())
+            .filter_map(|entry| json_file::read::<PostListPage>(&entry.path()));
+        let mirrored: Vec<Post> = self.mirror.posts().values().cloned().collect();
+        for posts_here in pages.map(|page| page.posts).chain([mirrored]) {
+            for post in posts_here {
+                let number = post.post_number.to_i32();
+                if posts
+                    .get(&number)
+                    .is_none_or(|known| known.revision_number < post.revision_number)
+                {
+                    posts.insert(number, post);
+                }
+            }
+        }
+        posts.into_values().collect()
+    }
+
+    pub fn load_pending(&self) -> Vec<PendingAction> {
+        json_file::read(&self.pending_path()).unwrap_or_default()
+    }
+
+    /// Cancels out a queued opposite action on the same post instead of sending both.
+    pub fn queue(&self, action: PendingAction) -> anyhow::Result<()> {
+        let mut pending = self.load_pending();
+        if let Some(index) = pending
+            .iter()
+            .position(|queued| *queued == action.opposite())
+        {
+            pending.remove(index);
+        } else if !pending.contains(&action) {
+            pending.push(action);
+        }
+        json_file::write(&self.pending_path(), &pending)
+    }
+
+    /// Removes an action once it has been sent.
+    pub fn dequeue(&self, action: PendingAction) -> anyhow::Result<()> {
+        let mut pending = self.load_pending();
+        pending.retain(|queued| *queued != action);
+        json_file::write(&self.pending_path(), &pending)
+    }
+
+    /// Shows the post as if the queued actions had been sent.
+    pub fn apply_pending(&self, post: &mut Post) {
+        for action in self.load_pending() {
+            action.apply(post);
+        }
+    }
+
+    /// Deletes bodies of older revisions of the same post.
+    pub fn store_body(&self, post_number: &PostNumber, body: &PostBody) -> anyhow::Result<()> {
+        self.record_revision(post_number, body.revision_number);
+        let path = self.body_path(post_number, body.revision_number);
+        let prefix = format!("{}-", post_number.to_i32());
+        if let Ok(entries) = fs::read_dir(self.dir.join("posts")) {
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                if name.to_string_lossy().starts_with(&prefix) && entry.path() != path {
+                    fs::remove_file(entry.path())?;
+                }
+            }
+        }
+        json_file::write(&path, body)?;
+        self.prune()
+    }
+
+    pub fn record_revisions(&self, posts: &[Post]) {
+        for post in posts {
+            self.record_revision(&post.post_number, post.revision_number);
+        }
+    }
+
+    /// Keeps the higher revision so a stale list can't roll it back.
+    pub fn record_revision(&self, post_number: &PostNumber, revision_number: i32) {
+        if let Ok(mut revisions) = self.revisions.lock() {
+            let known = revisions.entry(post_number.to_i32()).or_default();
+            *known = (*known).max(revision_number);
+        }
+    }
+
+    pub fn clear(&self) -> anyhow::Result<()> {
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir)?;
+        }
+        if let Ok(mut revisions) = self.revisions.lock() {
+            revisions.clear();
+        }
+        Ok(())
+    }
+
+    /// Deletes the least recently modified files until the total fits the limit.
+    fn prune(&self) -> anyhow::Result<()> {
+        let mut files = vec![];
+        for dir in ["posts", "lists"] {
+            let Ok(entries) = fs::read_dir(self.dir.join(dir)) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let metadata = entry.metadata()?;
+                files.push((metadata.modified()?, metadata.len(), entry.path()));
+            }
+        }
+        let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+        files.sort();
+        for (_, len, path) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= len;
+        }
+        Ok(())
+    }
+
+    /// Hashes the key's JSON with unseeded FNV so the name stays the same across runs.
+    fn list_path(&self, key: &impl Serialize) -> PathBuf {
+        let mut hasher = FnvHasher::default();
+        hasher.write(&serde_json::to_vec(key).unwrap_or_default());
+        self.dir
+            .join("lists")
+            .join(format!("{:016x}.json", hasher.finish()))
+    }
+
+    fn pending_path(&self) -> PathBuf {
+        self.dir.join("pending.json")
+    }
+
+    fn body_path(&self, post_number: &PostNumber, revision_number: i32) -> PathBuf {
+        self.dir
+            .join("posts")
+            .join(format!("{}-{}.json", post_number.to_i32(), revision_number))
+    }
+}
+
+/// A star or watch made offline, sent once esa is reachable again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "action", content = "post_number")]
+pub enum PendingAction {
+    Star(i32),
+    Unstar(i32),
+    Watch(i32),
+    Unwatch(i32),
+}
+
+impl PendingAction {
+    pub fn post_number(self) -> PostNumber {
+        match self {
+            PendingAction::Star(number)
+            | PendingAction::Unstar(number)
+            | PendingAction::Watch(number)
+            | PendingAction::Unwatch(number) => PostNumber::from(number),
+        }
+    }
+
+    fn opposite(self) -> Self {
+        match self {
+            PendingAction::Star(number) => PendingAction::Unstar(number),
+            PendingAction::Unstar(number) => PendingAction::Star(number),
+            PendingAction::Watch(number) => PendingAction::Unwatch(number),
+            PendingAction::Unwatch(number) => PendingAction::Watch(number),
+        }
+    }
+
+    fn apply(self, post: &mut Post) {
+        if post.post_number != self.post_number() {
+            return;
+        }
+        match self {
+            PendingAction::Star(_) if !post.starred => {
+                post.starred = true;
+                post.stars += 1;
+            }
+            PendingAction::Unstar(_) if post.starred => {
+                post.starred = false;
+                post.stars = post.stars.saturating_sub(1);
+            }
+            PendingAction::Watch(_) if !post.watched => {
+                post.watched = true;
+                post.watches += 1;
+            }
+            PendingAction::Unwatch(_) if post.watched => {
+                post.watched = false;
+                post.watches = post.watches.saturating_sub(1);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::{UserId, sample_post};
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn post() -> Post {
+        sample_post()
+    }
+
+    fn cache(dir: &Path, max_megabytes: u64) -> PostCache {
+        PostCache::next_to(&dir.join("config.toml"), "my_team", max_megabytes)
+    }
+
+    fn body(revision_number: i32, body_md: &str) -> PostBody {
+        PostBody {
+            body_md: body_md.to_string(),
+            revision_number,
+            revised_by: UserId("taro".to_string()),
+            stale: false,
+            conflicted: false,
+        }
+    }
+
+    #[rstest]
+    fn test_body_is_served_only_at_the_latest_revision(post: Post) {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), 10);
+        let post_number = PostNumber::from(42);
+        cache.store_body(&post_number, &body(7, "rev 7")).unwrap();
+        assert_eq!(cache.load_body(&post_number).unwrap().body_md, "rev 7");
+
+        // a list shows the post has been revised since
+        let post = Post {
+            revision_number: 8,
+            ..post
+        };
+        cache.record_revisions(&[post]);
+        assert!(cache.load_body(&post_number).is_none());
+
+        cache.store_body(&post_number, &body(8, "rev 8")).unwrap();
+        assert_eq!(cache.load_body(&post_number).unwrap().body_md, "rev 8");
+        assert!(!cache.body_path(&post_number, 7).exists());
+    }
+
+    #[rstest]
+    fn test_list_round_trip(post: Post) {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), 10);
+        let key = ("sort:updated", 1);
+        let page = PostListPage {
+            posts: vec![post],
+            next_page: Some(2),
+            total_count: Some(21),
+            stale: false,
+        };
+        cache.store_list(&key, &page).unwrap();
+        let loaded = cache.load_list(&key).unwrap();
+        assert_eq!(loaded.posts[0].name, "デプロイ手順のまとめ");
+        assert_eq!((loaded.next_page, loaded.total_count), (Some(2), Some(21)));
+        assert!(cache.load_list(&("sort:updated", 2)).is_none());
+        // the list from esa tells which revision of the body is current
+        assert_eq!(cache.revisions.lock().unwrap().get(&42), Some(&7));
+        // but one read back from disk may be out of date
+        let restarted = PostCache::next_to(&dir.path().join("config.toml"), "my_team", 10);
+        assert!(restarted.load_list(&key).is_some());
+        assert!(restarted.revisions.lock().unwrap().is_empty());
+    }
+
+    #[rstest]
+    fn test_pending_actions(mut post: Post) {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), 10);
+        cache.queue(PendingAction::Star(42)).unwrap();
+        cache.queue(PendingAction::Watch(42)).unwrap();
+        cache.queue(PendingAction::Star(42)).unwrap();
+        // watching and unwatching again while offline sends neither
+        cache.queue(PendingAction::Unwatch(42)).unwrap();
+        assert_eq!(cache.load_pending(), vec![PendingAction::Star(42)]);
+
+        // the post is starred already, so only the watch shows
+        cache.queue(PendingAction::Watch(42)).unwrap();
+        cache.apply_pending(&mut post);
+        assert!(post.watched);
+        assert_eq!((post.stars, post.watches), (3, 6));
+
+        cache.dequeue(PendingAction::Star(42)).unwrap();
+        assert_eq!(cache.load_pending(), vec![PendingAction::Watch(42)]);
+    }
+
+    #[rstest]
+    fn test_falls_back_to_the_mirror(post: Post) {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), 10);
+        let mirror = Mirror::next_to(&dir.path().join("config.toml"), "my_team");
+        let post_number = PostNumber::from(42);
+        mirror
+            .store_index(&[(42, post)].into_iter().collect())
+            .unwrap();
+        mirror.store_body(&post_number, "mirrored").unwrap();
+
+        assert_eq!(cache.cached_posts()[0].name, "デプロイ手順のまとめ");
+        cache.record_revision(&post_number, 7);
+        assert_eq!(cache.load_body(&post_number).unwrap().body_md, "mirrored");
+        // the mirror is older than what esa reports now
+        cache.record_revision(&post_number, 8);
+        assert!(cache.load_body(&post_number).is_none());
+
+        cache.store_body(&post_number, &body(6, "rev 6")).unwrap();
+        assert_eq!(
+            cache.load_latest_body(&post_number).unwrap().body_md,
+            "mirrored"
+        );
+    }
+
+    #[test]
+    fn test_prune_over_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), 0);
+        cache
+            .store_body(&PostNumber::from(1), &body(1, "body"))
+            .unwrap();
+        assert!(cache.load_body(&PostNumber::from(1)).is_none());
+    }
+}
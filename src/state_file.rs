@@ -0,0 +1,37 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::domains::State;
+
+/// The state file kept next to the config file.
+#[derive(Debug, Clone)]
+pub struct StateFile {
+    path: PathBuf,
+}
+
+impl StateFile {
+    pub fn next_to(config_path: &Path) -> Self {
+        Self {
+            path: config_path.with_file_name("state.toml"),
+        }
+    }
+
+    /// Starts from an empty state when the file can't be read.
+    pub fn load(&self) -> State {
+        let Ok(state_str) = fs::read_to_string(&self.path) else {
+            return State::default();
+        };
+        toml::from_str(&state_str).unwrap_or_else(|e| {
+            eprintln!("failed to parse state file: {}", e);
+            State::default()
+        })
+    }
+
+    pub fn save(&self, state: &State) -> anyhow::Result<()> {
+        let state_str = toml::to_string(state)?;
+        fs::write(&self.path, state_str)?;
+        Ok(())
+    }
+}
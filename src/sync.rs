@@ -0,0 +1,281 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    domains::{Post, PostNumber, PostSort, SortOrder},
+    http_gateways::{EsaClientHttpGateway, MirrorPage, MirroredPost, RateLimited},
+    mirror::{Mirror, SyncRun},
+};
+
+/// Posts per page, also used to show how many pages are left.
+pub const PER_PAGE: u32 = 100;
+
+/// The first wait after a 429 without `Retry-After`, doubled on each one in a row.
+const FIRST_BACKOFF: Duration = Duration::from_secs(60);
+const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+
+/// Fetches the team's posts with their bodies into `mirror`.
+///
+/// After a completed sync only posts updated since are fetched, unless `full` is set. Progress
+/// is saved after every page, so an interrupted sync picks up from that page. Pages are taken
+/// in number order, so posts updated mid-sync aren't missed. Finally the post numbers are
+/// compared with esa's, and posts deleted there are removed locally.
+pub async fn sync(
+    api: &dyn EsaClientHttpGateway,
+    mirror: &Mirror,
+    full: bool,
+) -> anyhow::Result<()> {
+    let mut state = mirror.load_state();
+    let mut run = match state.in_progress.clone() {
+        Some(run) => {
+            println!(
+                "resuming the sync started at {} from page {}",
+                run.started_at, run.next_page
+            );
+            run
+        }
+        None => SyncRun {
+            started_at: Utc::now(),
+            query: if full {
+                None
+            } else {
+                sync_query(state.last_synced_at)
+            },
+            next_page: 1,
+        },
+    };
+    match &run.query {
+        Some(query) => println!("fetching posts matching `{}`", query),
+        None => println!("fetching every post"),
+    }
+    state.in_progress = Some(run.clone());
+    mirror.store_state(&state)?;
+
+    let mut index = mirror.load_index();
+    // a full run from the first page sees every post, so its numbers are esa's list
+    let mut fetched = (run.query.is_none() && run.next_page == 1).then(HashSet::new);
+    let (mut updated, mut unchanged) = (0, 0);
+    loop {
+        let page = fetch_page(
+            api,
+            run.query.clone(),
+            PostSort::Number,
+            SortOrder::Asc,
+            run.next_page,
+        )
+        .await?;
+        if let Some(fetched) = &mut fetched {
+            fetched.extend(page.posts.iter().map(|post| post.post.post_number.to_i32()));
+        }
+        let pages = page
+            .total_count
+            .map(|count| count.div_ceil(PER_PAGE).max(1));
+        let mut updated_here = 0;
+        for post in page.posts {
+            if store_post(mirror, &mut index, post)? {
+                updated_here += 1;
+            } else {
+                unchanged += 1;
+            }
+        }
+        updated += updated_here;
+        mirror.store_index(&index)?;
+        println!(
+            "page {}/{}: {} updated",
+            run.next_page,
+            pages.map_or("?".to_string(), |pages| pages.to_string()),
+            updated_here
+        );
+        let Some(next_page) = page.next_page else {
+            break;
+        };
+        run.next_page = next_page;
+        state.in_progress = Some(run.clone());
+        mirror.store_state(&state)?;
+    }
+
+    let listed = match fetched {
+        Some(fetched) => fetched,
+        None => list_numbers(api).await?,
+    };
+    let deleted = remove_deleted(mirror, &mut index, &listed)?;
+
+    state.last_synced_at = Some(run.started_at);
+    state.in_progress = None;
+    mirror.store_state(&state)?;
+    println!(
+        "synced: {} updated, {} unchanged, {} deleted",
+        updated, unchanged, deleted
+    );
+    Ok(())
+}
+
+/// Fetches page `page` in `sort` and `order` with bodies, retrying it after a wait when rate
+/// limited.
+pub async fn fetch_page(
+    api: &dyn EsaClientHttpGateway,
+    query: Option<String>,
+    sort: PostSort,
+    order: SortOrder,
+    page: i32,
+) -> anyhow::Result<MirrorPage> {
+    retry_rate_limited(|| api.fetch_posts_with_bodies(query.clone(), sort, order, page)).await
+}
+
+/// Retries `request` when rate limited, after the time esa gives or the `backoff`.
+async fn retry_rate_limited<T, F>(mut request: impl FnMut() -> F) -> anyhow::Result<T>
+where
+    F: Future<Output = anyhow::Result<T>>,
+{
+    let mut retries = 0;
+    loop {
+        match request().await {
+            Err(e) => {
+                let Some(limited) = e.downcast_ref::<RateLimited>() else {
+                    return Err(e);
+                };
+                let wait = limited.retry_after.unwrap_or_else(|| backoff(retries));
+                println!("rate limited, retrying in {}s", wait.as_secs());
+                tokio::time::sleep(wait).await;
+                retries += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Lists every post number on esa, without bodies so it's cheaper than a full fetch. A sync
+/// of updated posts compares against this, since a deletion can hide behind a new post.
+async fn list_numbers(api: &dyn EsaClientHttpGateway) -> anyhow::Result<HashSet<i32>> {
+    let mut listed = HashSet::new();
+    let mut page = 1;
+    loop {
+        let posts = retry_rate_limited(|| {
+            api.fetch_posts(
+                None,
+                Some(PostSort::Number),
+                Some(SortOrder::Asc),
+                Some(PER_PAGE),
+                page,
+            )
+        })
+        .await?;
+        listed.extend(posts.posts.iter().map(|post| post.post_number.to_i32()));
+        let Some(next_page) = posts.next_page else {
+            break;
+        };
+        page = next_page;
+    }
+    Ok(listed)
+}
+
+/// Removes posts missing from `listed` from `index` and the mirror, returning how many.
+fn remove_deleted(
+    mirror: &Mirror,
+    index: &mut BTreeMap<i32, Post>,
+    listed: &HashSet<i32>,
+) -> anyhow::Result<usize> {
+    let deleted: Vec<i32> = index
+        .keys()
+        .filter(|number| !listed.contains(number))
+        .copied()
+        .collect();
+    if deleted.is_empty() {
+        return Ok(0);
+    }
+    for number in &deleted {
+        index.remove(number);
+        mirror.remove(&PostNumber::from(*number))?;
+    }
+    mirror.store_index(index)?;
+    Ok(deleted.len())
+}
+
+/// Writes the body and comments only when the update time or comment count changed or the
+/// body is missing, and says whether it did. Comments don't bump the post's update time, so
+/// the count is checked too. List fields such as stars are refreshed every time.
+fn store_post(
+    mirror: &Mirror,
+    index: &mut BTreeMap<i32, Post>,
+    mirrored: MirroredPost,
+) -> anyhow::Result<bool> {
+    let MirroredPost {
+        post,
+        body,
+        comments,
+    } = mirrored;
+    let number = post.post_number.to_i32();
+    let changed = index.get(&number).is_none_or(|known| {
+        known.updated_at != post.updated_at || known.comments_count != post.comments_count
+    }) || !mirror.has_body(&post.post_number);
+    if changed {
+        mirror.store_body(&post.post_number, &body.body_md)?;
+        mirror.store_comments(&post.post_number, &comments)?;
+    }
+    index.insert(number, post);
+    Ok(changed)
+}
+
+/// The query for posts updated since the last sync. esa only filters by date, so it starts a
+/// day early to cover time zones.
+fn sync_query(last_synced_at: Option<DateTime<Utc>>) -> Option<String> {
+    let since = last_synced_at? - chrono::Duration::days(1);
+    Some(format!("updated:>{}", since.format("%Y-%m-%d")))
+}
+
+fn backoff(retries: u32) -> Duration {
+    FIRST_BACKOFF
+        .saturating_mul(2u32.saturating_pow(retries))
+        .min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::sample_post;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_sync_query() {
+        assert_eq!(sync_query(None), None);
+        let last = Utc.with_ymd_and_hms(2025, 3, 1, 2, 30, 0).unwrap();
+        assert_eq!(
+            sync_query(Some(last)).as_deref(),
+            Some("updated:>2025-02-28")
+        );
+    }
+
+    #[test]
+    fn test_remove_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let mirror = Mirror::next_to(&dir.path().join("config.toml"), "my_team");
+        let mut index = BTreeMap::new();
+        for number in [1, 2] {
+            let post = Post {
+                post_number: number.into(),
+                ..sample_post()
+            };
+            mirror.store_body(&post.post_number, "本文").unwrap();
+            index.insert(number, post);
+        }
+        // 1 was deleted and 3 created, so the counts alone match
+        let listed = HashSet::from([2, 3]);
+        assert_eq!(remove_deleted(&mirror, &mut index, &listed).unwrap(), 1);
+        assert_eq!(index.keys().copied().collect::<Vec<_>>(), vec![2]);
+        assert!(!mirror.has_body(&PostNumber::from(1)));
+        assert!(mirror.has_body(&PostNumber::from(2)));
+        assert_eq!(mirror.load_index().len(), 1);
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(0), Duration::from_secs(60));
+        assert_eq!(backoff(2), Duration::from_secs(240));
+        assert_eq!(backoff(10), MAX_BACKOFF);
+        assert_eq!(backoff(40), MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,11 @@
+//! Nerd Font icons shared by the panes.
+
+pub const STAR_ICON: &str = "\u{f005}";
+pub const UNSTAR_ICON: &str = "\u{f006}";
+pub const WATCH_ICON: &str = "\u{f441}";
+pub const UNWATCH_ICON: &str = "\u{f06e}";
+pub const TASKS_ICON: &str = "\u{f0ae}";
+pub const COMMENTS_ICON: &str = "\u{f075}";
+pub const USER_ICON: &str = "\u{f007}";
+pub const STOCK_ICON: &str = "\u{f02d}";
+pub const FLOW_ICON: &str = "\u{f0e7}";
@@ -1,4 +1,11 @@
+mod icons;
 mod post_content;
 mod post_list;
-pub use post_content::PostContent;
+mod recent_posts;
+mod search;
+mod status_bar;
+pub use post_content::{InlineImages, PostContent};
 pub use post_list::PostList;
+pub use recent_posts::RecentPosts;
+pub use search::{Search, SearchRequest, SearchScope};
+pub use status_bar::{StatusBar, StatusMessage};
@@ -0,0 +1,412 @@
+use std::{str::FromStr, sync::LazyLock};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+use syntect::{
+    easy::ScopeRangeIterator,
+    highlighting::ScopeSelectors,
+    parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet},
+};
+
+use super::{diagram, math};
+use crate::domains::Theme;
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+
+/// Fence languages that don't match a syntax name or file extension.
+const LANGUAGE_ALIASES: [(&str, &str); 6] = [
+    ("shell", "sh"),
+    ("console", "sh"),
+    ("zsh", "sh"),
+    ("yml", "yaml"),
+    ("golang", "go"),
+    ("postgresql", "sql"),
+];
+
+/// A piece of the post body, either markdown for md-tui or a fenced code block we draw ourselves.
+#[derive(Clone, Debug)]
+pub enum Segment {
+    Markdown(String),
+    Code(HighlightedCode),
+}
+
+#[derive(Clone, Debug)]
+pub struct HighlightedCode {
+    /// The fence info, e.g. `sql` or `ruby:app.rb` as esa writes file names.
+    pub info: String,
+    pub title: String,
+    /// The code as written, for copying.
+    pub source: String,
+    pub lines: Vec<Line<'static>>,
+    /// Whether line numbers make sense, which they don't for a drawn diagram.
+    pub numbered: bool,
+}
+
+/// Maps syntax scopes to the theme roles, so code follows the configured colors.
+pub struct CodeHighlighter {
+    rules: Vec<(ScopeSelectors, Style)>,
+    default: Style,
+    /// Math macros we can't convert, kept as written.
+    verbatim: Style,
+}
+
+impl CodeHighlighter {
+    pub fn new(theme: &Theme) -> Self {
+        let rules = [
+            ("comment", Style::new().fg(theme.muted).add_modifier(Modifier::ITALIC)),
+            ("string, constant.character, markup.inserted", Style::new().fg(theme.success)),
+            (
+                "constant.numeric, constant.language, constant.other, support.constant, variable.language",
+                Style::new().fg(theme.warning),
+            ),
+            ("keyword, storage", Style::new().fg(theme.accent)),
+            (
+                "entity.name.function, support.function, meta.function-call, entity.name.tag",
+                Style::new().fg(theme.link),
+            ),
+            (
+                "entity.name.type, entity.name.class, support.type, support.class, entity.other.attribute-name",
+                Style::new().fg(theme.warning),
+            ),
+            ("invalid, markup.deleted", Style::new().fg(theme.error)),
+        ]
+        .into_iter()
+        .filter_map(|(selector, style)| {
+            ScopeSelectors::from_str(selector)
+                .ok()
+                .map(|selector| (selector, style))
+        })
+        .collect();
+        Self {
+            rules,
+            default: Style::new().fg(theme.primary),
+            verbatim: Style::new()
+                .fg(theme.warning)
+                .add_modifier(Modifier::ITALIC),
+        }
+    }
+
+    /// Splits preprocessed markdown at its fenced code blocks, highlighting each block.
+    pub fn segments(&self, markdown: &str) -> Vec<Segment> {
+        let mut segments = vec![];
+        let mut prose = String::new();
+        let mut code: Option<(String, usize, Vec<&str>)> = None;
+        for line in markdown.lines() {
+            let trimmed = line.trim_start();
+            match &mut code {
+                Some((info, indent, lines)) => {
+                    if trimmed.starts_with("```") {
+                        segments.push(Segment::Code(self.highlight(info, lines)));
+                        code = None;
+                    } else {
+                        lines.push(strip_indent(line, *indent));
+                    }
+                }
+                None => {
+                    if let Some(info) = trimmed.strip_prefix("```") {
+                        if !prose.trim().is_empty() {
+                            segments.push(Segment::Markdown(std::mem::take(&mut prose)));
+                        }
+                        prose.clear();
+                        let indent = line.len() - trimmed.len();
+                        code = Some((info.trim().to_string(), indent, vec![]));
+                    } else {
+                        prose.push_str(line);
+                        prose.push('\n');
+                    }
+                }
+            }
+        }
+        // an unclosed fence runs to the end of the post, as in markdown
+        if let Some((info, _, lines)) = code {
+            segments.push(Segment::Code(self.highlight(&info, &lines)));
+        } else if !prose.trim().is_empty() {
+            segments.push(Segment::Markdown(prose));
+        }
+        segments
+    }
+
+    fn highlight(&self, info: &str, lines: &[&str]) -> HighlightedCode {
+        let language = language(info);
+        let title = match info.split_once(':') {
+            Some((language, file_name)) => format!("{} {}", language, file_name),
+            None => info.to_string(),
+        };
+        if language == "math" {
+            return HighlightedCode {
+                info: info.to_string(),
+                title,
+                source: lines.join("\n"),
+                lines: math::to_unicode(&lines.join("\n"))
+                    .into_iter()
+                    .map(|line| {
+                        Line::from(
+                            line.into_iter()
+                                .map(|span| {
+                                    let style = if span.verbatim {
+                                        self.verbatim
+                                    } else {
+                                        self.default
+                                    };
+                                    Span::styled(span.text, style)
+                                })
+                                .collect::<Vec<_>>(),
+                        )
+                    })
+                    .collect(),
+                numbered: false,
+            };
+        }
+        if diagram::is_diagram(&language) {
+            return match diagram::render(&language, lines) {
+                Some(drawn) => HighlightedCode {
+                    info: info.to_string(),
+                    title,
+                    source: lines.join("\n"),
+                    lines: drawn
+                        .into_iter()
+                        .map(|line| Line::styled(line, self.default))
+                        .collect(),
+                    numbered: false,
+                },
+                // show the source of diagrams we can't draw, labeled as such
+                None => HighlightedCode {
+                    title: format!("{} diagram (source)", title),
+                    ..self.highlight_syntax(info, lines, title.clone())
+                },
+            };
+        }
+        self.highlight_syntax(info, lines, title)
+    }
+
+    fn highlight_syntax(&self, info: &str, lines: &[&str], title: String) -> HighlightedCode {
+        let syntax = find_syntax(info);
+        let mut state = syntax.map(ParseState::new);
+        let mut stack = ScopeStack::new();
+        let highlighted = lines
+            .iter()
+            .map(|line| {
+                let line = line.replace('\t', "    ");
+                let Some(state) = &mut state else {
+                    return Line::from(Span::styled(line, self.default));
+                };
+                let with_newline = format!("{}\n", line);
+                let Ok(ops) = state.parse_line(&with_newline, &SYNTAXES) else {
+                    return Line::from(Span::styled(line, self.default));
+                };
+                let mut spans: Vec<Span<'static>> = vec![];
+                for (range, op) in ScopeRangeIterator::new(&ops, &with_newline) {
+                    let _ = stack.apply(op);
+                    let text = with_newline[range].trim_end_matches('\n');
+                    if text.is_empty() {
+                        continue;
+                    }
+                    let style = self.style_for(&stack);
+                    match spans.last_mut() {
+                        Some(last) if last.style == style => last.content.to_mut().push_str(text),
+                        _ => spans.push(Span::styled(text.to_string(), style)),
+                    }
+                }
+                Line::from(spans)
+            })
+            .collect();
+        HighlightedCode {
+            info: info.to_string(),
+            title,
+            source: lines.join("\n"),
+            lines: highlighted,
+            numbered: true,
+        }
+    }
+
+    fn style_for(&self, stack: &ScopeStack) -> Style {
+        self.rules
+            .iter()
+            .filter_map(|(selector, style)| {
+                selector
+                    .does_match(stack.as_slice())
+                    .map(|power| (power, style))
+            })
+            .max_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map_or(self.default, |(_, style)| *style)
+    }
+}
+
+impl HighlightedCode {
+    /// Rows the block takes: the code between a top and a bottom border.
+    pub fn height(&self) -> u16 {
+        (self.lines.len() as u16).saturating_add(2)
+    }
+
+    /// Columns the widest line needs, including the line number gutter.
+    pub fn content_width(&self, line_numbers: bool) -> u16 {
+        let widest = self.lines.iter().map(Line::width).max().unwrap_or(0) as u16;
+        widest.saturating_add(self.gutter_width(line_numbers))
+    }
+
+    fn gutter_width(&self, line_numbers: bool) -> u16 {
+        if line_numbers && self.numbered {
+            self.lines.len().to_string().len() as u16 + 1
+        } else {
+            0
+        }
+    }
+
+    /// Draws the block into a buffer of its own, shifted left by `scroll_x` columns.
+    pub fn render(&self, width: u16, scroll_x: u16, line_numbers: bool, theme: &Theme) -> Buffer {
+        let area = Rect::new(0, 0, width, self.height());
+        let mut buf = Buffer::empty(area);
+        let block = Block::default()
+            .title(self.title.as_str())
+            .borders(Borders::ALL)
+            .border_style(Style::new().fg(theme.muted))
+            .title_style(Style::new().fg(theme.muted));
+        let inner = block.inner(area);
+        block.render(area, &mut buf);
+
+        let gutter_width = self.gutter_width(line_numbers);
+        let [gutter_area, code_area] =
+            Layout::horizontal([Constraint::Length(gutter_width), Constraint::Fill(1)])
+                .areas(inner);
+        if gutter_width > 0 {
+            let numbers: Vec<Line> = (1..=self.lines.len())
+                .map(|n| {
+                    Line::styled(
+                        format!("{:>width$} ", n, width = gutter_width as usize - 1),
+                        Style::new().fg(theme.muted),
+                    )
+                })
+                .collect();
+            Paragraph::new(numbers).render(gutter_area, &mut buf);
+        }
+        Paragraph::new(self.lines.clone())
+            .scroll((0, scroll_x))
+            .render(code_area, &mut buf);
+        buf
+    }
+}
+
+/// The language of a fence info such as `ruby:app.rb`, in lower case.
+fn language(info: &str) -> String {
+    info.split(':')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase()
+}
+
+fn find_syntax(info: &str) -> Option<&'static SyntaxReference> {
+    let language = language(info);
+    if language.is_empty() {
+        return None;
+    }
+    let language = LANGUAGE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == language)
+        .map_or(language.as_str(), |(_, name)| name);
+    SYNTAXES
+        .find_syntax_by_token(language)
+        .or_else(|| SYNTAXES.find_syntax_by_extension(language))
+}
+
+/// Removes up to `indent` leading spaces, the indentation of the fence itself.
+fn strip_indent(line: &str, indent: usize) -> &str {
+    let spaces = line.len() - line.trim_start_matches(' ').len();
+    &line[spaces.min(indent)..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::ThemeConfig;
+    use insta::assert_snapshot;
+    use ratatui::style::Color;
+    use rstest::rstest;
+
+    fn theme() -> Theme {
+        Theme::from_config(&ThemeConfig::default())
+    }
+
+    /// Joins the spans of each line, naming the theme role of every colored span.
+    fn describe(code: &HighlightedCode, theme: &Theme) -> String {
+        let roles = [
+            (theme.primary, "primary"),
+            (theme.muted, "muted"),
+            (theme.accent, "accent"),
+            (theme.error, "error"),
+            (theme.success, "success"),
+            (theme.warning, "warning"),
+            (theme.link, "link"),
+        ];
+        let role = |color: Option<Color>| {
+            roles
+                .iter()
+                .find(|(c, _)| Some(*c) == color)
+                .map_or("?", |(_, name)| name)
+        };
+        code.lines
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| match role(span.style.fg) {
+                        "primary" => span.content.to_string(),
+                        name => format!("<{}>{}</>", name, span.content),
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_segments_split_fenced_code() {
+        let highlighter = CodeHighlighter::new(&theme());
+        let markdown = "# Runbook\n\n- step\n  ```sh\n  echo hi\n  ```\nafter\n```\nunclosed\n";
+        let segments = highlighter.segments(markdown);
+        let summary: Vec<String> = segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Markdown(markdown) => format!("markdown {:?}", markdown),
+                Segment::Code(code) => format!("code {:?} {}", code.info, code.lines.len()),
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "markdown \"# Runbook\\n\\n- step\\n\"",
+                "code \"sh\" 1",
+                "markdown \"after\\n\"",
+                "code \"\" 1",
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "highlight_sql",
+        "sql",
+        "-- active users\nSELECT id, name FROM users WHERE age > 20 AND name = 'esa';"
+    )]
+    #[case(
+        "highlight_yaml",
+        "yaml",
+        "# deploy\nservice:\n  replicas: 3\n  image: \"app:latest\"\n  enabled: true"
+    )]
+    #[case(
+        "highlight_shell",
+        "shell:deploy.sh",
+        "#!/bin/bash\nfor host in $HOSTS; do\n  ssh \"$host\" 'sudo systemctl restart app' # restart\ndone"
+    )]
+    fn test_highlight(#[case] name: &str, #[case] info: &str, #[case] code: &str) {
+        let theme = theme();
+        let highlighter = CodeHighlighter::new(&theme);
+        let lines: Vec<&str> = code.lines().collect();
+        assert_snapshot!(name, describe(&highlighter.highlight(info, &lines), &theme));
+    }
+}
@@ -0,0 +1,156 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Empty,
+    /// Line ends meeting in this cell, drawn as the matching box-drawing junction.
+    Lines(u8),
+    Char(char),
+    /// The right half of a double-width character.
+    Wide,
+}
+
+/// A character grid that grows as it is drawn on, joining lines that meet into junctions.
+#[derive(Default)]
+pub struct Canvas {
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Canvas {
+    fn cell_mut(&mut self, x: usize, y: usize) -> &mut Cell {
+        if self.rows.len() <= y {
+            self.rows.resize(y + 1, vec![]);
+        }
+        let row = &mut self.rows[y];
+        if row.len() <= x {
+            row.resize(x + 1, Cell::Empty);
+        }
+        &mut row[x]
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Cell {
+        self.rows
+            .get(y)
+            .and_then(|row| row.get(x))
+            .copied()
+            .unwrap_or(Cell::Empty)
+    }
+
+    fn connect(&mut self, x: usize, y: usize, direction: u8) {
+        let cell = self.cell_mut(x, y);
+        match cell {
+            Cell::Empty => *cell = Cell::Lines(direction),
+            Cell::Lines(mask) => *mask |= direction,
+            Cell::Char(_) | Cell::Wide => {}
+        }
+    }
+
+    /// Draws a horizontal or vertical line between two cells, both included.
+    pub fn line(&mut self, from: (usize, usize), to: (usize, usize)) {
+        let ((x1, y1), (x2, y2)) = (from.min(to), from.max(to));
+        if y1 == y2 {
+            for x in x1..x2 {
+                self.connect(x, y1, RIGHT);
+                self.connect(x + 1, y1, LEFT);
+            }
+        } else if x1 == x2 {
+            for y in y1..y2 {
+                self.connect(x1, y, DOWN);
+                self.connect(x1, y + 1, UP);
+            }
+        }
+    }
+
+    /// Draws the border of a box whose top left corner is `(x, y)`.
+    pub fn rect(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        let (right, bottom) = (x + width - 1, y + height - 1);
+        self.line((x, y), (right, y));
+        self.line((x, bottom), (right, bottom));
+        self.line((x, y), (x, bottom));
+        self.line((right, y), (right, bottom));
+    }
+
+    /// Blanks the cells inside a box so lines behind it don't show through.
+    pub fn clear(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        for row in y..y + height {
+            for col in x..x + width {
+                *self.cell_mut(col, row) = Cell::Char(' ');
+            }
+        }
+    }
+
+    pub fn set_char(&mut self, x: usize, y: usize, c: char) {
+        *self.cell_mut(x, y) = Cell::Char(c);
+    }
+
+    /// Replaces the plain horizontal line cells between `x1` and `x2` on row `y` with `c`.
+    pub fn restyle_horizontal(&mut self, x1: usize, x2: usize, y: usize, c: char) {
+        for x in x1.min(x2)..=x1.max(x2) {
+            if self.cell(x, y) == Cell::Lines(LEFT | RIGHT) {
+                self.set_char(x, y, c);
+            }
+        }
+    }
+
+    pub fn text(&mut self, x: usize, y: usize, text: &str) {
+        let mut x = x;
+        for c in text.chars() {
+            let width = c.width().unwrap_or(0);
+            if width == 0 {
+                continue;
+            }
+            self.set_char(x, y, c);
+            if width == 2 {
+                *self.cell_mut(x + 1, y) = Cell::Wide;
+            }
+            x += width;
+        }
+    }
+
+    /// Writes `text` only where it doesn't cover anything, so labels never hide lines.
+    pub fn text_if_blank(&mut self, x: usize, y: usize, text: &str) {
+        let blank = (x..x + text.width()).all(|col| self.cell(col, y) == Cell::Empty);
+        if blank {
+            self.text(x, y, text);
+        }
+    }
+
+    pub fn into_lines(self) -> Vec<String> {
+        self.rows
+            .into_iter()
+            .map(|row| {
+                let line: String = row
+                    .into_iter()
+                    .filter_map(|cell| match cell {
+                        Cell::Empty => Some(' '),
+                        Cell::Lines(mask) => Some(junction(mask)),
+                        Cell::Char(c) => Some(c),
+                        Cell::Wide => None,
+                    })
+                    .collect();
+                line.trim_end().to_string()
+            })
+            .collect()
+    }
+}
+
+fn junction(mask: u8) -> char {
+    match mask {
+        m if m == UP | DOWN | LEFT | RIGHT => '┼',
+        m if m == UP | DOWN | RIGHT => '├',
+        m if m == UP | DOWN | LEFT => '┤',
+        m if m == DOWN | LEFT | RIGHT => '┬',
+        m if m == UP | LEFT | RIGHT => '┴',
+        m if m == DOWN | RIGHT => '┌',
+        m if m == DOWN | LEFT => '┐',
+        m if m == UP | RIGHT => '└',
+        m if m == UP | LEFT => '┘',
+        m if m & (UP | DOWN) != 0 => '│',
+        _ => '─',
+    }
+}
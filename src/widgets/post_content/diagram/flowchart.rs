@@ -0,0 +1,582 @@
+use unicode_width::UnicodeWidthStr;
+
+use super::{canvas::Canvas, clean_label};
+
+/// Statements that only affect styling or grouping, which the ASCII rendering leaves out.
+const IGNORED_STATEMENTS: [&str; 8] = [
+    "subgraph",
+    "end",
+    "style",
+    "classDef",
+    "class",
+    "click",
+    "linkStyle",
+    "direction",
+];
+
+/// Node brackets, longest first so `((` wins over `(`.
+const SHAPES: [(&str, &str, Shape); 12] = [
+    ("(((", ")))", Shape::Round),
+    ("((", "))", Shape::Round),
+    ("([", "])", Shape::Round),
+    ("[(", ")]", Shape::Rect),
+    ("[[", "]]", Shape::Rect),
+    ("[/", "]", Shape::Rect),
+    ("[\\", "]", Shape::Rect),
+    ("{{", "}}", Shape::Diamond),
+    ("(", ")", Shape::Round),
+    ("[", "]", Shape::Rect),
+    ("{", "}", Shape::Diamond),
+    (">", "]", Shape::Rect),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    Rect,
+    Round,
+    Diamond,
+}
+
+struct Node {
+    id: String,
+    label: String,
+    shape: Shape,
+}
+
+struct Edge {
+    from: usize,
+    to: usize,
+    label: Option<String>,
+    arrow: bool,
+}
+
+/// A mermaid `graph` / `flowchart`. Right-to-left and bottom-to-top charts are drawn
+/// left-to-right and top-to-bottom.
+pub struct Flowchart {
+    horizontal: bool,
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+impl Flowchart {
+    /// Parses the chart, `None` when the header or a statement isn't understood.
+    pub fn parse(lines: &[&str]) -> Option<Self> {
+        let mut statements = lines
+            .iter()
+            .map(|line| line.split("%%").next().unwrap_or(""))
+            .flat_map(|line| line.split(';'))
+            .map(str::trim)
+            .filter(|statement| !statement.is_empty());
+        let mut header = statements.next()?.split_whitespace();
+        if !matches!(header.next()?, "graph" | "flowchart") {
+            return None;
+        }
+        let mut chart = Self {
+            horizontal: matches!(header.next(), Some("LR" | "RL")),
+            nodes: vec![],
+            edges: vec![],
+        };
+        for statement in statements {
+            let keyword = statement.split_whitespace().next().unwrap_or("");
+            if !IGNORED_STATEMENTS.contains(&keyword) {
+                chart.parse_statement(statement)?;
+            }
+        }
+        (!chart.nodes.is_empty()).then_some(chart)
+    }
+
+    /// Parses a chain such as `A[Start] --> B{OK?} -->|yes| C & D`.
+    fn parse_statement(&mut self, statement: &str) -> Option<()> {
+        let mut rest = statement;
+        let mut previous = self.parse_group(&mut rest)?;
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                return Some(());
+            }
+            let (label, arrow) = parse_link(&mut rest)?;
+            let next = self.parse_group(&mut rest)?;
+            for &from in &previous {
+                for &to in &next {
+                    self.edges.push(Edge {
+                        from,
+                        to,
+                        label: label.clone(),
+                        arrow,
+                    });
+                }
+            }
+            previous = next;
+        }
+    }
+
+    fn parse_group(&mut self, rest: &mut &str) -> Option<Vec<usize>> {
+        let mut group = vec![self.parse_node(rest)?];
+        while let Some(after) = rest.trim_start().strip_prefix('&') {
+            *rest = after;
+            group.push(self.parse_node(rest)?);
+        }
+        Some(group)
+    }
+
+    fn parse_node(&mut self, rest: &mut &str) -> Option<usize> {
+        *rest = rest.trim_start();
+        let id_len = rest
+            .find(|c: char| c.is_whitespace() || "[](){}<>-=.|&:\"".contains(c))
+            .unwrap_or(rest.len());
+        let id = &rest[..id_len];
+        if id.is_empty() {
+            return None;
+        }
+        *rest = &rest[id_len..];
+        let shape = parse_shape(rest);
+        if let Some(class) = rest.strip_prefix(":::") {
+            let class_len = class
+                .find(|c: char| c.is_whitespace() || "-=.&".contains(c))
+                .unwrap_or(class.len());
+            *rest = &class[class_len..];
+        }
+        let index = match self.nodes.iter().position(|node| node.id == id) {
+            Some(index) => index,
+            None => {
+                self.nodes.push(Node {
+                    id: id.to_string(),
+                    label: id.to_string(),
+                    shape: Shape::Rect,
+                });
+                self.nodes.len() - 1
+            }
+        };
+        if let Some((shape, label)) = shape {
+            self.nodes[index].shape = shape;
+            self.nodes[index].label = label;
+        }
+        Some(index)
+    }
+}
+
+fn parse_shape(rest: &mut &str) -> Option<(Shape, String)> {
+    let (open, close, shape) = SHAPES.iter().find(|(open, _, _)| rest.starts_with(open))?;
+    let inner = &rest[open.len()..];
+    // a quoted label may contain the closing bracket
+    let search_from = match inner.strip_prefix('"') {
+        Some(quoted) => quoted.find('"').map_or(0, |end| end + 2),
+        None => 0,
+    };
+    let end = search_from + inner[search_from..].find(close)?;
+    let label = clean_label(inner[..end].trim_matches(['/', '\\']));
+    *rest = &inner[end + close.len()..];
+    Some((*shape, label))
+}
+
+/// Parses a link such as `-->`, `-.->`, `==>`, `---`, `-->|label|` or `-- label -->`,
+/// returning its label and whether it ends in an arrowhead.
+fn parse_link(rest: &mut &str) -> Option<(Option<String>, bool)> {
+    fn take_run<'a>(rest: &mut &'a str) -> &'a str {
+        let len = rest
+            .find(|c: char| !matches!(c, '-' | '=' | '.' | '<' | '>'))
+            .unwrap_or(rest.len());
+        let run = &rest[..len];
+        *rest = &rest[len..];
+        run
+    }
+    let mut run = take_run(rest);
+    if run.len() < 2 {
+        return None;
+    }
+    let mut label = None;
+    if matches!(run, "--" | "==" | "-.") {
+        let end = ["--", "==", ".-"]
+            .iter()
+            .filter_map(|token| rest.find(token))
+            .min()?;
+        label = Some(clean_label(&rest[..end]));
+        *rest = &rest[end..];
+        run = take_run(rest);
+    }
+    if let Some(piped) = rest.strip_prefix('|') {
+        let end = piped.find('|')?;
+        label = Some(clean_label(&piped[..end]));
+        *rest = &piped[end + 1..];
+    }
+    Some((label.filter(|label| !label.is_empty()), run.ends_with('>')))
+}
+
+/// A node or a point an edge passes through on a layer between its ends.
+struct Item {
+    node: Option<usize>,
+    layer: usize,
+    /// Position across the layers, set once the layers are ordered.
+    cross: usize,
+    cross_size: usize,
+    main_size: usize,
+}
+
+impl Item {
+    fn anchor(&self) -> usize {
+        self.cross + self.cross_size / 2
+    }
+
+    /// Spreads `count` edges along the side of a box so they don't merge into one line.
+    fn port(&self, index: usize, count: usize) -> usize {
+        let inside = self.cross_size.saturating_sub(2);
+        if count <= 1 || inside < count {
+            return self.anchor();
+        }
+        self.cross + 1 + inside * (2 * index + 1) / (2 * count)
+    }
+}
+
+/// The items an edge passes through, from the upper layer to the lower one.
+struct Chain {
+    items: Vec<usize>,
+    label: Option<String>,
+    arrow: bool,
+    /// The edge points back up, against the layering.
+    reversed: bool,
+}
+
+impl Flowchart {
+    /// Draws the chart with the nodes in layers, top to bottom or left to right, and the
+    /// edges routed in the channels between the layers.
+    pub fn render(&self) -> Vec<String> {
+        let back_edges = self.back_edges();
+        let layer_of = self.layers(&back_edges);
+        let mut items: Vec<Item> = self
+            .nodes
+            .iter()
+            .zip(&layer_of)
+            .enumerate()
+            .map(|(index, (node, &layer))| {
+                let width = node.label.width() + 4;
+                let (cross_size, main_size) = if self.horizontal {
+                    (3, width)
+                } else {
+                    (width, 3)
+                };
+                Item {
+                    node: Some(index),
+                    layer,
+                    cross: 0,
+                    cross_size,
+                    main_size,
+                }
+            })
+            .collect();
+        let mut chains = vec![];
+        for (edge, &reversed) in self.edges.iter().zip(&back_edges) {
+            let (upper, lower) = if reversed {
+                (edge.to, edge.from)
+            } else {
+                (edge.from, edge.to)
+            };
+            if upper == lower {
+                continue;
+            }
+            let mut chain = vec![upper];
+            for layer in layer_of[upper] + 1..layer_of[lower] {
+                items.push(Item {
+                    node: None,
+                    layer,
+                    cross: 0,
+                    cross_size: 1,
+                    main_size: 0,
+                });
+                chain.push(items.len() - 1);
+            }
+            chain.push(lower);
+            chains.push(Chain {
+                items: chain,
+                label: edge.label.clone(),
+                arrow: edge.arrow,
+                reversed,
+            });
+        }
+
+        let layer_count = layer_of.iter().max().map_or(0, |max| max + 1);
+        let layers = self.order_layers(&items, &chains, layer_count);
+        self.place_across(&mut items, &layers);
+        let ports = self.ports(&items, &chains);
+
+        // sizes along the main axis of the layers and of the channels after them
+        let layer_sizes: Vec<usize> = layers
+            .iter()
+            .map(|layer| {
+                layer
+                    .iter()
+                    .map(|&item| items[item].main_size)
+                    .max()
+                    .unwrap_or(0)
+                    .max(1)
+            })
+            .collect();
+        let mut tracks = vec![vec![]; layer_count];
+        let mut label_space = vec![0; layer_count];
+        for (chain_index, chain) in chains.iter().enumerate() {
+            for (segment, pair) in chain.items.windows(2).enumerate() {
+                let (a, b) = (&items[pair[0]], &items[pair[1]]);
+                let (ca, cb) = ports[chain_index][segment];
+                if ca != cb {
+                    tracks[a.layer].push((ca, cb, chain_index, segment));
+                }
+                if let Some(label) = &chain.label
+                    && b.node.is_some()
+                {
+                    let space = if self.horizontal {
+                        label.width() + 2
+                    } else {
+                        1
+                    };
+                    label_space[a.layer] = label_space[a.layer].max(space);
+                }
+            }
+        }
+        for channel in &mut tracks {
+            channel.sort();
+        }
+        let mut layer_starts = vec![0; layer_count];
+        for layer in 1..layer_count {
+            let channel = 2 + tracks[layer - 1].len() + label_space[layer - 1];
+            layer_starts[layer] = layer_starts[layer - 1] + layer_sizes[layer - 1] + channel;
+        }
+
+        let point = |main: usize, cross: usize| {
+            if self.horizontal {
+                (main, cross)
+            } else {
+                (cross, main)
+            }
+        };
+        let mut canvas = Canvas::default();
+        for item in &items {
+            let start = layer_starts[item.layer];
+            match item.node {
+                Some(node) => {
+                    let (x, y) = point(start, item.cross);
+                    self.draw_node(&mut canvas, &self.nodes[node], x, y);
+                }
+                None => canvas.line(
+                    point(start, item.anchor()),
+                    point(start + layer_sizes[item.layer] - 1, item.anchor()),
+                ),
+            }
+        }
+        let (arrow_forward, arrow_back) = if self.horizontal {
+            ('▶', '◀')
+        } else {
+            ('▼', '▲')
+        };
+        for (chain_index, chain) in chains.iter().enumerate() {
+            let last_segment = chain.items.len() - 2;
+            for (segment, pair) in chain.items.windows(2).enumerate() {
+                let (a, b) = (&items[pair[0]], &items[pair[1]]);
+                let layer = a.layer;
+                let channel_start = layer_starts[layer] + layer_sizes[layer];
+                let start = match a.node {
+                    Some(_) => layer_starts[layer] + a.main_size - 1,
+                    None => channel_start - 1,
+                };
+                let target_start = layer_starts[b.layer];
+                let arrow_at_end = segment == last_segment && chain.arrow && !chain.reversed;
+                let end = if b.node.is_some() && arrow_at_end {
+                    target_start - 1
+                } else {
+                    target_start
+                };
+                let (ca, cb) = ports[chain_index][segment];
+                match tracks[layer]
+                    .iter()
+                    .position(|&(_, _, c, s)| c == chain_index && s == segment)
+                {
+                    Some(track) => {
+                        let track = channel_start + 1 + track;
+                        canvas.line(point(start, ca), point(track, ca));
+                        canvas.line(point(track, ca), point(track, cb));
+                        canvas.line(point(track, cb), point(end, cb));
+                    }
+                    None => canvas.line(point(start, ca), point(end, cb)),
+                }
+                if arrow_at_end {
+                    let (x, y) = point(end, cb);
+                    canvas.set_char(x, y, arrow_forward);
+                }
+                if segment == 0 && chain.arrow && chain.reversed {
+                    let (x, y) = point(start + 1, ca);
+                    canvas.set_char(x, y, arrow_back);
+                }
+                if segment == last_segment
+                    && let Some(label) = &chain.label
+                {
+                    let (x, y) = if self.horizontal {
+                        (channel_start + 2 + tracks[layer].len(), cb - 1)
+                    } else {
+                        (cb + 2, target_start - 2)
+                    };
+                    canvas.text_if_blank(x, y, label);
+                }
+            }
+        }
+        canvas.into_lines()
+    }
+
+    fn draw_node(&self, canvas: &mut Canvas, node: &Node, x: usize, y: usize) {
+        let width = node.label.width() + 4;
+        canvas.rect(x, y, width, 3);
+        canvas.text(x + 2, y + 1, &node.label);
+        let corners = match node.shape {
+            Shape::Rect => return,
+            Shape::Round => ['╭', '╮', '╰', '╯'],
+            Shape::Diamond => ['╱', '╲', '╲', '╱'],
+        };
+        canvas.set_char(x, y, corners[0]);
+        canvas.set_char(x + width - 1, y, corners[1]);
+        canvas.set_char(x, y + 2, corners[2]);
+        canvas.set_char(x + width - 1, y + 2, corners[3]);
+    }
+
+    /// Picks where each segment of each chain leaves its upper item and enters its lower one,
+    /// ordered by the position of the other end to avoid crossings.
+    fn ports(&self, items: &[Item], chains: &[Chain]) -> Vec<Vec<(usize, usize)>> {
+        let mut leaving = vec![vec![]; items.len()];
+        let mut entering = vec![vec![]; items.len()];
+        for (chain_index, chain) in chains.iter().enumerate() {
+            for (segment, pair) in chain.items.windows(2).enumerate() {
+                leaving[pair[0]].push((items[pair[1]].anchor(), chain_index, segment));
+                entering[pair[1]].push((items[pair[0]].anchor(), chain_index, segment));
+            }
+        }
+        let mut ports: Vec<Vec<(usize, usize)>> = chains
+            .iter()
+            .map(|chain| vec![(0, 0); chain.items.len() - 1])
+            .collect();
+        for (index, item) in items.iter().enumerate() {
+            leaving[index].sort();
+            entering[index].sort();
+            let count = leaving[index].len();
+            for (i, &(_, chain, segment)) in leaving[index].iter().enumerate() {
+                ports[chain][segment].0 = item.port(i, count);
+            }
+            let count = entering[index].len();
+            for (i, &(_, chain, segment)) in entering[index].iter().enumerate() {
+                ports[chain][segment].1 = item.port(i, count);
+            }
+        }
+        ports
+    }
+
+    /// Marks the edges that close a cycle, found by a depth-first search.
+    fn back_edges(&self) -> Vec<bool> {
+        fn visit(chart: &Flowchart, node: usize, state: &mut [u8], back: &mut [bool]) {
+            state[node] = 1;
+            for (index, edge) in chart.edges.iter().enumerate() {
+                if edge.from != node {
+                    continue;
+                }
+                match state[edge.to] {
+                    0 => visit(chart, edge.to, state, back),
+                    1 => back[index] = true,
+                    _ => {}
+                }
+            }
+            state[node] = 2;
+        }
+        let mut state = vec![0; self.nodes.len()];
+        let mut back = vec![false; self.edges.len()];
+        for node in 0..self.nodes.len() {
+            if state[node] == 0 {
+                visit(self, node, &mut state, &mut back);
+            }
+        }
+        back
+    }
+
+    /// Puts every node one layer below the lowest node pointing to it.
+    fn layers(&self, back_edges: &[bool]) -> Vec<usize> {
+        let mut layers = vec![0; self.nodes.len()];
+        // the edges form no cycle once the back edges are turned around, so this settles
+        for _ in 0..self.nodes.len() {
+            let mut changed = false;
+            for (edge, &reversed) in self.edges.iter().zip(back_edges) {
+                let (upper, lower) = if reversed {
+                    (edge.to, edge.from)
+                } else {
+                    (edge.from, edge.to)
+                };
+                if upper != lower && layers[lower] <= layers[upper] {
+                    layers[lower] = layers[upper] + 1;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        layers
+    }
+
+    /// Orders each layer by the average position of the items above, to keep edges short.
+    fn order_layers(&self, items: &[Item], chains: &[Chain], count: usize) -> Vec<Vec<usize>> {
+        let mut layers = vec![vec![]; count];
+        for (index, item) in items.iter().enumerate() {
+            layers[item.layer].push(index);
+        }
+        let mut above = vec![vec![]; items.len()];
+        for chain in chains {
+            for pair in chain.items.windows(2) {
+                above[pair[1]].push(pair[0]);
+            }
+        }
+        for layer in 1..count {
+            let positions: Vec<usize> = {
+                let mut positions = vec![0; items.len()];
+                for (position, &item) in layers[layer - 1].iter().enumerate() {
+                    positions[item] = position;
+                }
+                positions
+            };
+            let keys: Vec<(usize, f64)> = layers[layer]
+                .iter()
+                .enumerate()
+                .map(|(position, &item)| {
+                    let key = if above[item].is_empty() {
+                        position as f64
+                    } else {
+                        above[item]
+                            .iter()
+                            .map(|&a| positions[a] as f64)
+                            .sum::<f64>()
+                            / above[item].len() as f64
+                    };
+                    (item, key)
+                })
+                .collect();
+            let mut sorted = keys;
+            sorted.sort_by(|a, b| a.1.total_cmp(&b.1));
+            layers[layer] = sorted.into_iter().map(|(item, _)| item).collect();
+        }
+        layers
+    }
+
+    /// Lays out each layer across the main axis, centered on the widest layer.
+    fn place_across(&self, items: &mut [Item], layers: &[Vec<usize>]) {
+        let gap = if self.horizontal { 1 } else { 2 };
+        let extents: Vec<usize> = layers
+            .iter()
+            .map(|layer| {
+                layer
+                    .iter()
+                    .map(|&item| items[item].cross_size)
+                    .sum::<usize>()
+                    + gap * layer.len().saturating_sub(1)
+            })
+            .collect();
+        let widest = extents.iter().copied().max().unwrap_or(0);
+        for (layer, extent) in layers.iter().zip(extents) {
+            let mut cross = (widest - extent) / 2;
+            for &item in layer {
+                items[item].cross = cross;
+                cross += items[item].cross_size + gap;
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+mod canvas;
+mod flowchart;
+mod sequence;
+
+use flowchart::Flowchart;
+use sequence::Sequence;
+
+/// Fence languages esa renders as diagrams.
+const DIAGRAM_LANGUAGES: [&str; 4] = ["mermaid", "uml", "plantuml", "puml"];
+
+pub fn is_diagram(language: &str) -> bool {
+    DIAGRAM_LANGUAGES.contains(&language)
+}
+
+/// Draws a mermaid flowchart or sequence diagram, or a PlantUML sequence diagram, with
+/// box-drawing characters. `None` for other kinds of diagrams.
+pub fn render(language: &str, lines: &[&str]) -> Option<Vec<String>> {
+    match language {
+        "mermaid" => Flowchart::parse(lines)
+            .map(|chart| chart.render())
+            .or_else(|| Sequence::parse_mermaid(lines).map(|sequence| sequence.render())),
+        "uml" | "plantuml" | "puml" => {
+            Sequence::parse_plantuml(lines).map(|sequence| sequence.render())
+        }
+        _ => None,
+    }
+}
+
+/// Unquotes a label and turns its `<br>` line breaks into spaces.
+fn clean_label(label: &str) -> String {
+    let label = label.trim();
+    let label = label
+        .strip_prefix('"')
+        .and_then(|label| label.strip_suffix('"'))
+        .unwrap_or(label);
+    ["<br>", "<br/>", "<br />"]
+        .iter()
+        .fold(label.to_string(), |label, br| label.replace(br, " "))
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        "flowchart_top_down",
+        "mermaid",
+        "graph TD\n  A[Start] --> B{Is it OK?}\n  B -->|Yes| C(Deploy)\n  B -->|No| D[Rollback]\n  D --> A\n  C --> E[Done]\n  A --> E"
+    )]
+    #[case(
+        "flowchart_left_right",
+        "mermaid",
+        "flowchart LR; build[ビルド] --> test[テスト] -- pass --> release([リリース]); test -.-> fix[修正]"
+    )]
+    #[case(
+        "sequence_mermaid",
+        "mermaid",
+        "sequenceDiagram\n  participant U as User\n  participant A as API\n  participant DB\n  U->>A: GET /posts\n  A->>DB: SELECT\n  DB-->>A: rows\n  Note right of DB: indexed\n  loop every page\n    A->>A: render\n  end\n  A-->>U: 200 OK"
+    )]
+    #[case(
+        "sequence_plantuml",
+        "uml",
+        "@startuml\nactor Alice\nparticipant \"esa API\" as esa\nAlice -> esa: PATCH /posts/1\nesa --> Alice: 409 Conflict\nnote over Alice, esa: retry with the latest revision\n@enduml"
+    )]
+    fn test_render(#[case] name: &str, #[case] language: &str, #[case] source: &str) {
+        let lines: Vec<&str> = source.lines().collect();
+        assert_snapshot!(name, render(language, &lines).unwrap().join("\n"));
+    }
+
+    #[rstest]
+    #[case("mermaid", "pie title Pets\n  \"Dogs\" : 386")]
+    #[case("mermaid", "classDiagram\n  Animal <|-- Duck")]
+    #[case(
+        "uml",
+        "@startuml\nclass Post\nclass Comment\nPost *-- Comment\n@enduml"
+    )]
+    fn test_render_unsupported(#[case] language: &str, #[case] source: &str) {
+        let lines: Vec<&str> = source.lines().collect();
+        assert!(render(language, &lines).is_none());
+    }
+}
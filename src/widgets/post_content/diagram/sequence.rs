@@ -0,0 +1,474 @@
+use unicode_width::UnicodeWidthStr;
+
+use super::{canvas::Canvas, clean_label};
+
+/// Mermaid arrows, each found at its earliest position with longer ones winning ties.
+const MERMAID_ARROWS: [Arrow; 8] = [
+    Arrow::new("-->>", true, Head::Filled),
+    Arrow::new("->>", false, Head::Filled),
+    Arrow::new("--x", true, Head::Cross),
+    Arrow::new("-x", false, Head::Cross),
+    Arrow::new("--)", true, Head::Open),
+    Arrow::new("-)", false, Head::Open),
+    Arrow::new("-->", true, Head::None),
+    Arrow::new("->", false, Head::None),
+];
+
+const PLANTUML_ARROWS: [Arrow; 6] = [
+    Arrow::new("-->>", true, Head::Open),
+    Arrow::new("->>", false, Head::Open),
+    Arrow::new("-->", true, Head::Filled),
+    Arrow::new("->", false, Head::Filled),
+    Arrow::new("<--", true, Head::Filled).reversed(),
+    Arrow::new("<-", false, Head::Filled).reversed(),
+];
+
+const PLANTUML_PARTICIPANTS: [&str; 8] = [
+    "participant",
+    "actor",
+    "boundary",
+    "control",
+    "entity",
+    "database",
+    "collections",
+    "queue",
+];
+
+const GROUPS: [&str; 10] = [
+    "alt", "else", "opt", "loop", "par", "and", "critical", "option", "break", "group",
+];
+
+#[derive(Clone, Copy)]
+enum Head {
+    None,
+    Filled,
+    Open,
+    Cross,
+}
+
+impl Head {
+    fn char(self, rightwards: bool) -> Option<char> {
+        match (self, rightwards) {
+            (Head::None, _) => None,
+            (Head::Filled, true) => Some('▶'),
+            (Head::Filled, false) => Some('◀'),
+            (Head::Open, true) => Some('>'),
+            (Head::Open, false) => Some('<'),
+            (Head::Cross, _) => Some('x'),
+        }
+    }
+}
+
+struct Arrow {
+    token: &'static str,
+    dashed: bool,
+    head: Head,
+    /// The arrow points from the right participant to the left one, as in `A <- B`.
+    reversed: bool,
+}
+
+impl Arrow {
+    const fn new(token: &'static str, dashed: bool, head: Head) -> Self {
+        Self {
+            token,
+            dashed,
+            head,
+            reversed: false,
+        }
+    }
+
+    const fn reversed(self) -> Self {
+        Self {
+            reversed: true,
+            ..self
+        }
+    }
+}
+
+enum Placement {
+    Left,
+    Right,
+    Over,
+}
+
+enum Event {
+    Message {
+        from: usize,
+        to: usize,
+        text: String,
+        dashed: bool,
+        head: Head,
+    },
+    Note {
+        first: usize,
+        last: usize,
+        placement: Placement,
+        text: String,
+    },
+    /// The start of a block such as `loop` or `alt`, or a divider.
+    Section(String),
+}
+
+/// A mermaid `sequenceDiagram` or a PlantUML sequence diagram.
+#[derive(Default)]
+pub struct Sequence {
+    participants: Vec<(String, String)>,
+    events: Vec<Event>,
+}
+
+impl Sequence {
+    pub fn parse_mermaid(lines: &[&str]) -> Option<Self> {
+        let mut lines = lines
+            .iter()
+            .map(|line| line.split("%%").next().unwrap_or("").trim())
+            .filter(|line| !line.is_empty());
+        if lines.next()? != "sequenceDiagram" {
+            return None;
+        }
+        let mut sequence = Self::default();
+        for line in lines {
+            let (keyword, rest) = split_keyword(line);
+            match keyword {
+                "participant" | "actor" => sequence.declare(rest),
+                "Note" | "note" => sequence.note(rest)?,
+                "end" | "activate" | "deactivate" | "autonumber" | "rect" | "box" | "title" => {}
+                _ if GROUPS.contains(&keyword) => sequence.section(keyword, rest),
+                _ => sequence.message(line, &MERMAID_ARROWS)?,
+            }
+        }
+        sequence.has_messages().then_some(sequence)
+    }
+
+    /// Parses the sequence diagrams among PlantUML sources, `None` for other diagram kinds.
+    pub fn parse_plantuml(lines: &[&str]) -> Option<Self> {
+        let mut sequence = Self::default();
+        let mut note: Option<(String, Vec<String>)> = None;
+        for line in lines.iter().map(|line| line.trim()) {
+            if let Some((target, text)) = &mut note {
+                if line.eq_ignore_ascii_case("end note") {
+                    let rest = format!("{}: {}", target, text.join(" "));
+                    sequence.note(&rest)?;
+                    note = None;
+                } else {
+                    text.push(line.to_string());
+                }
+                continue;
+            }
+            if line.is_empty() || line.starts_with('\'') || line == "..." || line == "|||" {
+                continue;
+            }
+            if line.starts_with("@end") {
+                break;
+            }
+            if let Some(divider) = line.strip_prefix("==") {
+                sequence.section(divider.trim_matches('=').trim(), "");
+                continue;
+            }
+            let (keyword, rest) = split_keyword(line);
+            match keyword {
+                _ if keyword.starts_with("@start") => {}
+                _ if PLANTUML_PARTICIPANTS.contains(&keyword) => sequence.declare(rest),
+                "note" if rest.contains(':') => sequence.note(rest)?,
+                "note" => note = Some((rest.to_string(), vec![])),
+                "end" | "activate" | "deactivate" | "autonumber" | "skinparam" | "title"
+                | "hide" | "show" | "autoactivate" => {}
+                _ if GROUPS.contains(&keyword) => sequence.section(keyword, rest),
+                _ => sequence.message(line, &PLANTUML_ARROWS)?,
+            }
+        }
+        sequence.has_messages().then_some(sequence)
+    }
+
+    fn has_messages(&self) -> bool {
+        self.events
+            .iter()
+            .any(|event| matches!(event, Event::Message { .. }))
+    }
+
+    fn participant(&mut self, id: &str) -> usize {
+        let id = id.trim().trim_matches('"');
+        match self.participants.iter().position(|(known, _)| known == id) {
+            Some(index) => index,
+            None => {
+                self.participants.push((id.to_string(), id.to_string()));
+                self.participants.len() - 1
+            }
+        }
+    }
+
+    /// Declares `A`, `A as Alice` or PlantUML's `"Alice Smith" as A`.
+    fn declare(&mut self, rest: &str) {
+        let (id, label) = match rest.split_once(" as ") {
+            Some((first, second)) if first.trim().starts_with('"') => (second, Some(first)),
+            Some((first, second)) => (first, Some(second)),
+            None => (rest, None),
+        };
+        // PlantUML may follow the name with a color or an order
+        let id = id.split_whitespace().next().unwrap_or("");
+        if id.is_empty() {
+            return;
+        }
+        let index = self.participant(id);
+        if let Some(label) = label {
+            self.participants[index].1 = clean_label(label.trim().trim_matches('"'));
+        }
+    }
+
+    /// Parses `right of A: text`, `left of A: text` or `over A, B: text`.
+    fn note(&mut self, rest: &str) -> Option<()> {
+        let (target, text) = rest.split_once(':')?;
+        let target = target.trim();
+        let (placement, ids) = if let Some(ids) = target.strip_prefix("left of") {
+            (Placement::Left, ids)
+        } else if let Some(ids) = target.strip_prefix("right of") {
+            (Placement::Right, ids)
+        } else {
+            (Placement::Over, target.strip_prefix("over")?)
+        };
+        let indexes: Vec<usize> = ids.split(',').map(|id| self.participant(id)).collect();
+        self.events.push(Event::Note {
+            first: *indexes.iter().min()?,
+            last: *indexes.iter().max()?,
+            placement,
+            text: clean_label(text),
+        });
+        Some(())
+    }
+
+    fn section(&mut self, keyword: &str, rest: &str) {
+        let text = format!("{} {}", keyword, rest);
+        self.events.push(Event::Section(text.trim().to_string()));
+    }
+
+    fn message(&mut self, line: &str, arrows: &[Arrow]) -> Option<()> {
+        let (position, arrow) = arrows
+            .iter()
+            .filter_map(|arrow| line.find(arrow.token).map(|position| (position, arrow)))
+            .min_by_key(|(position, _)| *position)?;
+        let from = line[..position].trim();
+        let (to, text) = line[position + arrow.token.len()..]
+            .split_once(':')
+            .unwrap_or((&line[position + arrow.token.len()..], ""));
+        let to = to.trim().trim_start_matches(['+', '-']).trim();
+        if from.is_empty() || to.is_empty() {
+            return None;
+        }
+        let (from, to) = if arrow.reversed {
+            (to, from)
+        } else {
+            (from, to)
+        };
+        let from = self.participant(from);
+        let to = self.participant(to);
+        self.events.push(Event::Message {
+            from,
+            to,
+            text: clean_label(text),
+            dashed: arrow.dashed,
+            head: arrow.head,
+        });
+        Some(())
+    }
+}
+
+impl Sequence {
+    /// Draws the participants as boxes with their lifelines and the messages as arrows
+    /// between them, from top to bottom.
+    pub fn render(&self) -> Vec<String> {
+        let centers = self.centers();
+        let widths: Vec<usize> = self
+            .participants
+            .iter()
+            .map(|(_, label)| label.width() + 4)
+            .collect();
+        let total_width = centers
+            .iter()
+            .zip(&widths)
+            .map(|(center, width)| center + width - width / 2)
+            .max()
+            .unwrap_or(0);
+        let mut canvas = Canvas::default();
+        self.draw_participants(&mut canvas, &centers, 0);
+
+        let mut notes = vec![];
+        let mut y = 3;
+        for event in &self.events {
+            match event {
+                Event::Message {
+                    from,
+                    to,
+                    text,
+                    dashed,
+                    head,
+                } if from == to => {
+                    let x = centers[*from];
+                    canvas.line((x, y), (x + 3, y));
+                    canvas.line((x + 3, y), (x + 3, y + 1));
+                    if *dashed {
+                        canvas.restyle_horizontal(x, x + 3, y, '╌');
+                    }
+                    match head.char(false) {
+                        Some(head) => {
+                            canvas.line((x + 3, y + 1), (x + 1, y + 1));
+                            canvas.set_char(x + 1, y + 1, head);
+                        }
+                        None => canvas.line((x + 3, y + 1), (x, y + 1)),
+                    }
+                    canvas.text(x + 5, y, text);
+                    y += 2;
+                }
+                Event::Message {
+                    from,
+                    to,
+                    text,
+                    dashed,
+                    head,
+                } => {
+                    let (from, to) = (centers[*from], centers[*to]);
+                    let rightwards = from < to;
+                    let middle = (from + to) / 2;
+                    canvas.text(middle.saturating_sub(text.width() / 2), y, text);
+                    let head = head.char(rightwards);
+                    let end = match (head, rightwards) {
+                        (None, _) => to,
+                        (Some(_), true) => to - 1,
+                        (Some(_), false) => to + 1,
+                    };
+                    canvas.line((from, y + 1), (end, y + 1));
+                    if *dashed {
+                        canvas.restyle_horizontal(from, end, y + 1, '╌');
+                    }
+                    if let Some(head) = head {
+                        canvas.set_char(end, y + 1, head);
+                    }
+                    y += 2;
+                }
+                Event::Note {
+                    first,
+                    last,
+                    placement,
+                    text,
+                } => {
+                    let mut width = text.width() + 4;
+                    let x = match placement {
+                        Placement::Left => centers[*first] - 1 - width,
+                        Placement::Right => centers[*last] + 2,
+                        Placement::Over if first == last => centers[*first] - width / 2,
+                        Placement::Over => {
+                            width = width.max(centers[*last] - centers[*first] + 5);
+                            centers[*first] - 2
+                        }
+                    };
+                    canvas.rect(x, y, width, 3);
+                    canvas.clear(x + 1, y + 1, width - 2, 1);
+                    canvas.text(x + 2, y + 1, text);
+                    notes.push((x, y, width));
+                    y += 3;
+                }
+                Event::Section(text) => {
+                    for x in 0..total_width {
+                        canvas.set_char(x, y, '┄');
+                    }
+                    canvas.text(1, y, &format!(" [{}] ", text));
+                    y += 1;
+                }
+            }
+        }
+
+        let footer = y + 1;
+        self.draw_participants(&mut canvas, &centers, footer);
+        for &x in &centers {
+            // lifelines stop at the notes drawn over them
+            let covered = |row: usize| {
+                notes.iter().any(|&(nx, ny, width)| {
+                    (nx..nx + width).contains(&x) && (ny..ny + 3).contains(&row)
+                })
+            };
+            let mut run_start = None;
+            for row in 2..=footer {
+                match (covered(row), run_start) {
+                    (false, None) => run_start = Some(row),
+                    (true, Some(start)) => {
+                        canvas.line((x, start), (x, row - 1));
+                        run_start = None;
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(start) = run_start {
+                canvas.line((x, start), (x, footer));
+            }
+        }
+        canvas.into_lines()
+    }
+
+    fn draw_participants(&self, canvas: &mut Canvas, centers: &[usize], y: usize) {
+        for ((_, label), &center) in self.participants.iter().zip(centers) {
+            let width = label.width() + 4;
+            let x = center - width / 2;
+            canvas.rect(x, y, width, 3);
+            canvas.text(x + 2, y + 1, label);
+        }
+    }
+
+    /// Spaces the lifelines so that every message and note label fits between them.
+    fn centers(&self) -> Vec<usize> {
+        let widths: Vec<usize> = self
+            .participants
+            .iter()
+            .map(|(_, label)| label.width() + 4)
+            .collect();
+        let mut centers = Vec::with_capacity(widths.len());
+        let mut left = 0;
+        for width in &widths {
+            centers.push(left + width / 2);
+            left += width + 2;
+        }
+
+        let mut constraints = vec![];
+        let mut margin = 0;
+        for event in &self.events {
+            match event {
+                Event::Message { from, to, text, .. } if from == to => {
+                    constraints.push((*from, from + 1, text.width() + 7));
+                }
+                Event::Message { from, to, text, .. } => {
+                    constraints.push((*from.min(to), *from.max(to), text.width() + 4));
+                }
+                Event::Note {
+                    first,
+                    last,
+                    placement,
+                    text,
+                } => {
+                    let width = text.width() + 4;
+                    match placement {
+                        Placement::Left if *first == 0 => margin = margin.max(width + 1),
+                        Placement::Left => constraints.push((first - 1, *first, width + 3)),
+                        Placement::Right => constraints.push((*last, last + 1, width + 3)),
+                        Placement::Over if first == last => margin = margin.max(width / 2),
+                        Placement::Over => {}
+                    }
+                }
+                Event::Section(_) => {}
+            }
+        }
+        constraints.retain(|&(_, right, _)| right < centers.len());
+        constraints.sort_by_key(|&(left, right, _)| right - left);
+        for (left, right, needed) in constraints {
+            let gap = centers[right] - centers[left];
+            if gap < needed {
+                for center in &mut centers[right..] {
+                    *center += needed - gap;
+                }
+            }
+        }
+        let shift = margin.saturating_sub(centers.first().copied().unwrap_or(0));
+        centers.iter().map(|center| center + shift).collect()
+    }
+}
+
+fn split_keyword(line: &str) -> (&str, &str) {
+    line.split_once(char::is_whitespace)
+        .map_or((line, ""), |(keyword, rest)| (keyword, rest.trim()))
+}
@@ -0,0 +1,162 @@
+use chrono::{DateTime, Utc};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+
+use crate::{
+    domains::{Post, PostBody, Theme, TimeDisplay},
+    widgets::icons::{
+        COMMENTS_ICON, STAR_ICON, TASKS_ICON, UNSTAR_ICON, UNWATCH_ICON, USER_ICON, WATCH_ICON,
+    },
+};
+
+/// The lines describing the post above its body, or only its title when collapsed.
+pub fn header_lines(
+    post: &Post,
+    body: &PostBody,
+    time: &TimeDisplay,
+    now: DateTime<Utc>,
+    theme: &Theme,
+    expanded: bool,
+) -> Vec<Line<'static>> {
+    let muted = Style::new().fg(theme.muted);
+    let mut title = vec![
+        Span::styled(if expanded { "▾ " } else { "▸ " }, muted),
+        Span::styled(
+            post.name.clone(),
+            Style::new().fg(theme.primary).add_modifier(Modifier::BOLD),
+        ),
+    ];
+    if post.wip {
+        title.push(Span::raw(" "));
+        title.push(Span::styled(
+            " WIP ",
+            Style::new()
+                .fg(theme.warning)
+                .add_modifier(Modifier::REVERSED | Modifier::BOLD),
+        ));
+    }
+    if body.stale {
+        title.push(Span::raw(" "));
+        title.push(Span::styled(
+            " cached ",
+            Style::new()
+                .fg(theme.muted)
+                .add_modifier(Modifier::REVERSED),
+        ));
+    }
+    if body.conflicted {
+        title.push(Span::raw(" "));
+        title.push(Span::styled(
+            " conflict ",
+            Style::new()
+                .fg(theme.error)
+                .add_modifier(Modifier::REVERSED | Modifier::BOLD),
+        ));
+    }
+    if !expanded {
+        return vec![Line::from(title)];
+    }
+
+    let mut lines = vec![];
+    if let Some(category) = &post.category {
+        lines.push(Line::styled(format!("{}/", category), muted));
+    }
+    lines.push(Line::from(title));
+    if !post.tags.is_empty() {
+        let tags = post
+            .tags
+            .iter()
+            .map(|tag| format!("#{}", tag.label))
+            .collect::<Vec<_>>()
+            .join(" ");
+        lines.push(Line::styled(tags, Style::new().fg(theme.accent)));
+    }
+
+    let mut authors = format!(
+        "{} created {} by @{}",
+        USER_ICON,
+        time.format(post.created_at, now),
+        post.created_by.id.0
+    );
+    if post.updated_at != post.created_at {
+        authors.push_str(&format!(
+            " · updated {} by @{}",
+            time.format(post.updated_at, now),
+            post.updated_by.id.0
+        ));
+    }
+    authors.push_str(&format!(" · rev {}", body.revision_number));
+    lines.push(Line::styled(authors, muted));
+
+    let star_icon = if post.starred { STAR_ICON } else { UNSTAR_ICON };
+    let watch_icon = if post.watched {
+        WATCH_ICON
+    } else {
+        UNWATCH_ICON
+    };
+    let mut counts = format!(
+        "{} {}  {} {}  {} {}",
+        star_icon, post.stars, watch_icon, post.watches, COMMENTS_ICON, post.comments_count
+    );
+    if post.tasks_count > 0 {
+        counts.push_str(&format!(
+            "  {} {}/{}",
+            TASKS_ICON, post.done_tasks_count, post.tasks_count
+        ));
+    }
+    lines.push(Line::styled(counts, muted));
+    lines.push(Line::styled(
+        post.url.to_string(),
+        Style::new()
+            .fg(theme.link)
+            .add_modifier(Modifier::UNDERLINED),
+    ));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::{ThemeConfig, UserId, sample_post};
+    use chrono::TimeZone;
+    use insta::assert_snapshot;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn post() -> Post {
+        sample_post()
+    }
+
+    #[rstest]
+    #[case("header_expanded", true, false, false)]
+    #[case("header_collapsed", false, false, false)]
+    #[case("header_stale", false, true, false)]
+    #[case("header_conflicted", false, false, true)]
+    fn test_header_lines(
+        post: Post,
+        #[case] name: &str,
+        #[case] expanded: bool,
+        #[case] stale: bool,
+        #[case] conflicted: bool,
+    ) {
+        let time = TimeDisplay::new(Some("Asia/Tokyo"), false);
+        let now = Utc.with_ymd_and_hms(2026, 5, 1, 12, 0, 0).unwrap();
+        let theme = Theme::from_config(&ThemeConfig::default());
+        let body = PostBody {
+            body_md: String::new(),
+            revision_number: 7,
+            revised_by: UserId("taro".to_string()),
+            stale,
+            conflicted,
+        };
+        let lines = header_lines(&post, &body, &time, now, &theme, expanded);
+        let text = lines
+            .iter()
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_snapshot!(name, text);
+    }
+}
@@ -0,0 +1,40 @@
+use super::Content;
+
+const MAX_HISTORY: usize = 100;
+
+/// A post shown in the content pane, with the scroll position it was left at.
+#[derive(Clone)]
+pub struct HistoryEntry {
+    pub content: Content,
+    pub scroll: u16,
+}
+
+/// Back/forward stacks of the posts opened in the content pane.
+#[derive(Default)]
+pub struct History {
+    back: Vec<HistoryEntry>,
+    forward: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// Records the entry being left when a new post is opened.
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.back.push(entry);
+        if self.back.len() > MAX_HISTORY {
+            self.back.remove(0);
+        }
+        self.forward.clear();
+    }
+
+    pub fn back(&mut self, current: Option<HistoryEntry>) -> Option<HistoryEntry> {
+        let entry = self.back.pop()?;
+        self.forward.extend(current);
+        Some(entry)
+    }
+
+    pub fn forward(&mut self, current: Option<HistoryEntry>) -> Option<HistoryEntry> {
+        let entry = self.forward.pop()?;
+        self.back.extend(current);
+        Some(entry)
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use image::DynamicImage;
+use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+use ratatui_image::{
+    Image, Resize,
+    picker::{Picker, ProtocolType},
+    protocol::Protocol,
+};
+
+use crate::{domains::DisplayConfig, http_gateways::EsaClientHttpGateway, image_cache::ImageCache};
+
+const ATTACHMENT_HOSTS: [&str; 2] = ["files.esa.io", "img.esa.io"];
+
+/// Attached images of the posts shown so far, encoded for the terminal's graphics protocol.
+pub struct InlineImages {
+    picker: Picker,
+    cache: ImageCache,
+    max_height: u16,
+    max_bytes: u64,
+    images: HashMap<String, DynamicImage>,
+    /// Encoded images for the current pane width, dropped when the width changes.
+    protocols: HashMap<String, Protocol>,
+    protocols_width: u16,
+}
+
+impl InlineImages {
+    pub fn new(picker: Picker, cache: ImageCache, display: &DisplayConfig) -> Self {
+        Self {
+            picker,
+            cache,
+            max_height: display.image_max_height,
+            max_bytes: display.image_max_bytes,
+            images: HashMap::new(),
+            protocols: HashMap::new(),
+            protocols_width: 0,
+        }
+    }
+}
+
+impl InlineImages {
+    /// Loads the esa attachments among `urls` from the cache, downloading the missing ones.
+    pub async fn load(&mut self, api: &dyn EsaClientHttpGateway, urls: &[String]) {
+        for url in urls {
+            if self.images.contains_key(url) || !is_attachment(url) {
+                continue;
+            }
+            let bytes = match self.cache.load(url) {
+                Some(bytes) => bytes,
+                None => match api.fetch_attachment(url, self.max_bytes).await {
+                    Ok(bytes) => {
+                        if let Err(e) = self.cache.store(url, &bytes) {
+                            eprintln!("failed to cache image: {}", e);
+                        }
+                        bytes
+                    }
+                    Err(e) => {
+                        eprintln!("failed to download image: {}", e);
+                        continue;
+                    }
+                },
+            };
+            match image::load_from_memory(&bytes) {
+                Ok(image) => self.insert(url.clone(), image),
+                Err(e) => eprintln!("failed to decode image: {}", e),
+            }
+        }
+    }
+
+    pub fn insert(&mut self, url: String, image: DynamicImage) {
+        self.protocols.remove(&url);
+        self.images.insert(url, image);
+    }
+
+    /// Graphics protocols can only draw whole images, half-blocks can be cut at any row.
+    pub fn can_clip(&self) -> bool {
+        self.picker.protocol_type() == ProtocolType::Halfblocks
+    }
+
+    /// Returns the image encoded to fit `width` columns and the height cap.
+    pub fn protocol(&mut self, url: &str, width: u16) -> Option<&Protocol> {
+        if width != self.protocols_width {
+            self.protocols.clear();
+            self.protocols_width = width;
+        }
+        if !self.protocols.contains_key(url) {
+            let image = self.images.get(url)?;
+            let area = Rect::new(0, 0, width, self.max_height);
+            match self
+                .picker
+                .new_protocol(image.clone(), area, Resize::Fit(None))
+            {
+                Ok(protocol) => {
+                    self.protocols.insert(url.to_string(), protocol);
+                }
+                Err(e) => {
+                    eprintln!("failed to encode image: {}", e);
+                    self.images.remove(url);
+                    return None;
+                }
+            }
+        }
+        self.protocols.get(url)
+    }
+}
+
+/// Renders an encoded image into a buffer of its own size.
+pub fn render_image(protocol: &Protocol) -> Buffer {
+    let area = protocol.area();
+    let mut buf = Buffer::empty(Rect::new(0, 0, area.width, area.height));
+    Image::new(protocol).render(buf.area, &mut buf);
+    buf
+}
+
+fn is_attachment(url: &str) -> bool {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| ATTACHMENT_HOSTS.contains(&host)))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+
+    #[test]
+    fn test_render_image_with_halfblocks() {
+        let display = DisplayConfig {
+            image_max_height: 2,
+            ..DisplayConfig::default()
+        };
+        let mut images = InlineImages::new(
+            Picker::halfblocks(),
+            ImageCache::next_to(std::path::Path::new("/nonexistent/config.toml"), 100),
+            &display,
+        );
+        let image = image::load_from_memory(include_bytes!("fixtures/quadrants.png")).unwrap();
+        images.insert("quadrants.png".to_string(), image);
+
+        let protocol = images.protocol("quadrants.png", 10).unwrap();
+        assert_snapshot!(format!("{:?}", render_image(protocol)));
+    }
+}
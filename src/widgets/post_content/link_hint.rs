@@ -0,0 +1,129 @@
+use std::ops::Range;
+
+use md_tui::nodes::{textcomponent::TextComponent, word::WordType};
+use unicode_width::UnicodeWidthStr;
+
+const HINT_KEYS: &str = "asdfghjklqwertyuiopzxcvbnm";
+
+#[derive(Clone, Debug)]
+pub struct VisibleLink {
+    pub text: String,
+    pub url: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct LinkHint {
+    pub key: char,
+    pub link: VisibleLink,
+}
+
+pub fn assign_hints(links: &[VisibleLink]) -> Vec<LinkHint> {
+    HINT_KEYS
+        .chars()
+        .zip(links.iter().cloned())
+        .map(|(key, link)| LinkHint { key, link })
+        .collect()
+}
+
+/// Where a link of the post is drawn: one column range per row its words cover, with rows
+/// counted from the top of the post.
+#[derive(Clone, Debug)]
+pub struct LinkCells {
+    pub cells: Vec<(u16, Range<u16>)>,
+    pub url: String,
+}
+
+impl LinkCells {
+    pub fn contains(&self, row: u16, column: u16) -> bool {
+        self.cells
+            .iter()
+            .any(|(y, columns)| *y == row && columns.contains(&column))
+    }
+}
+
+/// Collects the links whose first line lies within `scroll..scroll + height`.
+///
+/// `components` are the text components paired with the row they start at.
+pub fn visible_links<'a>(
+    components: impl IntoIterator<Item = (u16, &'a TextComponent)>,
+    scroll: u16,
+    height: u16,
+) -> Vec<VisibleLink> {
+    let visible = scroll..scroll.saturating_add(height);
+    components
+        .into_iter()
+        .flat_map(|(y_offset, comp)| placed_links(y_offset, comp))
+        .filter(|(_, cells)| {
+            cells
+                .cells
+                .first()
+                .is_some_and(|(y, _)| visible.contains(y))
+        })
+        .map(|(link, _)| link)
+        .collect()
+}
+
+/// Collects where every link is drawn, for finding the link under a mouse click.
+pub fn link_cells<'a>(
+    components: impl IntoIterator<Item = (u16, &'a TextComponent)>,
+) -> Vec<LinkCells> {
+    components
+        .into_iter()
+        .flat_map(|(y_offset, comp)| placed_links(y_offset, comp))
+        .map(|(_, cells)| cells)
+        .collect()
+}
+
+/// The links of a component, skipping footnotes, with the cells their words cover.
+fn placed_links(y_offset: u16, comp: &TextComponent) -> Vec<(VisibleLink, LinkCells)> {
+    let urls = comp
+        .meta_info()
+        .iter()
+        .filter(|w| matches!(w.kind(), WordType::LinkData | WordType::FootnoteInline))
+        .map(|w| (w.kind(), w.content().to_string()));
+
+    // consecutive link words form one link, in the same order as the meta info
+    let mut groups: Vec<(String, LinkCells)> = vec![];
+    let mut in_link = false;
+    for (row, words) in comp.content().iter().enumerate() {
+        let y = y_offset.saturating_add(row as u16);
+        let mut x: u16 = 0;
+        for word in words {
+            let width = word.content().width() as u16;
+            let columns = x..x.saturating_add(width);
+            x = x.saturating_add(width);
+            if matches!(word.kind(), WordType::Link | WordType::FootnoteInline) {
+                if in_link && let Some((text, link)) = groups.last_mut() {
+                    text.push_str(word.content());
+                    match link.cells.last_mut() {
+                        Some((last_y, last)) if *last_y == y => last.end = columns.end,
+                        _ => link.cells.push((y, columns)),
+                    }
+                } else {
+                    let link = LinkCells {
+                        cells: vec![(y, columns)],
+                        url: String::new(),
+                    };
+                    groups.push((word.content().to_string(), link));
+                }
+                in_link = true;
+            } else {
+                in_link = false;
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .zip(urls)
+        .filter(|(_, (kind, _))| *kind == WordType::LinkData)
+        .map(|((text, mut link), (_, url))| {
+            link.url = url.clone();
+            let visible = VisibleLink {
+                text: text.trim().to_string(),
+                url,
+            };
+            (visible, link)
+        })
+        .collect()
+}
@@ -0,0 +1,620 @@
+/// A run of converted math, `verbatim` when it is a macro we don't know, kept as written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MathSpan {
+    pub text: String,
+    pub verbatim: bool,
+}
+
+const SYMBOLS: [(&str, &str); 120] = [
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ϵ"),
+    ("varepsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("vartheta", "ϑ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("pi", "π"),
+    ("varpi", "ϖ"),
+    ("rho", "ρ"),
+    ("varrho", "ϱ"),
+    ("sigma", "σ"),
+    ("varsigma", "ς"),
+    ("tau", "τ"),
+    ("upsilon", "υ"),
+    ("phi", "ϕ"),
+    ("varphi", "φ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Theta", "Θ"),
+    ("Lambda", "Λ"),
+    ("Xi", "Ξ"),
+    ("Pi", "Π"),
+    ("Sigma", "Σ"),
+    ("Upsilon", "Υ"),
+    ("Phi", "Φ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    ("sum", "∑"),
+    ("prod", "∏"),
+    ("coprod", "∐"),
+    ("int", "∫"),
+    ("iint", "∬"),
+    ("iiint", "∭"),
+    ("oint", "∮"),
+    ("infty", "∞"),
+    ("partial", "∂"),
+    ("nabla", "∇"),
+    ("pm", "±"),
+    ("mp", "∓"),
+    ("times", "×"),
+    ("div", "÷"),
+    ("cdot", "⋅"),
+    ("cdots", "⋯"),
+    ("ldots", "…"),
+    ("dots", "…"),
+    ("vdots", "⋮"),
+    ("ddots", "⋱"),
+    ("leq", "≤"),
+    ("le", "≤"),
+    ("geq", "≥"),
+    ("ge", "≥"),
+    ("neq", "≠"),
+    ("ne", "≠"),
+    ("ll", "≪"),
+    ("gg", "≫"),
+    ("approx", "≈"),
+    ("equiv", "≡"),
+    ("sim", "∼"),
+    ("simeq", "≃"),
+    ("cong", "≅"),
+    ("propto", "∝"),
+    ("in", "∈"),
+    ("notin", "∉"),
+    ("ni", "∋"),
+    ("subset", "⊂"),
+    ("subseteq", "⊆"),
+    ("supset", "⊃"),
+    ("supseteq", "⊇"),
+    ("cup", "∪"),
+    ("cap", "∩"),
+    ("setminus", "∖"),
+    ("emptyset", "∅"),
+    ("varnothing", "∅"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("neg", "¬"),
+    ("lnot", "¬"),
+    ("land", "∧"),
+    ("wedge", "∧"),
+    ("lor", "∨"),
+    ("vee", "∨"),
+    ("oplus", "⊕"),
+    ("otimes", "⊗"),
+    ("to", "→"),
+    ("rightarrow", "→"),
+    ("leftarrow", "←"),
+    ("gets", "←"),
+    ("leftrightarrow", "↔"),
+    ("Rightarrow", "⇒"),
+    ("implies", "⇒"),
+    ("Leftarrow", "⇐"),
+    ("Leftrightarrow", "⇔"),
+    ("iff", "⇔"),
+    ("mapsto", "↦"),
+    ("langle", "⟨"),
+    ("rangle", "⟩"),
+    ("lfloor", "⌊"),
+    ("rfloor", "⌋"),
+    ("lceil", "⌈"),
+    ("rceil", "⌉"),
+    ("circ", "∘"),
+    ("ast", "∗"),
+    ("star", "⋆"),
+    ("prime", "′"),
+    ("hbar", "ℏ"),
+    ("ell", "ℓ"),
+    ("aleph", "ℵ"),
+];
+
+/// Operators written upright, shown as their name.
+const FUNCTIONS: [&str; 24] = [
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "log", "ln", "lg", "exp", "lim", "max", "min", "sup", "inf", "det", "gcd", "arg",
+];
+
+/// Font and text macros whose argument is shown as is.
+const FONTS: [&str; 13] = [
+    "mathrm",
+    "mathbf",
+    "mathit",
+    "mathsf",
+    "mathtt",
+    "mathcal",
+    "boldsymbol",
+    "text",
+    "textrm",
+    "textbf",
+    "textit",
+    "operatorname",
+    "mbox",
+];
+
+/// Sizing and style macros that don't change the text.
+const IGNORED: [&str; 18] = [
+    "left",
+    "right",
+    "big",
+    "Big",
+    "bigg",
+    "Bigg",
+    "bigl",
+    "bigr",
+    "Bigl",
+    "Bigr",
+    "middle",
+    "limits",
+    "nolimits",
+    "displaystyle",
+    "textstyle",
+    "scriptstyle",
+    "nonumber",
+    "notag",
+];
+
+const ACCENTS: [(&str, char); 8] = [
+    ("hat", '\u{302}'),
+    ("widehat", '\u{302}'),
+    ("bar", '\u{304}'),
+    ("overline", '\u{305}'),
+    ("vec", '\u{20D7}'),
+    ("dot", '\u{307}'),
+    ("ddot", '\u{308}'),
+    ("tilde", '\u{303}'),
+];
+
+const DOUBLE_STRUCK: [(char, char); 7] = [
+    ('N', 'ℕ'),
+    ('Z', 'ℤ'),
+    ('Q', 'ℚ'),
+    ('R', 'ℝ'),
+    ('C', 'ℂ'),
+    ('P', 'ℙ'),
+    ('H', 'ℍ'),
+];
+
+const VULGAR_FRACTIONS: [(&str, &str, char); 8] = [
+    ("1", "2", '½'),
+    ("1", "3", '⅓'),
+    ("2", "3", '⅔'),
+    ("1", "4", '¼'),
+    ("3", "4", '¾'),
+    ("1", "5", '⅕'),
+    ("1", "6", '⅙'),
+    ("1", "8", '⅛'),
+];
+
+const SUPERSCRIPTS: &str = "0⁰1¹2²3³4⁴5⁵6⁶7⁷8⁸9⁹+⁺-⁻−⁻=⁼(⁽)⁾aᵃbᵇcᶜdᵈeᵉfᶠgᵍhʰiⁱjʲkᵏlˡmᵐnⁿoᵒpᵖrʳsˢtᵗuᵘvᵛwʷxˣyʸzᶻAᴬBᴮDᴰEᴱGᴳHᴴIᴵJᴶKᴷLᴸMᴹNᴺOᴼPᴾRᴿTᵀUᵁVⱽWᵂ′′*﹡∗﹡";
+const SUBSCRIPTS: &str =
+    "0₀1₁2₂3₃4₄5₅6₆7₇8₈9₉+₊-₋−₋=₌(₍)₎aₐeₑhₕiᵢjⱼkₖlₗmₘnₙoₒpₚrᵣsₛtₜuᵤvᵥxₓβᵦγᵧρᵨφᵩχᵪ";
+
+/// Converts LaTeX math into lines of Unicode text, keeping macros we don't know as written.
+pub fn to_unicode(latex: &str) -> Vec<Vec<MathSpan>> {
+    let mut parser = Parser {
+        chars: latex.chars().collect(),
+        pos: 0,
+    };
+    let mut spans = Spans::default();
+    parser.expression(&mut spans, false);
+    let mut lines = vec![vec![]];
+    for span in spans.0 {
+        let mut parts = span.text.split('\n');
+        if let Some(first) = parts.next() {
+            push(lines.last_mut().unwrap(), first, span.verbatim);
+        }
+        for part in parts {
+            lines.push(vec![]);
+            push(lines.last_mut().unwrap(), part, span.verbatim);
+        }
+    }
+    lines
+        .into_iter()
+        .map(|mut line| {
+            if let Some(first) = line.first_mut() {
+                first.text = first.text.trim_start().to_string();
+            }
+            if let Some(last) = line.last_mut() {
+                last.text = last.text.trim_end().to_string();
+            }
+            line.retain(|span| !span.text.is_empty());
+            line
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Converts math for use within a line of text.
+pub fn to_inline_text(latex: &str) -> String {
+    to_unicode(latex)
+        .iter()
+        .map(|line| {
+            line.iter()
+                .map(|span| span.text.as_str())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn push(line: &mut Vec<MathSpan>, text: &str, verbatim: bool) {
+    if text.is_empty() {
+        return;
+    }
+    match line.last_mut() {
+        Some(last) if last.verbatim == verbatim => last.text.push_str(text),
+        _ => line.push(MathSpan {
+            text: text.to_string(),
+            verbatim,
+        }),
+    }
+}
+
+#[derive(Default)]
+struct Spans(Vec<MathSpan>);
+
+impl Spans {
+    fn text(&mut self, text: &str) {
+        push(&mut self.0, text, false);
+    }
+
+    fn verbatim(&mut self, text: &str) {
+        push(&mut self.0, text, true);
+    }
+
+    fn extend(&mut self, other: Spans) {
+        for span in other.0 {
+            push(&mut self.0, &span.text, span.verbatim);
+        }
+    }
+
+    fn has_verbatim(&self) -> bool {
+        self.0.iter().any(|span| span.verbatim)
+    }
+
+    fn plain(&self) -> String {
+        self.0.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    /// A single letter, digit run or symbol that needs no parentheses around it.
+    fn is_atom(&self) -> bool {
+        let text = self.plain();
+        let text = text.trim();
+        text.chars().count() == 1 || (!text.is_empty() && text.chars().all(char::is_alphanumeric))
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Converts until the end of the input, or of the group when `in_group` is set.
+    fn expression(&mut self, out: &mut Spans, in_group: bool) {
+        while let Some(c) = self.next() {
+            match c {
+                '}' if in_group => return,
+                '{' => self.expression(out, true),
+                '^' | '_' => {
+                    let argument = self.argument();
+                    out.extend(script(argument, c == '^'));
+                }
+                '\\' => self.command(out),
+                '&' => out.text(" "),
+                '~' => out.text(" "),
+                c if c.is_whitespace() => {
+                    self.skip_whitespace();
+                    out.text(" ");
+                }
+                c => out.text(&c.to_string()),
+            }
+        }
+    }
+
+    /// Reads the argument of a macro or a script: a group, a macro or a single character.
+    fn argument(&mut self) -> Spans {
+        self.skip_whitespace();
+        let mut out = Spans::default();
+        match self.next() {
+            Some('{') => self.expression(&mut out, true),
+            Some('\\') => self.command(&mut out),
+            Some(c) => out.text(&c.to_string()),
+            None => {}
+        }
+        out
+    }
+
+    /// Reads the raw text of a `{...}` group, as used for environment names.
+    fn raw_group(&mut self) -> String {
+        self.skip_whitespace();
+        if self.peek() != Some('{') {
+            return String::new();
+        }
+        self.pos += 1;
+        let mut text = String::new();
+        while let Some(c) = self.next() {
+            if c == '}' {
+                break;
+            }
+            text.push(c);
+        }
+        text
+    }
+
+    fn skip_space_before_term(&mut self) {
+        let next = self.chars[self.pos..].iter().find(|c| !c.is_whitespace());
+        if next.is_some_and(|c| c.is_alphanumeric()) {
+            self.skip_whitespace();
+        }
+    }
+
+    fn optional_argument(&mut self) -> Option<Spans> {
+        self.skip_whitespace();
+        if self.peek() != Some('[') {
+            return None;
+        }
+        self.pos += 1;
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c != ']') {
+            self.pos += 1;
+        }
+        let inner: String = self.chars[start..self.pos].iter().collect();
+        self.pos += 1;
+        Some(to_spans(&inner))
+    }
+
+    fn command(&mut self, out: &mut Spans) {
+        let Some(first) = self.next() else {
+            out.verbatim("\\");
+            return;
+        };
+        if !first.is_ascii_alphabetic() {
+            match first {
+                '\\' => out.text("\n"),
+                ',' | ':' | ';' | ' ' => out.text(" "),
+                '!' => {}
+                '|' => out.text("‖"),
+                c => out.text(&c.to_string()),
+            }
+            return;
+        }
+        let mut name = first.to_string();
+        while let Some(c) = self.peek().filter(char::is_ascii_alphabetic) {
+            name.push(c);
+            self.pos += 1;
+        }
+        let name = name.as_str();
+        if let Some((_, symbol)) = SYMBOLS.iter().find(|(macro_name, _)| *macro_name == name) {
+            out.text(symbol);
+            // `\partial f` and `\alpha x` read as one term
+            if symbol.chars().all(|c| c.is_alphabetic() || c == '∂') {
+                self.skip_space_before_term();
+            }
+        } else if FUNCTIONS.contains(&name) {
+            out.text(name);
+        } else if FONTS.contains(&name) {
+            out.extend(self.argument());
+        } else if IGNORED.contains(&name) {
+            // `\left.` and `\right.` mark a missing delimiter
+            if self.peek() == Some('.') {
+                self.pos += 1;
+            }
+        } else if let Some((_, mark)) = ACCENTS.iter().find(|(accent, _)| *accent == name) {
+            let argument = self.argument();
+            if argument.has_verbatim() {
+                out.extend(argument);
+            } else {
+                out.text(&accent(&argument.plain(), *mark));
+            }
+        } else {
+            match name {
+                "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                    let numerator = self.argument();
+                    let denominator = self.argument();
+                    out.extend(fraction(numerator, denominator));
+                }
+                "sqrt" => {
+                    let index = self.optional_argument();
+                    let radicand = self.argument();
+                    if let Some(index) = index {
+                        out.extend(script(index, true));
+                    }
+                    out.text("√");
+                    out.extend(parenthesize(radicand));
+                }
+                "mathbb" => {
+                    let argument = self.argument().plain();
+                    let text: String = argument
+                        .chars()
+                        .map(|c| {
+                            DOUBLE_STRUCK
+                                .iter()
+                                .find(|(letter, _)| *letter == c)
+                                .map_or(c, |(_, struck)| *struck)
+                        })
+                        .collect();
+                    out.text(&text);
+                }
+                "not" => {
+                    let negated = self.argument().plain();
+                    match negated.as_str() {
+                        "=" => out.text("≠"),
+                        "∈" => out.text("∉"),
+                        _ => out.text(&format!("{}\u{338}", negated)),
+                    }
+                }
+                "quad" => out.text("  "),
+                "qquad" => out.text("    "),
+                "begin" | "end" => {
+                    self.raw_group();
+                }
+                _ => out.verbatim(&format!("\\{}", name)),
+            }
+        }
+    }
+}
+
+fn to_spans(latex: &str) -> Spans {
+    let mut parser = Parser {
+        chars: latex.chars().collect(),
+        pos: 0,
+    };
+    let mut out = Spans::default();
+    parser.expression(&mut out, false);
+    out
+}
+
+/// Writes the argument of `^` or `_` with Unicode super- or subscripts when every
+/// character has one, and as `^(...)` otherwise.
+fn script(argument: Spans, superscript: bool) -> Spans {
+    let table = if superscript {
+        SUPERSCRIPTS
+    } else {
+        SUBSCRIPTS
+    };
+    let mut out = Spans::default();
+    if !argument.has_verbatim() {
+        let text = argument.plain();
+        let text = text.trim();
+        let mapped: Option<String> = text.chars().map(|c| lookup(table, c)).collect();
+        if let Some(mapped) = mapped.filter(|mapped| !mapped.is_empty()) {
+            out.text(&mapped);
+            return out;
+        }
+    }
+    out.text(if superscript { "^" } else { "_" });
+    out.extend(parenthesize(argument));
+    out
+}
+
+/// Finds `c` in a table of pairs written one after the other.
+fn lookup(table: &str, c: char) -> Option<char> {
+    let chars: Vec<char> = table.chars().collect();
+    chars
+        .chunks(2)
+        .find(|pair| pair[0] == c)
+        .map(|pair| pair[1])
+}
+
+fn fraction(numerator: Spans, denominator: Spans) -> Spans {
+    let (top, bottom) = (numerator.plain(), denominator.plain());
+    let mut out = Spans::default();
+    if let Some((_, _, vulgar)) = VULGAR_FRACTIONS
+        .iter()
+        .find(|(n, d, _)| *n == top.trim() && *d == bottom.trim())
+    {
+        out.text(&vulgar.to_string());
+        return out;
+    }
+    out.extend(parenthesize(numerator));
+    out.text("/");
+    out.extend(parenthesize(denominator));
+    out
+}
+
+fn parenthesize(spans: Spans) -> Spans {
+    if spans.is_atom() {
+        return spans;
+    }
+    let mut out = Spans::default();
+    out.text("(");
+    out.extend(spans);
+    out.text(")");
+    out
+}
+
+fn accent(text: &str, mark: char) -> String {
+    let text = text.trim();
+    if mark == '\u{305}' {
+        // an overline covers every character
+        return text.chars().flat_map(|c| [c, mark]).collect();
+    }
+    format!("{}{}", text, mark)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(r"E = mc^2", "E = mc²")]
+    #[case(r"\alpha + \beta \leq \Gamma", "α + β ≤ Γ")]
+    #[case(r"x_{i+1} = x_i^{2} - \frac{1}{2}", "xᵢ₊₁ = xᵢ² - ½")]
+    #[case(r"\sum_{i=1}^{n} a_i", "∑ᵢ₌₁ⁿ aᵢ")]
+    #[case(r"\frac{a+b}{c}", "(a+b)/c")]
+    #[case(r"\sqrt{x^2 + y^2}", "√(x² + y²)")]
+    #[case(r"\int_0^\infty e^{-x} dx", "∫₀^∞ e⁻ˣ dx")]
+    #[case(r"\hat{y} \in \mathbb{R}", "y\u{302} ∈ ℝ")]
+    #[case(r"\left( \frac{\partial f}{\partial x} \right)", "( (∂f)/(∂x) )")]
+    #[case(r"P(A \mid B)", r"P(A \mid B)")]
+    #[case(r"\sin x + \log_2 n", "sin x + log₂ n")]
+    fn test_to_inline_text(#[case] latex: &str, #[case] expected: &str) {
+        assert_eq!(to_inline_text(latex), expected);
+    }
+
+    #[test]
+    fn test_unknown_macros_stay_verbatim() {
+        let lines = to_unicode(r"\begin{aligned} a &= \xyz{b} \\ c &= d \end{aligned}");
+        assert_eq!(
+            lines,
+            vec![
+                vec![
+                    MathSpan {
+                        text: "a  = ".to_string(),
+                        verbatim: false
+                    },
+                    MathSpan {
+                        text: r"\xyz".to_string(),
+                        verbatim: true
+                    },
+                    MathSpan {
+                        text: "b".to_string(),
+                        verbatim: false
+                    },
+                ],
+                vec![MathSpan {
+                    text: "c  = d".to_string(),
+                    verbatim: false
+                }],
+            ]
+        );
+    }
+}
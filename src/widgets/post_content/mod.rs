@@ -1,30 +1,157 @@
-use crate::{domains::{Post, Theme}, http_gateways::EsaClientHttpGateway};
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+mod code_block;
+mod diagram;
+mod header;
+mod history;
+mod inline_image;
+mod link_hint;
+mod math;
+mod preprocess;
+mod yank;
+
+use crate::{
+    browser,
+    domains::{
+        DisplayConfig, LinkTarget, Post, PostBody, Theme, TimeDisplay, task_items, toggle_task,
+    },
+    http_gateways::EsaClientHttpGateway,
+};
+use chrono::Utc;
+use code_block::{CodeHighlighter, Segment};
+use crossterm::event::{
+    KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use history::{History, HistoryEntry};
+pub use inline_image::InlineImages;
+use link_hint::{LinkCells, LinkHint, VisibleLink};
 use md_tui::{
-    nodes::{root::Component, textcomponent::TextComponent},
+    nodes::{
+        root::Component,
+        textcomponent::{TextComponent, TextNode},
+        word::WordType,
+    },
     parser::parse_markdown,
 };
-use ratatui::{layout::Rect, prelude::Widget, style::Style};
+use preprocess::{IMAGE_ICON, PreprocessContext};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    prelude::Widget,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Paragraph,
+        StatefulWidget,
+    },
+};
+use std::{collections::HashSet, sync::Arc};
+pub use yank::YankItem;
+
+/// Rows scrolled per notch of the mouse wheel.
+const WHEEL_ROWS: u16 = 3;
 
+#[derive(Clone)]
 pub struct Content {
     pub post: Post,
+    /// The body as returned by esa.
+    pub body: PostBody,
+    /// The body after preprocessing, as handed to md-tui.
     pub markdown_content: String,
+    /// `markdown_content` split at its fenced code blocks, which are highlighted once here.
+    pub segments: Vec<Segment>,
 }
 
 pub struct PostContent {
     pub content: Option<Content>,
     pub scroll: u16,
+    /// Horizontal scroll shared by every code block.
+    code_scroll_x: u16,
     view_height: u16,
-    pub api: Box<dyn EsaClientHttpGateway>,
+    visible_links: Vec<VisibleLink>,
+    /// Every link of the post as of the last render, offered by the yank panel.
+    links: Vec<VisibleLink>,
+    /// Where each link was last drawn, for mouse clicks.
+    link_cells: Vec<LinkCells>,
+    /// The area inside the border as of the last render.
+    inner_area: Rect,
+    link_hints: Option<Vec<LinkHint>>,
+    /// Selection in the task list panel, `None` while the panel is closed.
+    task_state: Option<ListState>,
+    yank_items: Vec<YankItem>,
+    /// Selection in the yank panel, `None` while the panel is closed.
+    yank_state: Option<ListState>,
+    /// The item picked in the yank panel, waiting to be copied by the app.
+    yanked: Option<YankItem>,
+    /// Why the last task toggle wasn't saved, waiting to be shown by the app.
+    task_error: Option<String>,
+    history: History,
+    team_name: String,
+    custom_emojis: HashSet<String>,
+    custom_emojis_loaded: bool,
+    expand_details: bool,
+    display: DisplayConfig,
+    time: TimeDisplay,
+    /// Whether the header shows every detail of the post or only its title.
+    header_expanded: bool,
+    images: Option<InlineImages>,
+    highlighter: CodeHighlighter,
+    pub api: Arc<dyn EsaClientHttpGateway>,
     theme: Theme,
 }
 
+/// A block of the post placed at its row.
+struct PlacedComponent {
+    y_offset: u16,
+    block: PlacedBlock,
+}
+
+enum PlacedBlock {
+    /// A text component, with the inline image drawn below it.
+    Text {
+        comp: TextComponent,
+        image: Option<PlacedImage>,
+    },
+    /// The code block at this index of `Content::segments`.
+    Code { segment: usize, height: u16 },
+}
+
+struct PlacedImage {
+    url: String,
+    height: u16,
+}
+
 impl PostContent {
-    pub fn new(api: Box<dyn EsaClientHttpGateway>, theme: Theme) -> Self {
+    pub fn new(
+        api: Arc<dyn EsaClientHttpGateway>,
+        team_name: String,
+        display: DisplayConfig,
+        images: Option<InlineImages>,
+        theme: Theme,
+    ) -> Self {
         Self {
             content: None,
             scroll: 0,
+            code_scroll_x: 0,
             view_height: 0,
+            visible_links: vec![],
+            links: vec![],
+            link_cells: vec![],
+            inner_area: Rect::default(),
+            link_hints: None,
+            task_state: None,
+            yank_items: vec![],
+            yank_state: None,
+            yanked: None,
+            task_error: None,
+            history: History::default(),
+            team_name,
+            custom_emojis: HashSet::new(),
+            custom_emojis_loaded: false,
+            expand_details: false,
+            time: TimeDisplay::new(display.timezone.as_deref(), display.relative_times),
+            header_expanded: display.header_expanded,
+            display,
+            images,
+            highlighter: CodeHighlighter::new(&theme),
             api,
             theme,
         }
@@ -33,61 +160,394 @@ impl PostContent {
 
 impl PostContent {
     pub async fn show_post(&mut self, post: &Post) -> anyhow::Result<()> {
-        let markdown_content = self
+        let body = self
             .api
             .fetch_post_content(&post.post_number)
             .await
             .unwrap_or_else(|e| {
                 eprintln!("failed to fetch post content: {}", e);
-                String::from("# Error\nFailed to load content.")
+                PostBody {
+                    body_md: String::from("# Error\nFailed to load content."),
+                    revision_number: 0,
+                    revised_by: post.updated_by.id.clone(),
+                    stale: false,
+                    conflicted: false,
+                }
             });
+        self.load_custom_emojis().await;
+        let markdown_content = self.preprocess(&body.body_md);
+        if let Some(images) = &mut self.images {
+            images
+                .load(
+                    self.api.as_ref(),
+                    &preprocess::image_urls(&markdown_content),
+                )
+                .await;
+        }
+        if let Some(current) = self.current_entry() {
+            self.history.push(current);
+        }
         let content = Content {
             post: post.clone(),
+            body,
+            segments: self.highlighter.segments(&markdown_content),
             markdown_content,
         };
         self.content = Some(content);
         self.scroll = 0;
+        self.code_scroll_x = 0;
+        self.task_state = None;
+        self.yank_state = None;
         Ok(())
     }
 
-    pub fn handle_key(&mut self, key: KeyEvent) {
+    fn preprocess(&self, body_md: &str) -> String {
+        preprocess::preprocess(
+            body_md,
+            &PreprocessContext {
+                team_name: &self.team_name,
+                custom_emojis: &self.custom_emojis,
+                custom_emoji_format: &self.display.custom_emoji,
+                expand_details: self.expand_details,
+            },
+        )
+    }
+
+    /// Re-runs preprocessing after a display toggle such as folding `<details>`.
+    fn refresh_markdown(&mut self) {
+        if let Some(body_md) = self.content.as_ref().map(|c| c.body.body_md.clone()) {
+            let markdown_content = self.preprocess(&body_md);
+            let segments = self.highlighter.segments(&markdown_content);
+            if let Some(content) = &mut self.content {
+                content.markdown_content = markdown_content;
+                content.segments = segments;
+            }
+        }
+    }
+
+    /// Fetches the team's custom emojis on first use and keeps them for the session.
+    async fn load_custom_emojis(&mut self) {
+        if self.custom_emojis_loaded {
+            return;
+        }
+        match self.api.fetch_custom_emojis().await {
+            Ok(emojis) => self.custom_emojis = emojis.into_iter().collect(),
+            Err(e) => eprintln!("failed to fetch custom emojis: {}", e),
+        }
+        self.custom_emojis_loaded = true;
+    }
+
+    /// The post being shown and how far it is scrolled.
+    pub fn current_post(&self) -> Option<(&Post, u16)> {
+        self.content.as_ref().map(|c| (&c.post, self.scroll))
+    }
+
+    /// While link hints, the task list or the yank panel are shown, every key is consumed by
+    /// the content pane.
+    pub fn is_capturing_keys(&self) -> bool {
+        self.link_hints.is_some() || self.task_state.is_some() || self.yank_state.is_some()
+    }
+
+    pub fn take_yanked(&mut self) -> Option<YankItem> {
+        self.yanked.take()
+    }
+
+    pub fn take_task_error(&mut self) -> Option<String> {
+        self.task_error.take()
+    }
+
+    pub async fn handle_key(&mut self, key: KeyEvent) {
         if key.kind != KeyEventKind::Press {
             return;
         }
-        if let KeyCode::Char(' ') = key.code {
-            if self.view_height == 0 {
-                return;
+        if let Some(hints) = self.link_hints.take() {
+            if let KeyCode::Char(c) = key.code
+                && let Some(hint) = hints.into_iter().find(|hint| hint.key == c)
+            {
+                self.follow_link(&hint.link.url).await;
             }
-            if key.modifiers.contains(KeyModifiers::SHIFT) {
-                self.scroll = self.scroll.saturating_sub(self.view_height);
-            } else {
-                self.scroll = self.scroll.saturating_add(self.view_height);
+            return;
+        }
+        if self.task_state.is_some() {
+            self.handle_task_key(key).await;
+            return;
+        }
+        if self.yank_state.is_some() {
+            self.handle_yank_key(key);
+            return;
+        }
+        match key.code {
+            KeyCode::Char(' ') => {
+                if self.view_height == 0 {
+                    return;
+                }
+                if key.modifiers.contains(KeyModifiers::SHIFT) {
+                    self.scroll = self.scroll.saturating_sub(self.view_height);
+                } else {
+                    self.scroll = self.scroll.saturating_add(self.view_height);
+                }
             }
+            KeyCode::Char('f') => {
+                if !self.visible_links.is_empty() {
+                    self.link_hints = Some(link_hint::assign_hints(&self.visible_links));
+                }
+            }
+            KeyCode::Char('t') => {
+                let has_tasks = self
+                    .content
+                    .as_ref()
+                    .is_some_and(|c| !task_items(&c.body.body_md).is_empty());
+                if has_tasks {
+                    self.task_state = Some(ListState::default().with_selected(Some(0)));
+                }
+            }
+            KeyCode::Char('y') => {
+                if let Some(content) = &self.content {
+                    self.yank_items = yank::yank_items(content, &self.links);
+                    self.yank_state = Some(ListState::default().with_selected(Some(0)));
+                }
+            }
+            KeyCode::Char('<') => self.code_scroll_x = self.code_scroll_x.saturating_sub(8),
+            KeyCode::Char('>') => self.code_scroll_x = self.code_scroll_x.saturating_add(8),
+            KeyCode::Char('m') => self.header_expanded = !self.header_expanded,
+            KeyCode::Char('z') => {
+                self.expand_details = !self.expand_details;
+                self.refresh_markdown();
+            }
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => self.go_back(),
+            KeyCode::Char('i') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.go_forward()
+            }
+            KeyCode::Tab => self.go_forward(),
+            _ => {}
         }
     }
 
-    fn render_paragraph(&self, inner_area: Rect, buf: &mut ratatui::buffer::Buffer, scroll: u16) {
-        let Some(markdown_content) = &self.content.as_ref().map(|c| &c.markdown_content) else {
+    /// Scrolls with the wheel and follows the clicked link.
+    pub async fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if !self.inner_area.contains((mouse.column, mouse.row).into()) {
             return;
+        }
+        match mouse.kind {
+            MouseEventKind::ScrollDown => self.scroll = self.scroll.saturating_add(WHEEL_ROWS),
+            MouseEventKind::ScrollUp => self.scroll = self.scroll.saturating_sub(WHEEL_ROWS),
+            MouseEventKind::Down(MouseButton::Left) if !self.is_capturing_keys() => {
+                let row = self.scroll.saturating_add(mouse.row - self.inner_area.y);
+                let column = mouse.column - self.inner_area.x;
+                if let Some(url) = self
+                    .link_cells
+                    .iter()
+                    .find(|link| link.contains(row, column))
+                    .map(|link| link.url.clone())
+                {
+                    self.follow_link(&url).await;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    async fn handle_task_key(&mut self, key: KeyEvent) {
+        let Some(state) = &mut self.task_state else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => state.select_previous(),
+            KeyCode::Char(' ') | KeyCode::Char('x') | KeyCode::Enter => {
+                if let Some(index) = state.selected() {
+                    self.toggle_task(index).await;
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('t') | KeyCode::Char('q') => self.task_state = None,
+            _ => {}
+        }
+    }
+
+    fn handle_yank_key(&mut self, key: KeyEvent) {
+        let Some(state) = &mut self.yank_state else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => state.select_previous(),
+            KeyCode::Char('y') | KeyCode::Enter => {
+                self.yanked = state
+                    .selected()
+                    .and_then(|index| self.yank_items.get(index).cloned());
+                self.yank_state = None;
+            }
+            KeyCode::Esc | KeyCode::Char('q') => self.yank_state = None,
+            _ => {}
+        }
+    }
+
+    /// Toggles the `index`-th task and saves the body, guarded by the revision it was loaded at.
+    async fn toggle_task(&mut self, index: usize) {
+        let Some(content) = &self.content else {
+            return;
+        };
+        let Some(task) = task_items(&content.body.body_md).into_iter().nth(index) else {
+            return;
+        };
+        let Some(body_md) = toggle_task(&content.body.body_md, task.line) else {
+            return;
+        };
+        let verb = if task.done { "Uncheck" } else { "Check" };
+        let message = format!("{} \"{}\"", verb, task.text);
+        let result = self
+            .api
+            .update_post_body(&content.post.post_number, &content.body, body_md, &message)
+            .await;
+        match result {
+            Ok(body) => {
+                if let Some(content) = &mut self.content {
+                    let tasks = task_items(&body.body_md);
+                    content.post.tasks_count = tasks.len() as u32;
+                    content.post.done_tasks_count = tasks.iter().filter(|t| t.done).count() as u32;
+                    content.body = body;
+                }
+                self.refresh_markdown();
+            }
+            Err(e) => self.task_error = Some(format!("Failed to update the task: {}", e)),
+        }
+    }
+
+    async fn follow_link(&mut self, raw: &str) {
+        match LinkTarget::parse(raw, &self.team_name) {
+            Some(LinkTarget::Post(post_number)) => match self.api.fetch_post(&post_number).await {
+                Some(post) => {
+                    if let Err(e) = self.show_post(&post).await {
+                        eprintln!("failed to show post: {}", e);
+                    }
+                }
+                None => eprintln!("failed to fetch post: {}", post_number),
+            },
+            Some(LinkTarget::External(url)) => {
+                if let Err(e) = browser::open_url(url.as_str()) {
+                    eprintln!("failed to open browser: {}", e);
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn current_entry(&self) -> Option<HistoryEntry> {
+        self.content.clone().map(|content| HistoryEntry {
+            content,
+            scroll: self.scroll,
+        })
+    }
+
+    fn restore(&mut self, entry: HistoryEntry) {
+        self.content = Some(entry.content);
+        self.scroll = entry.scroll;
+        self.code_scroll_x = 0;
+        self.refresh_markdown();
+    }
+
+    fn go_back(&mut self) {
+        if let Some(entry) = self.history.back(self.current_entry()) {
+            self.restore(entry);
+        }
+    }
+
+    fn go_forward(&mut self) {
+        if let Some(entry) = self.history.forward(self.current_entry()) {
+            self.restore(entry);
+        }
+    }
+
+    /// Stacks the text components and code blocks, reserving rows below image placeholders
+    /// for the image.
+    fn layout(&mut self, width: u16) -> Vec<PlacedComponent> {
+        let mut placed = vec![];
+        let mut y_offset: u16 = 0;
+        let Some(content) = &self.content else {
+            return placed;
         };
+        for (index, segment) in content.segments.iter().enumerate() {
+            let markdown = match segment {
+                Segment::Markdown(markdown) => markdown,
+                Segment::Code(code) => {
+                    let height = code.height();
+                    placed.push(PlacedComponent {
+                        y_offset,
+                        block: PlacedBlock::Code {
+                            segment: index,
+                            height,
+                        },
+                    });
+                    y_offset = y_offset.saturating_add(height);
+                    continue;
+                }
+            };
+            let root = parse_markdown(None, markdown, width);
+            for component in root.children() {
+                let Component::TextComponent(comp) = component else {
+                    continue;
+                };
+                let image = image_url(comp).and_then(|url| {
+                    let protocol = self.images.as_mut()?.protocol(url, width)?;
+                    Some(PlacedImage {
+                        url: url.to_string(),
+                        height: protocol.area().height,
+                    })
+                });
+                let block = PlacedBlock::Text {
+                    comp: comp.clone(),
+                    image,
+                };
+                let height = block.height();
+                placed.push(PlacedComponent { y_offset, block });
+                y_offset = y_offset.saturating_add(height);
+            }
+        }
+        placed
+    }
+
+    fn render_paragraph(
+        &mut self,
+        placed: Vec<PlacedComponent>,
+        inner_area: Rect,
+        buf: &mut Buffer,
+        scroll: u16,
+    ) {
         let local_area = Rect::new(0, 0, inner_area.width, inner_area.height);
-        let mut inner_buf = ratatui::buffer::Buffer::empty(local_area);
-        let component_root = parse_markdown(None, markdown_content, local_area.width);
-        let mut text_components: Vec<TextComponent> = component_root
-            .children()
-            .into_iter()
-            .filter_map(|c| match c {
-                Component::TextComponent(t) => Some(t.clone()),
-                _ => None,
-            })
-            .collect();
-        let mut y_offset = 0;
-        for mut comp in text_components.drain(..) {
-            let height = comp.height();
-            comp.set_y_offset(y_offset);
-            comp.set_scroll_offset(scroll);
-            comp.render(local_area, &mut inner_buf);
-            y_offset = y_offset.saturating_add(height);
+        let mut inner_buf = Buffer::empty(local_area);
+        for PlacedComponent { y_offset, block } in placed {
+            match block {
+                PlacedBlock::Text { mut comp, image } => {
+                    let height = comp.height();
+                    comp.set_y_offset(y_offset);
+                    comp.set_scroll_offset(scroll);
+                    comp.render(local_area, &mut inner_buf);
+                    if let Some(image) = image {
+                        self.render_inline_image(
+                            &image.url,
+                            y_offset.saturating_add(height),
+                            scroll,
+                            &mut inner_buf,
+                        );
+                    }
+                }
+                PlacedBlock::Code { segment, height } => {
+                    let visible = y_offset < scroll.saturating_add(local_area.height)
+                        && y_offset.saturating_add(height) > scroll;
+                    if let Some(Segment::Code(code)) =
+                        self.content.as_ref().and_then(|c| c.segments.get(segment))
+                        && visible
+                    {
+                        let code_buf = code.render(
+                            local_area.width,
+                            self.code_scroll_x,
+                            self.display.code_line_numbers,
+                            &self.theme,
+                        );
+                        blit(&code_buf, y_offset, scroll, &mut inner_buf);
+                    }
+                }
+            }
         }
         for y in 0..inner_area.height {
             for x in 0..inner_area.width {
@@ -99,35 +559,292 @@ impl PostContent {
             }
         }
     }
+
+    /// Draws the image whose first row is `top`, skipping images cut by the viewport
+    /// unless the protocol can draw part of them.
+    fn render_inline_image(&mut self, url: &str, top: u16, scroll: u16, buf: &mut Buffer) {
+        let Some(images) = &mut self.images else {
+            return;
+        };
+        let can_clip = images.can_clip();
+        let Some(protocol) = images.protocol(url, buf.area.width) else {
+            return;
+        };
+        let image_buf = inline_image::render_image(protocol);
+        let height = image_buf.area.height;
+        let fully_visible =
+            top >= scroll && top.saturating_add(height) <= scroll.saturating_add(buf.area.height);
+        if !fully_visible && !can_clip {
+            return;
+        }
+        blit(&image_buf, top, scroll, buf);
+    }
+
+    /// The widest code block can scroll until its longest line ends at the right border.
+    fn max_code_scroll_x(&self, width: u16) -> u16 {
+        let Some(content) = &self.content else {
+            return 0;
+        };
+        content
+            .segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Code(code) => Some(code.content_width(self.display.code_line_numbers)),
+                Segment::Markdown(_) => None,
+            })
+            .max()
+            .map_or(0, |content_width| {
+                content_width.saturating_sub(width.saturating_sub(2))
+            })
+    }
+
+    fn render_tasks(&mut self, inner_area: Rect, buf: &mut Buffer) {
+        let (Some(state), Some(content)) = (&mut self.task_state, &self.content) else {
+            return;
+        };
+        let tasks = task_items(&content.body.body_md);
+        let items: Vec<ListItem> = tasks
+            .iter()
+            .map(|task| {
+                let (checkbox, style) = if task.done {
+                    ("[x] ", Style::new().fg(self.theme.success))
+                } else {
+                    ("[ ] ", Style::new().fg(self.theme.muted))
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(checkbox, style),
+                    Span::styled(task.text.clone(), Style::new().fg(self.theme.primary)),
+                ]))
+            })
+            .collect();
+        let done = tasks.iter().filter(|t| t.done).count();
+        let height = (items.len() as u16)
+            .saturating_add(2)
+            .min(inner_area.height / 2)
+            .max(3.min(inner_area.height));
+        let area = Rect::new(
+            inner_area.x,
+            inner_area.bottom().saturating_sub(height),
+            inner_area.width,
+            height,
+        );
+        let block = Block::default()
+            .title(format!("Tasks {}/{}", done, tasks.len()))
+            .borders(Borders::ALL)
+            .border_style(Style::new().fg(self.theme.muted))
+            .title_style(Style::new().fg(self.theme.primary));
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(
+                Style::new()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+        Clear.render(area, buf);
+        StatefulWidget::render(list, area, buf, state);
+    }
+
+    fn render_yank(&mut self, inner_area: Rect, buf: &mut Buffer) {
+        let Some(state) = &mut self.yank_state else {
+            return;
+        };
+        let items: Vec<ListItem> = self
+            .yank_items
+            .iter()
+            .map(|item| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:<10}", item.kind),
+                        Style::new().fg(self.theme.accent),
+                    ),
+                    Span::styled(item.label.clone(), Style::new().fg(self.theme.primary)),
+                ]))
+            })
+            .collect();
+        let height = (items.len() as u16)
+            .saturating_add(2)
+            .min(inner_area.height / 2)
+            .max(3.min(inner_area.height));
+        let area = Rect::new(
+            inner_area.x,
+            inner_area.bottom().saturating_sub(height),
+            inner_area.width,
+            height,
+        );
+        let block = Block::default()
+            .title("Yank")
+            .borders(Borders::ALL)
+            .border_style(Style::new().fg(self.theme.muted))
+            .title_style(Style::new().fg(self.theme.primary));
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(
+                Style::new()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+        Clear.render(area, buf);
+        StatefulWidget::render(list, area, buf, state);
+    }
+
+    fn render_link_hints(&self, inner_area: Rect, buf: &mut Buffer) {
+        let Some(hints) = &self.link_hints else {
+            return;
+        };
+        let lines: Vec<Line> = hints
+            .iter()
+            .map(|hint| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("[{}] ", hint.key),
+                        Style::new()
+                            .fg(self.theme.accent)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        format!("{} ", hint.link.text),
+                        Style::new().fg(self.theme.primary),
+                    ),
+                    Span::styled(hint.link.url.clone(), Style::new().fg(self.theme.link)),
+                ])
+            })
+            .collect();
+        let height = (lines.len() as u16)
+            .saturating_add(2)
+            .min(inner_area.height);
+        let area = Rect::new(
+            inner_area.x,
+            inner_area.bottom().saturating_sub(height),
+            inner_area.width,
+            height,
+        );
+        let block = Block::default()
+            .title("Links")
+            .borders(Borders::ALL)
+            .border_style(Style::new().fg(self.theme.muted))
+            .title_style(Style::new().fg(self.theme.primary));
+        Clear.render(area, buf);
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
 }
 
 impl Widget for &mut PostContent {
-    fn render(self, area: Rect, buf: &mut ratatui::buffer::Buffer) {
+    fn render(self, area: Rect, buf: &mut Buffer) {
         let block = ratatui::widgets::Block::default()
             .title("Post Content")
             .borders(ratatui::widgets::Borders::ALL)
             .border_style(Style::new().fg(self.theme.muted))
             .title_style(Style::new().fg(self.theme.primary));
-        let inner_area = block.inner(area);
+        let mut inner_area = block.inner(area);
         block.render(area, buf);
+        if let Some(content) = &self.content {
+            let lines = header::header_lines(
+                &content.post,
+                &content.body,
+                &self.time,
+                Utc::now(),
+                &self.theme,
+                self.header_expanded,
+            );
+            // one more row for the rule under the header
+            let height = (lines.len() as u16 + 1).min(inner_area.height);
+            let [header_area, body_area] =
+                Layout::vertical([Constraint::Length(height), Constraint::Fill(1)])
+                    .areas(inner_area);
+            Paragraph::new(lines)
+                .block(
+                    Block::default()
+                        .borders(Borders::BOTTOM)
+                        .border_style(Style::new().fg(self.theme.muted)),
+                )
+                .render(header_area, buf);
+            inner_area = body_area;
+        }
 
         self.view_height = inner_area.height;
+        self.inner_area = inner_area;
+        if self.content.is_none() {
+            return;
+        }
+        let placed = self.layout(inner_area.width);
         let max_scroll = {
-            let Some(markdown_content) = &self.content.as_ref().map(|c| &c.markdown_content) else {
-                return;
-            };
-            let component_root = parse_markdown(None, markdown_content, inner_area.width);
-            let total_height: u16 = component_root
-                .children()
-                .into_iter()
-                .filter_map(|c| match c {
-                    Component::TextComponent(t) => Some(t.height()),
-                    _ => None,
-                })
-                .sum();
+            let total_height = placed
+                .last()
+                .map_or(0, |last| last.y_offset.saturating_add(last.block.height()));
             total_height.saturating_sub(inner_area.height)
         };
         self.scroll = self.scroll.min(max_scroll);
-        self.render_paragraph(inner_area, buf, self.scroll);
+        self.code_scroll_x = self
+            .code_scroll_x
+            .min(self.max_code_scroll_x(inner_area.width));
+        let text_components = || {
+            placed.iter().filter_map(|p| match &p.block {
+                PlacedBlock::Text { comp, .. } => Some((p.y_offset, comp)),
+                PlacedBlock::Code { .. } => None,
+            })
+        };
+        self.visible_links =
+            link_hint::visible_links(text_components(), self.scroll, inner_area.height);
+        self.links = link_hint::visible_links(text_components(), 0, u16::MAX);
+        self.link_cells = link_hint::link_cells(text_components());
+        self.render_paragraph(placed, inner_area, buf, self.scroll);
+        self.render_tasks(inner_area, buf);
+        self.render_yank(inner_area, buf);
+        self.render_link_hints(inner_area, buf);
+    }
+}
+
+impl PlacedBlock {
+    fn height(&self) -> u16 {
+        match self {
+            PlacedBlock::Text { comp, image } => comp
+                .height()
+                .saturating_add(image.as_ref().map_or(0, |image| image.height)),
+            PlacedBlock::Code { height, .. } => *height,
+        }
+    }
+}
+
+/// Copies the rows of `src` into `dst` as if `src` started at row `top` of a view scrolled
+/// down by `scroll` rows.
+fn blit(src: &Buffer, top: u16, scroll: u16, dst: &mut Buffer) {
+    for row in 0..src.area.height {
+        let Some(y) = top.saturating_add(row).checked_sub(scroll) else {
+            continue;
+        };
+        for x in 0..src.area.width {
+            if let Some(cell) = src.cell((x, row))
+                && let Some(dst) = dst.cell_mut((x, y))
+            {
+                *dst = cell.clone();
+            }
+        }
+    }
+}
+
+/// The URL of the image when the component is nothing but an image placeholder.
+fn image_url(comp: &TextComponent) -> Option<&str> {
+    if comp.kind() != TextNode::Paragraph {
+        return None;
+    }
+    let words: Vec<_> = comp
+        .content()
+        .iter()
+        .flatten()
+        .filter(|w| !w.content().trim().is_empty())
+        .collect();
+    let starts_with_icon = words
+        .first()
+        .is_some_and(|w| w.content().trim_start().starts_with(IMAGE_ICON));
+    if !starts_with_icon || !words.iter().all(|w| w.kind() == WordType::Link) {
+        return None;
+    }
+    match comp.meta_info().as_slice() {
+        [link] if link.kind() == WordType::LinkData => Some(link.content()),
+        _ => None,
     }
 }
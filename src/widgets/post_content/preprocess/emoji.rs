@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+
+use super::map_prose_lines;
+
+/// Replaces `:shortcode:` with the Unicode emoji, or with `custom_format` for the team's custom emojis.
+///
+/// `{name}` in `custom_format` is replaced with the shortcode. Unknown shortcodes are kept as is.
+pub fn replace_emoji_shortcodes(
+    markdown: &str,
+    custom_emojis: &HashSet<String>,
+    custom_format: &str,
+) -> String {
+    map_prose_lines(markdown, |line, out| {
+        replace_line(line, custom_emojis, custom_format, out)
+    })
+}
+
+fn replace_line(
+    line: &str,
+    custom_emojis: &HashSet<String>,
+    custom_format: &str,
+    out: &mut String,
+) {
+    let mut rest = line;
+    let mut in_code = false;
+    while let Some(pos) = rest.find([':', '`']) {
+        let (before, from) = rest.split_at(pos);
+        out.push_str(before);
+        if let Some(after) = from.strip_prefix('`') {
+            in_code = !in_code;
+            out.push('`');
+            rest = after;
+            continue;
+        }
+        let name_len = from[1..]
+            .find(|c: char| !is_shortcode_char(c))
+            .unwrap_or(from.len() - 1);
+        let name = &from[1..1 + name_len];
+        let closed = from[1 + name_len..].starts_with(':');
+        let replacement = if in_code || !closed || name.is_empty() {
+            None
+        } else if let Some(emoji) = emojis::get_by_shortcode(name) {
+            Some(emoji.as_str().to_string())
+        } else if custom_emojis.contains(name) {
+            Some(custom_format.replace("{name}", name))
+        } else {
+            None
+        };
+        match replacement {
+            Some(replacement) => {
+                out.push_str(&replacement);
+                rest = &from[name_len + 2..];
+            }
+            None => {
+                // the closing colon may open the next shortcode
+                out.push(':');
+                out.push_str(name);
+                rest = &from[1 + name_len..];
+            }
+        }
+    }
+    out.push_str(rest);
+}
+
+fn is_shortcode_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+
+    fn custom_emojis() -> HashSet<String> {
+        HashSet::from([
+            "git-pull-request".to_string(),
+            "git-pull-request-draft".to_string(),
+        ])
+    }
+
+    #[test]
+    fn test_replace_emoji_shortcodes() {
+        let markdown = include_str!("../../../domains/fixtures/sample_markdown.md");
+        assert_snapshot!(replace_emoji_shortcodes(
+            markdown,
+            &custom_emojis(),
+            "[{name}]"
+        ));
+    }
+
+    #[test]
+    fn test_replace_emoji_shortcodes_with_nerd_font_fallback() {
+        let markdown =
+            ":tada: done :git-pull-request: at 10:30:45, `:smile:` :unknown:\n```\n:smile:\n```\n";
+        assert_snapshot!(replace_emoji_shortcodes(
+            markdown,
+            &custom_emojis(),
+            "\u{f407} {name}"
+        ));
+    }
+}
@@ -0,0 +1,304 @@
+use super::images::image_placeholder;
+
+/// Rewrites the raw HTML esa keeps in post bodies into markdown md-tui can show.
+///
+/// `<img>` becomes a link placeholder with the alt text and size, `<details>` a section
+/// that is folded unless `expand_details` is set (or it has the `open` attribute), `<br>`
+/// a line break, and common formatting tags their markdown equivalent. Other tags are
+/// left untouched.
+pub fn simplify_html(markdown: &str, expand_details: bool) -> String {
+    let mut state = HtmlState {
+        expand_details,
+        ..HtmlState::default()
+    };
+    let mut out = String::with_capacity(markdown.len());
+    let mut in_fence = false;
+    for line in markdown.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            state.emit(line, &mut out);
+        } else if in_fence {
+            state.emit(line, &mut out);
+        } else {
+            state.line(line, &mut out);
+        }
+    }
+    out
+}
+
+struct Details {
+    expanded: bool,
+}
+
+#[derive(Default)]
+struct HtmlState {
+    expand_details: bool,
+    details: Vec<Details>,
+    summary: Option<String>,
+    links: Vec<Option<String>>,
+    in_comment: bool,
+}
+
+struct Tag {
+    name: String,
+    closing: bool,
+    attrs: Vec<(String, String)>,
+}
+
+impl Tag {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl HtmlState {
+    fn visible(&self) -> bool {
+        self.details.iter().all(|d| d.expanded)
+    }
+
+    fn emit(&mut self, s: &str, out: &mut String) {
+        if let Some(summary) = &mut self.summary {
+            summary.push_str(s);
+        } else if self.visible() {
+            out.push_str(s);
+        }
+    }
+
+    fn line(&mut self, line: &str, out: &mut String) {
+        let in_table = line.trim_start().starts_with('|');
+        let mut rest = line;
+        let mut in_code = false;
+        while !rest.is_empty() {
+            if self.in_comment {
+                match rest.find("-->") {
+                    Some(end) => {
+                        self.in_comment = false;
+                        rest = &rest[end + 3..];
+                    }
+                    None => return,
+                }
+                continue;
+            }
+            let Some(pos) = rest.find(['<', '`', '&']) else {
+                self.emit(rest, out);
+                return;
+            };
+            let (before, from) = rest.split_at(pos);
+            self.emit(before, out);
+            if let Some(after) = from.strip_prefix('`') {
+                in_code = !in_code;
+                self.emit("`", out);
+                rest = after;
+            } else if in_code {
+                self.emit(&from[..1], out);
+                rest = &from[1..];
+            } else if let Some(after) = from.strip_prefix("<!--") {
+                self.in_comment = true;
+                rest = after;
+            } else if from.starts_with('&') {
+                let (decoded, len) = decode_entity(from);
+                self.emit(decoded, out);
+                rest = &from[len..];
+            } else if let Some((tag, len)) = parse_tag(from)
+                && self.handle_tag(&tag, in_table, out)
+            {
+                rest = &from[len..];
+            } else {
+                self.emit("<", out);
+                rest = &from[1..];
+            }
+        }
+    }
+
+    /// Writes the replacement of a known tag, returning `false` to keep the tag verbatim.
+    fn handle_tag(&mut self, tag: &Tag, in_table: bool, out: &mut String) -> bool {
+        let replacement = match (tag.name.as_str(), tag.closing) {
+            ("img", false) => image_tag_placeholder(tag),
+            ("br", false) if in_table => " ".to_string(),
+            ("br", false) => "\n".to_string(),
+            ("hr", false) => "\n---\n".to_string(),
+            ("b" | "strong", _) => "**".to_string(),
+            ("i" | "em", _) => "*".to_string(),
+            ("s" | "del" | "strike", _) => "~~".to_string(),
+            ("code" | "kbd", _) => "`".to_string(),
+            ("a", false) => {
+                let href = tag.attr("href").map(str::to_string);
+                let open = if href.is_some() { "[" } else { "" };
+                self.links.push(href);
+                open.to_string()
+            }
+            ("a", true) => match self.links.pop().flatten() {
+                Some(href) => format!("]({})", href),
+                None => String::new(),
+            },
+            ("details", false) => {
+                let expanded = self.expand_details || tag.attr("open").is_some();
+                self.details.push(Details { expanded });
+                String::new()
+            }
+            ("details", true) => {
+                self.details.pop();
+                self.summary = None;
+                String::new()
+            }
+            ("summary", false) => {
+                let parent_visible = self.details.iter().rev().skip(1).all(|d| d.expanded);
+                if parent_visible {
+                    self.summary = Some(String::new());
+                }
+                String::new()
+            }
+            ("summary", true) => {
+                // the summary stays visible even when its section is folded
+                if let Some(summary) = self.summary.take() {
+                    let expanded = self.details.last().is_none_or(|d| d.expanded);
+                    let marker = if expanded { "▼" } else { "▶" };
+                    let summary = summary.replace("**", "");
+                    let summary = summary.trim();
+                    let summary = if summary.is_empty() {
+                        "Details"
+                    } else {
+                        summary
+                    };
+                    if !out.is_empty() && !out.ends_with('\n') {
+                        out.push('\n');
+                    }
+                    out.push_str(&format!("**{} {}**\n", marker, summary));
+                }
+                String::new()
+            }
+            ("p" | "div", true) => "\n".to_string(),
+            (
+                "p" | "div" | "span" | "font" | "center" | "small" | "big" | "u" | "ins" | "mark"
+                | "abbr" | "sub" | "sup" | "section" | "article",
+                _,
+            ) => String::new(),
+            _ => return false,
+        };
+        self.emit(&replacement, out);
+        true
+    }
+}
+
+fn image_tag_placeholder(tag: &Tag) -> String {
+    let size = match (tag.attr("width"), tag.attr("height")) {
+        (Some(width), Some(height)) => format!(" {}x{}", width, height),
+        (Some(width), None) => format!(" {}px", width),
+        (None, Some(height)) => format!(" h{}px", height),
+        (None, None) => String::new(),
+    };
+    image_placeholder(tag.attr("alt").unwrap_or(""), &size, tag.attr("src"))
+}
+
+fn decode_entity(s: &str) -> (&'static str, usize) {
+    const ENTITIES: [(&str, &str); 6] = [
+        ("&nbsp;", " "),
+        ("&amp;", "&"),
+        ("&lt;", "<"),
+        ("&gt;", ">"),
+        ("&quot;", "\""),
+        ("&#39;", "'"),
+    ];
+    ENTITIES
+        .iter()
+        .find(|(entity, _)| s.starts_with(entity))
+        .map(|(entity, decoded)| (*decoded, entity.len()))
+        .unwrap_or(("&", 1))
+}
+
+/// Parses `<name attr="value">` or `</name>` at the start of `s`, returning the tag and its length.
+fn parse_tag(s: &str) -> Option<(Tag, usize)> {
+    let body_start = if s.starts_with("</") { 2 } else { 1 };
+    let end = s.find('>')?;
+    let body = s[body_start..end].trim_end_matches('/');
+    let name_len = body
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(body.len());
+    let name = &body[..name_len];
+    if name.is_empty() || !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let attrs_str = &body[name_len..];
+    if !attrs_str.is_empty() && !attrs_str.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some((
+        Tag {
+            name: name.to_ascii_lowercase(),
+            closing: body_start == 2,
+            attrs: parse_attrs(attrs_str),
+        },
+        end + 1,
+    ))
+}
+
+fn parse_attrs(s: &str) -> Vec<(String, String)> {
+    let mut attrs = vec![];
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        let key_len = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = rest[..key_len].to_ascii_lowercase();
+        rest = rest[key_len..].trim_start();
+        let value = if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (value, remaining) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let close = after[1..].find(quote).map_or(after.len(), |i| i + 1);
+                    (&after[1..close], after.get(close + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let len = after.find(char::is_whitespace).unwrap_or(after.len());
+                    (&after[..len], &after[len..])
+                }
+            };
+            rest = remaining.trim_start();
+            value.to_string()
+        } else {
+            String::new()
+        };
+        if !key.is_empty() {
+            attrs.push((key, value));
+        }
+    }
+    attrs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+    use rstest::rstest;
+
+    const DETAILS: &str = r#"<details><summary>手順 <b>詳細</b></summary>
+
+1. <a href="https://example.com">example</a> を開く<br>ログインする
+2. `<br>` はそのまま
+
+</details>
+<details open>
+<summary>開いている</summary>
+本文&nbsp;&amp;&lt;tag&gt;
+</details>
+| a | b<br/>c |
+<!-- comment -->
+<kbd>Ctrl</kbd> + <kbd>C</kbd> <unknown>
+"#;
+
+    #[test]
+    fn test_simplify_html_sample_markdown() {
+        let markdown = include_str!("../../../domains/fixtures/sample_markdown.md");
+        assert_snapshot!(simplify_html(markdown, false));
+    }
+
+    #[rstest]
+    #[case("details_folded", false)]
+    #[case("details_expanded", true)]
+    fn test_simplify_html_details(#[case] name: &str, #[case] expand_details: bool) {
+        assert_snapshot!(name, simplify_html(DETAILS, expand_details));
+    }
+}
@@ -0,0 +1,97 @@
+use super::map_prose_lines;
+
+/// Prefix of the link text shown in place of an image.
+pub const IMAGE_ICON: &str = "\u{f03e}";
+
+/// Formats the link that stands in for an image, e.g. `[ diagram.png 600px](https://…)`.
+pub fn image_placeholder(alt: &str, size: &str, src: Option<&str>) -> String {
+    let alt = if alt.is_empty() { "image" } else { alt };
+    let alt = alt.replace(['[', ']'], "");
+    match src {
+        Some(src) => format!("[{} {}{}]({})", IMAGE_ICON, alt, size, src),
+        None => format!("{} {}{}", IMAGE_ICON, alt, size),
+    }
+}
+
+/// Replaces `![alt](src)` with an image placeholder, giving placeholders that fill a whole line
+/// a paragraph of their own so the image can be drawn below them.
+pub fn link_images(markdown: &str) -> String {
+    map_prose_lines(markdown, |line, out| {
+        let replaced = replace_images(line);
+        let body = replaced.trim_end();
+        if body.starts_with(&format!("[{} ", IMAGE_ICON)) && placeholder_url(body).is_some() {
+            out.push('\n');
+            out.push_str(body);
+            out.push_str("\n\n");
+        } else {
+            out.push_str(&replaced);
+        }
+    })
+}
+
+/// URLs of the images in preprocessed markdown, in order of appearance.
+pub fn image_urls(markdown: &str) -> Vec<String> {
+    markdown
+        .lines()
+        .filter_map(|line| placeholder_url(line.trim_end()))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Returns the URL when `line` is exactly one image placeholder.
+fn placeholder_url(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix(&format!("[{} ", IMAGE_ICON))?;
+    let (_, target) = rest.split_once("](")?;
+    let url = target.strip_suffix(')')?;
+    (!url.is_empty() && !url.contains(char::is_whitespace)).then_some(url)
+}
+
+fn replace_images(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    let mut in_code = false;
+    while let Some(pos) = rest.find(['!', '`']) {
+        let (before, from) = rest.split_at(pos);
+        out.push_str(before);
+        if let Some(after) = from.strip_prefix('`') {
+            in_code = !in_code;
+            out.push('`');
+            rest = after;
+        } else if !in_code && let Some((placeholder, len)) = parse_image(from) {
+            out.push_str(&placeholder);
+            rest = &from[len..];
+        } else {
+            out.push('!');
+            rest = &from[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Parses `![alt](src "title")` at the start of `s`, returning the placeholder and its length.
+fn parse_image(s: &str) -> Option<(String, usize)> {
+    let after_open = s.strip_prefix("![")?;
+    let alt_end = after_open.find("](")?;
+    let alt = &after_open[..alt_end];
+    let target_start = 2 + alt_end + 2;
+    let target_end = target_start + s[target_start..].find(')')?;
+    let src = s[target_start..target_end].split_whitespace().next()?;
+    Some((image_placeholder(alt, "", Some(src)), target_end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_images() {
+        let markdown = "before\n![shot.png](https://files.esa.io/a.png)\ninline ![x](b.png \"title\") and `![code](c.png)`\n";
+        let expected = format!(
+            "before\n\n[{icon} shot.png](https://files.esa.io/a.png)\n\ninline [{icon} x](b.png) and `![code](c.png)`\n",
+            icon = IMAGE_ICON
+        );
+        assert_eq!(link_images(markdown), expected);
+        assert_eq!(image_urls(&expected), vec!["https://files.esa.io/a.png"]);
+    }
+}
@@ -0,0 +1,102 @@
+use super::{super::math::to_inline_text, map_prose_lines};
+
+/// Turns esa's `$$ ... $$` display math into ```` ```math ```` fences, and `$$ ... $$` within a
+/// line of text into inline code holding its Unicode conversion.
+pub fn fence_math(markdown: &str) -> String {
+    let mut block: Option<String> = None;
+    let mut out = map_prose_lines(markdown, |line, out| {
+        let trimmed = line.trim();
+        if let Some(math) = &mut block {
+            match trimmed.strip_suffix("$$") {
+                Some(last) => {
+                    math.push_str(last);
+                    push_fence(math, out);
+                    block = None;
+                }
+                None => math.push_str(line),
+            }
+        } else if let Some(rest) = trimmed.strip_prefix("$$")
+            && !rest.trim_end_matches("$$").contains("$$")
+        {
+            match rest.strip_suffix("$$") {
+                Some(math) => push_fence(math, out),
+                None => block = Some(format!("{}\n", rest)),
+            }
+        } else {
+            inline_math(line, out);
+        }
+    });
+    // an unclosed block runs to the end of the post
+    if let Some(math) = block {
+        push_fence(&math, &mut out);
+    }
+    out
+}
+
+fn push_fence(math: &str, out: &mut String) {
+    out.push_str("```math\n");
+    for line in math.lines().filter(|line| !line.trim().is_empty()) {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str("```\n");
+}
+
+fn inline_math(line: &str, out: &mut String) {
+    let mut rest = line;
+    let mut in_code = false;
+    while let Some(pos) = rest.find(['$', '`']) {
+        let (before, from) = rest.split_at(pos);
+        out.push_str(before);
+        if let Some(after) = from.strip_prefix('`') {
+            in_code = !in_code;
+            out.push('`');
+            rest = after;
+            continue;
+        }
+        let closing = from
+            .strip_prefix("$$")
+            .filter(|_| !in_code)
+            .and_then(|after| after.find("$$").map(|end| &after[..end]));
+        match closing {
+            Some(math) if !math.trim().is_empty() => {
+                out.push_str(&format!("`{}`", to_inline_text(math)));
+                rest = &from[math.len() + 4..];
+            }
+            _ => {
+                out.push('$');
+                rest = &from[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+
+    #[test]
+    fn test_fence_math() {
+        let markdown = "\
+Energy is $$E = mc^2$$, and `$$kept$$` in code.
+
+$$
+\\sum_{i=1}^{n} i = \\frac{n(n+1)}{2}
+$$
+
+$$\\alpha \\leq \\beta$$
+
+```
+$$ untouched $$
+```
+
+$$\\begin{aligned}
+a &= b \\\\
+c &= d
+\\end{aligned}$$
+";
+        assert_snapshot!(fence_math(markdown));
+    }
+}
@@ -0,0 +1,43 @@
+mod emoji;
+mod html;
+mod images;
+mod math;
+mod post_references;
+
+use std::collections::HashSet;
+
+pub use images::{IMAGE_ICON, image_urls};
+
+/// Context shared by the markdown passes run before handing a post body to md-tui.
+pub struct PreprocessContext<'a> {
+    pub team_name: &'a str,
+    pub custom_emojis: &'a HashSet<String>,
+    pub custom_emoji_format: &'a str,
+    pub expand_details: bool,
+}
+
+pub fn preprocess(markdown: &str, ctx: &PreprocessContext) -> String {
+    let markdown = math::fence_math(markdown);
+    let markdown = html::simplify_html(&markdown, ctx.expand_details);
+    let markdown = images::link_images(&markdown);
+    let markdown =
+        emoji::replace_emoji_shortcodes(&markdown, ctx.custom_emojis, ctx.custom_emoji_format);
+    post_references::link_post_references(&markdown, ctx.team_name)
+}
+
+/// Calls `f` on every line outside fenced code blocks, copying fenced lines verbatim.
+fn map_prose_lines(markdown: &str, mut f: impl FnMut(&str, &mut String)) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut in_fence = false;
+    for line in markdown.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            out.push_str(line);
+        } else if in_fence {
+            out.push_str(line);
+        } else {
+            f(line, &mut out);
+        }
+    }
+    out
+}
@@ -0,0 +1,73 @@
+use crate::domains::{PostNumber, post_url};
+
+use super::map_prose_lines;
+
+/// Turns bare `#123` references into links to the post of the same team, like esa does.
+pub fn link_post_references(markdown: &str, team_name: &str) -> String {
+    map_prose_lines(markdown, |line, out| link_line(line, team_name, out))
+}
+
+fn link_line(line: &str, team_name: &str, out: &mut String) {
+    let chars: Vec<char> = line.chars().collect();
+    let mut in_code = false;
+    let mut in_link_target = false;
+    let mut bracket_depth = 0usize;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        match c {
+            '`' => in_code = !in_code,
+            '[' if !in_code => bracket_depth += 1,
+            ']' if !in_code => bracket_depth = bracket_depth.saturating_sub(1),
+            '(' if !in_code && prev == Some(']') => in_link_target = true,
+            ')' if in_link_target => in_link_target = false,
+            '#' if !in_code
+                && !in_link_target
+                && bracket_depth == 0
+                && prev.is_none_or(|p| p.is_whitespace() || p == '(') =>
+            {
+                let digits: String = chars[i + 1..]
+                    .iter()
+                    .take_while(|d| d.is_ascii_digit())
+                    .collect();
+                let end = i + 1 + digits.len();
+                let terminated = chars
+                    .get(end)
+                    .is_none_or(|n| !n.is_alphanumeric() && *n != '_');
+                if let Ok(number) = digits.parse::<i32>()
+                    && terminated
+                {
+                    let url = post_url(team_name, &PostNumber::from(number));
+                    out.push_str(&format!("[#{}]({})", digits, url));
+                    i = end;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        out.push(c);
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+
+    #[test]
+    fn test_link_post_references() {
+        let markdown = include_str!("../../../domains/fixtures/sample_markdown.md");
+        assert_snapshot!(link_post_references(markdown, "my_team"));
+    }
+
+    #[test]
+    fn test_link_post_references_skips_code() {
+        let markdown = "see #12 and `#34`\n```\n#56\n```\n[#78](https://example.com) (#90) #1a\n";
+        assert_eq!(
+            link_post_references(markdown, "my_team"),
+            "see [#12](https://my_team.esa.io/posts/12) and `#34`\n```\n#56\n```\n[#78](https://example.com) ([#90](https://my_team.esa.io/posts/90)) #1a\n"
+        );
+    }
+}
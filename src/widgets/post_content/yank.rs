@@ -0,0 +1,54 @@
+use super::{Content, code_block::Segment, link_hint::VisibleLink};
+use crate::domains::post_reference;
+
+/// Something in the post that can be copied to the clipboard.
+#[derive(Clone, Debug)]
+pub struct YankItem {
+    /// What is copied, shown in the yank panel and the status bar.
+    pub kind: &'static str,
+    /// Preview shown next to the kind.
+    pub label: String,
+    pub text: String,
+}
+
+/// Lists the post URL, its reference and markdown, then every code block and link.
+pub fn yank_items(content: &Content, links: &[VisibleLink]) -> Vec<YankItem> {
+    let post = &content.post;
+    let url = post.url.to_string();
+    let mut items = vec![
+        YankItem {
+            kind: "URL",
+            label: url.clone(),
+            text: url.clone(),
+        },
+        YankItem {
+            kind: "Reference",
+            label: post.full_name.clone(),
+            text: post_reference(&post.post_number, &post.name, &url),
+        },
+        YankItem {
+            kind: "Markdown",
+            label: format!("{} lines", content.body.body_md.lines().count()),
+            text: content.body.body_md.clone(),
+        },
+    ];
+    items.extend(content.segments.iter().filter_map(|segment| {
+        let Segment::Code(code) = segment else {
+            return None;
+        };
+        let first_line = code.source.lines().find(|l| !l.trim().is_empty());
+        Some(YankItem {
+            kind: "Code",
+            label: format!("{} {}", code.info, first_line.unwrap_or("").trim())
+                .trim()
+                .to_string(),
+            text: code.source.clone(),
+        })
+    }));
+    items.extend(links.iter().map(|link| YankItem {
+        kind: "Link",
+        label: format!("{} {}", link.text, link.url),
+        text: link.url.clone(),
+    }));
+    items
+}
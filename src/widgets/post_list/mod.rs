@@ -1,7 +1,7 @@
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::{
     buffer::Buffer,
-    layout::{Constraint, Layout, Rect},
+    layout::{Constraint, Layout, Margin, Rect},
     style::{Modifier, Style},
     text::{Line, Span},
     widgets::{
@@ -9,76 +9,139 @@ use ratatui::{
     },
 };
 
+mod row_format;
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use row_format::RowFormat;
+use tokio::sync::mpsc::UnboundedSender;
+
 use crate::{
-    domains::{Post, PostViewConfig, Theme},
-    http_gateways::{EsaClientHttpGateway, PostListPage},
+    domains::{ListConfig, Post, PostViewConfig, Theme, TimeDisplay},
+    http_gateways::{EsaClientHttpGateway, FetchedPage, ListRequest, PostListPage},
 };
 
-const STAR_ICON: &str = "\u{f005}";
-const UNSTAR_ICON: &str = "\u{f006}";
-const WATCH_ICON: &str = "\u{f441}";
-const UNWATCH_ICON: &str = "\u{f06e}";
-
 pub struct PostList {
     pub posts: Vec<Post>,
     pub state: ListState,
     post_views: Vec<PostViewConfig>,
     selected_view: usize,
-    current_page: i32,
+    /// How many posts each loaded page brought, so a refetched page can replace its own.
+    page_lengths: Vec<usize>,
     next_page: Option<i32>,
-    pub api: Box<dyn EsaClientHttpGateway>,
+    total_count: Option<u32>,
+    /// Whether the posts were read from the cache because esa couldn't be reached.
+    stale: bool,
+    /// Whether the next page is being fetched; the list shows a loading row until it arrives.
+    loading: bool,
+    /// How close to the end the selection gets before the next page is fetched.
+    prefetch_rows: usize,
+    pub api: Arc<dyn EsaClientHttpGateway>,
+    /// Where pages fetched in the background are sent, for the app to hand back to
+    /// [`PostList::receive_page`].
+    pages: UnboundedSender<FetchedPage>,
     theme: Theme,
+    row_format: RowFormat,
+    /// Each view's own `row_format`, parsed once, in the order of `post_views`.
+    view_row_formats: Vec<Option<RowFormat>>,
+    compact_row_format: RowFormat,
+    time: TimeDisplay,
+    /// Whether posts are shown on one line each, from `list.compact`.
+    compact: bool,
+    /// Where the tabs and the list were last drawn, for mouse clicks.
+    tabs_area: Rect,
+    list_area: Rect,
 }
 
 impl PostList {
     pub fn new(
-        api: Box<dyn EsaClientHttpGateway>,
+        api: Arc<dyn EsaClientHttpGateway>,
+        pages: UnboundedSender<FetchedPage>,
         post_views: Vec<PostViewConfig>,
+        list: ListConfig,
+        time: TimeDisplay,
         theme: Theme,
     ) -> Self {
+        let view_row_formats = post_views
+            .iter()
+            .map(|view| view.row_format.as_deref().map(RowFormat::parse))
+            .collect();
         Self {
             posts: vec![],
             state: ListState::default(),
             post_views,
             selected_view: 0,
-            current_page: 1,
+            page_lengths: vec![],
             next_page: None,
+            total_count: None,
+            stale: false,
+            loading: false,
+            prefetch_rows: list.prefetch_rows,
             api,
+            pages,
             theme,
+            row_format: RowFormat::parse(&list.row_format),
+            view_row_formats,
+            compact_row_format: RowFormat::parse(&[list.compact_row_format]),
+            compact: list.compact,
+            time,
+            tabs_area: Rect::default(),
+            list_area: Rect::default(),
         }
     }
 }
 
 impl PostList {
     pub async fn init(&mut self) {
-        match self.fetch_posts_page(1).await {
-            Ok(PostListPage { posts, next_page }) => {
-                self.posts = posts;
-                self.current_page = 1;
-                self.next_page = next_page;
-                self.reset_selection();
-            }
-            Err(e) => {
-                eprintln!("failed to fetch posts: {}", e);
-            }
+        self.refresh_posts().await;
+    }
+
+    /// The current view's query for `page`.
+    fn request(&self, page: i32) -> ListRequest {
+        match self.post_views.get(self.selected_view) {
+            Some(view) => ListRequest {
+                query: view.query.clone(),
+                sort: view.sort,
+                order: view.order,
+                per_page: view.per_page,
+                page,
+            },
+            None => ListRequest {
+                page,
+                ..ListRequest::default()
+            },
         }
     }
 
-    async fn fetch_posts_page(&self, page: i32) -> anyhow::Result<PostListPage> {
-        let query = self
-            .post_views
-            .get(self.selected_view)
-            .and_then(|view| view.query.clone());
-        let response = self.api.fetch_posts(query, page).await?;
-        Ok(response)
+    async fn fetch_posts_page(
+        api: &dyn EsaClientHttpGateway,
+        request: &ListRequest,
+    ) -> anyhow::Result<PostListPage> {
+        api.fetch_posts(
+            request.query.clone(),
+            request.sort,
+            request.order,
+            request.per_page,
+            request.page,
+        )
+        .await
     }
 
     async fn refresh_posts(&mut self) {
-        match self.fetch_posts_page(1).await {
-            Ok(PostListPage { posts, next_page }) => {
+        self.loading = false;
+        match Self::fetch_posts_page(self.api.as_ref(), &self.request(1)).await {
+            Ok(PostListPage {
+                posts,
+                next_page,
+                total_count,
+                stale,
+            }) => {
+                self.page_lengths = vec![posts.len()];
                 self.posts = posts;
-                self.current_page = 1;
                 self.next_page = next_page;
+                self.total_count = total_count;
+                self.stale = stale;
                 self.reset_selection();
             }
             Err(e) => {
@@ -96,9 +159,97 @@ impl PostList {
             KeyCode::Char('k') | KeyCode::Up => self.state.select_previous(),
             KeyCode::Char('h') | KeyCode::Left => self.select_prev_view().await,
             KeyCode::Char('l') | KeyCode::Right => self.select_next_view().await,
-            KeyCode::Enter => self.load_more_if_needed().await,
+            KeyCode::Char('v') => self.cycle_sort().await,
+            KeyCode::Char('V') => self.reverse_order().await,
             _ => {}
         }
+        // also covers `Enter` on the load more row, which is past every post
+        self.prefetch_if_near_end();
+    }
+
+    /// Selects the clicked post or view and scrolls the list with the wheel.
+    ///
+    /// Returns `true` when a post was clicked, so it should be opened.
+    pub async fn handle_mouse(&mut self, mouse: MouseEvent) -> bool {
+        let position = (mouse.column, mouse.row).into();
+        match mouse.kind {
+            MouseEventKind::ScrollDown if self.list_area.contains(position) => {
+                self.state.select_next();
+            }
+            MouseEventKind::ScrollUp if self.list_area.contains(position) => {
+                self.state.select_previous();
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(view) = self.tab_at(mouse.column, mouse.row)
+                    && view != self.selected_view
+                {
+                    self.selected_view = view;
+                    self.refresh_posts().await;
+                } else if let Some(index) = self.item_at(mouse.column, mouse.row) {
+                    self.state.select(Some(index));
+                    if !self.is_load_more_index(index) {
+                        return true;
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.prefetch_if_near_end();
+        false
+    }
+
+    /// The view whose tab title is at the given cell, following the padding and dividers
+    /// `Tabs` draws between titles.
+    fn tab_at(&self, column: u16, row: u16) -> Option<usize> {
+        let inner = self.tabs_area.inner(Margin::new(1, 1));
+        if !inner.contains((column, row).into()) {
+            return None;
+        }
+        let mut x = inner.x;
+        for (index, view) in self.post_views.iter().enumerate() {
+            let width = Line::from(view.tab_title()).width() as u16 + 2;
+            if (x..x.saturating_add(width)).contains(&column) {
+                return Some(index);
+            }
+            x = x.saturating_add(width + 1);
+        }
+        None
+    }
+
+    /// The list item drawn at the given cell, counting from the first item on screen.
+    fn item_at(&self, column: u16, row: u16) -> Option<usize> {
+        let inner = self.list_area.inner(Margin::new(1, 1));
+        if !inner.contains((column, row).into()) {
+            return None;
+        }
+        let mut y = inner.y;
+        for index in self.state.offset()..self.item_count() {
+            let height = if self.is_load_more_index(index) {
+                1
+            } else {
+                self.current_row_format().height()
+            };
+            if (y..y.saturating_add(height)).contains(&row) {
+                return Some(index);
+            }
+            y = y.saturating_add(height);
+        }
+        None
+    }
+
+    /// The compact format, or the current view's own format, or the global one.
+    fn current_row_format(&self) -> &RowFormat {
+        if self.compact {
+            return &self.compact_row_format;
+        }
+        self.view_row_formats
+            .get(self.selected_view)
+            .and_then(Option::as_ref)
+            .unwrap_or(&self.row_format)
+    }
+
+    fn item_count(&self) -> usize {
+        self.posts.len() + usize::from(self.has_more())
     }
 
     pub async fn watch_selected(&mut self) {
@@ -157,6 +308,10 @@ impl PostList {
         self.refresh_selected_post().await;
     }
 
+    pub fn current_view(&self) -> Option<&PostViewConfig> {
+        self.post_views.get(self.selected_view)
+    }
+
     pub fn selected_post(&self) -> Option<&Post> {
         if let Some(selected) = self.state.selected() {
             if self.is_load_more_index(selected) {
@@ -168,6 +323,18 @@ impl PostList {
         }
     }
 
+    /// Copies the task counts of `post` to the same post in the list.
+    pub fn update_task_progress(&mut self, post: &Post) {
+        if let Some(target) = self
+            .posts
+            .iter_mut()
+            .find(|p| p.post_number == post.post_number)
+        {
+            target.tasks_count = post.tasks_count;
+            target.done_tasks_count = post.done_tasks_count;
+        }
+    }
+
     async fn select_prev_view(&mut self) {
         if self.post_views.is_empty() {
             return;
@@ -188,6 +355,24 @@ impl PostList {
         self.refresh_posts().await;
     }
 
+    /// Sorts the current view by the next field, for this session only.
+    async fn cycle_sort(&mut self) {
+        let Some(view) = self.post_views.get_mut(self.selected_view) else {
+            return;
+        };
+        view.sort = Some(view.sort.unwrap_or_default().next());
+        self.refresh_posts().await;
+    }
+
+    /// Flips the current view between ascending and descending, for this session only.
+    async fn reverse_order(&mut self) {
+        let Some(view) = self.post_views.get_mut(self.selected_view) else {
+            return;
+        };
+        view.order = Some(view.order.unwrap_or_default().reversed());
+        self.refresh_posts().await;
+    }
+
     fn selected_post_number(&self) -> Option<crate::domains::PostNumber> {
         let selected = self.state.selected()?;
         if self.is_load_more_index(selected) {
@@ -219,21 +404,63 @@ impl PostList {
         }
     }
 
-    async fn load_more_if_needed(&mut self) {
-        if !self.is_load_more_selected() {
-            return;
-        }
+    /// Fetches the next page in the background once the selection is within `prefetch_rows`
+    /// of the last post, so keys keep working while it loads.
+    fn prefetch_if_near_end(&mut self) {
         let Some(next_page) = self.next_page else {
             return;
         };
-        let requested_page = next_page;
+        let near_end = self
+            .state
+            .selected()
+            .is_some_and(|selected| selected + self.prefetch_rows >= self.posts.len());
+        if self.loading || !near_end {
+            return;
+        }
+        self.loading = true;
+        let request = self.request(next_page);
+        let api = Arc::clone(&self.api);
+        let pages = self.pages.clone();
+        tokio::spawn(async move {
+            let result = Self::fetch_posts_page(api.as_ref(), &request).await;
+            // the app has quit when nobody receives
+            let _ = pages.send(FetchedPage { request, result });
+        });
+    }
+
+    /// Takes a page fetched in the background: the next page is appended, and a page already
+    /// shown, refetched after the cache answered, replaces its posts. Pages of another view or
+    /// sort than the one shown are dropped.
+    pub fn receive_page(&mut self, fetched: FetchedPage) {
+        let page = fetched.request.page;
+        if page < 1 || fetched.request != self.request(page) {
+            return;
+        }
+        let index = (page - 1) as usize;
+        if index < self.page_lengths.len() {
+            if let Ok(fresh) = fetched.result {
+                self.replace_page(index, fresh);
+            }
+            return;
+        }
+        if self.next_page != Some(page) {
+            return;
+        }
+        self.loading = false;
         let selected = self.state.selected();
-        match self.fetch_posts_page(requested_page).await {
-            Ok(PostListPage { posts, next_page }) => {
+        match fetched.result {
+            Ok(PostListPage {
+                posts,
+                next_page,
+                total_count,
+                stale,
+            }) => {
                 let previous_len = self.posts.len();
+                self.page_lengths.push(posts.len());
                 self.posts.extend(posts);
-                self.current_page = requested_page;
                 self.next_page = next_page;
+                self.total_count = total_count;
+                self.stale |= stale;
                 if let Some(selected) = selected
                     && selected == previous_len
                 {
@@ -252,6 +479,36 @@ impl PostList {
         }
     }
 
+    /// Swaps the posts of the `index`-th loaded page, keeping the selected post selected.
+    fn replace_page(&mut self, index: usize, fresh: PostListPage) {
+        let start: usize = self.page_lengths[..index].iter().sum();
+        let end = start + self.page_lengths[index];
+        let selected_post = self.selected_post_number();
+        let load_more_selected = self.is_load_more_selected();
+        self.page_lengths[index] = fresh.posts.len();
+        self.posts.splice(start..end, fresh.posts);
+        if index + 1 == self.page_lengths.len() {
+            self.next_page = fresh.next_page;
+        }
+        self.total_count = fresh.total_count;
+
+        let moved = selected_post.and_then(|post_number| {
+            self.posts
+                .iter()
+                .position(|post| post.post_number == post_number)
+        });
+        if let Some(position) = moved {
+            self.state.select(Some(position));
+        } else if load_more_selected && self.has_more() {
+            self.state.select(Some(self.posts.len()));
+        } else if self.item_count() == 0 {
+            self.state.select(None);
+        } else if let Some(selected) = self.state.selected() {
+            self.state
+                .select(Some(selected.min(self.item_count().saturating_sub(1))));
+        }
+    }
+
     fn reset_selection(&mut self) {
         if self.posts.is_empty() && !self.has_more() {
             self.state.select(None);
@@ -280,7 +537,7 @@ impl PostList {
         } else {
             self.post_views
                 .iter()
-                .map(|view| Line::from(view.title.clone()))
+                .map(|view| Line::from(view.tab_title()))
                 .collect()
         };
         let tabs = Tabs::new(titles)
@@ -302,51 +559,55 @@ impl PostList {
     }
 
     fn render_list(&mut self, area: Rect, buf: &mut Buffer) {
+        let mut title = vec![Span::raw(match self.total_count {
+            Some(total) => format!("Posts ({}/{})", self.posts.len(), total),
+            None => "Posts".to_string(),
+        })];
+        if self.stale {
+            title.push(Span::styled(
+                " cached ",
+                Style::new().fg(self.theme.warning),
+            ));
+        }
         let block = Block::default()
-            .title("Posts")
+            .title(Line::from(title))
             .borders(Borders::ALL)
             .border_style(Style::new().fg(self.theme.muted))
             .title_style(Style::new().fg(self.theme.primary));
 
+        let row_format = self.current_row_format();
+        let now = Utc::now();
         let items: Vec<ListItem> = self
             .posts
             .iter()
             .map(|post| {
-                let tags = post
-                    .tags
-                    .iter()
-                    .map(|tag| tag.label.as_str())
-                    .collect::<Vec<_>>()
-                    .join(" ");
-                let header = if tags.is_empty() {
-                    format!("{} {}", post.post_number, post.full_name)
-                } else {
-                    format!("{} {} {}", post.post_number, post.full_name, tags)
-                };
-                let updated_at = post.updated_at.format("%Y-%m-%d %H:%M").to_string();
-                let meta = format!("\u{f007} @{}  {}", post.updated_by.id.0, updated_at);
-                let star_icon = if post.starred { STAR_ICON } else { UNSTAR_ICON };
-                let watch_icon = if post.watched {
-                    WATCH_ICON
-                } else {
-                    UNWATCH_ICON
-                };
-                let stats = format!(
-                    "{} {} {} {}",
-                    star_icon, post.stars, watch_icon, post.watches
-                );
-                ListItem::new(vec![
-                    Line::from(Span::styled(header, Style::new().fg(self.theme.primary))),
-                    Line::from(Span::styled(meta, Style::new().fg(self.theme.muted))),
-                    Line::from(Span::styled(stats, Style::new().fg(self.theme.muted))),
-                ])
+                let lines = row_format
+                    .render(post, &self.time, now)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, line)| {
+                        // the first line names the post, the rest are details
+                        let color = if index == 0 {
+                            self.theme.primary
+                        } else {
+                            self.theme.muted
+                        };
+                        Line::from(Span::styled(line, Style::new().fg(color)))
+                    })
+                    .collect::<Vec<_>>();
+                ListItem::new(lines)
             })
             .collect::<Vec<_>>();
 
         let mut items = items;
         if self.has_more() {
+            let label = if self.loading {
+                "読み込み中…"
+            } else {
+                "続きをロード"
+            };
             items.push(ListItem::new(vec![Line::from(Span::styled(
-                "続きをロード",
+                label,
                 Style::new()
                     .fg(self.theme.accent)
                     .add_modifier(Modifier::BOLD),
@@ -371,6 +632,8 @@ impl Widget for &mut PostList {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let layout = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]);
         let [tabs_area, list_area] = layout.areas(area);
+        self.tabs_area = tabs_area;
+        self.list_area = list_area;
         self.render_tabs(tabs_area, buf);
         self.render_list(list_area, buf);
     }
@@ -0,0 +1,251 @@
+use chrono::{DateTime, Utc};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{
+    domains::{Category, Post, PostKind, TimeDisplay},
+    widgets::icons::{
+        COMMENTS_ICON, FLOW_ICON, STAR_ICON, STOCK_ICON, TASKS_ICON, UNSTAR_ICON, UNWATCH_ICON,
+        WATCH_ICON,
+    },
+};
+
+/// The lines shown for each post in the list, parsed from templates like `{number} {name:30}`.
+#[derive(Clone, Debug)]
+pub struct RowFormat {
+    lines: Vec<Vec<Piece>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Field {
+        name: String,
+        /// Cells the value is padded or truncated to.
+        width: Option<usize>,
+        align_right: bool,
+    },
+}
+
+impl RowFormat {
+    pub fn parse(lines: &[String]) -> Self {
+        Self {
+            lines: lines.iter().map(|line| parse_line(line)).collect(),
+        }
+    }
+
+    pub fn height(&self) -> u16 {
+        self.lines.len().max(1) as u16
+    }
+
+    pub fn render(&self, post: &Post, time: &TimeDisplay, now: DateTime<Utc>) -> Vec<String> {
+        self.lines
+            .iter()
+            .map(|pieces| {
+                let mut line = String::new();
+                // an empty field such as `{wip}` takes the space after it along
+                let mut skip_space = false;
+                for piece in pieces {
+                    match piece {
+                        Piece::Text(text) => {
+                            let text = match text.strip_prefix(' ') {
+                                Some(rest) if skip_space => rest,
+                                _ => text,
+                            };
+                            line.push_str(text);
+                            skip_space = false;
+                        }
+                        Piece::Field {
+                            name,
+                            width,
+                            align_right,
+                        } => {
+                            let value = field(post, name, time, now)
+                                .unwrap_or_else(|| format!("{{{}}}", name));
+                            skip_space = value.is_empty() && width.is_none();
+                            match width {
+                                Some(width) => line.push_str(&fit(&value, *width, *align_right)),
+                                None => line.push_str(&value),
+                            }
+                        }
+                    }
+                }
+                line.trim_end().to_string()
+            })
+            .collect()
+    }
+}
+
+fn parse_line(line: &str) -> Vec<Piece> {
+    let mut pieces = vec![];
+    let mut rest = line;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        if start > 0 {
+            pieces.push(Piece::Text(rest[..start].to_string()));
+        }
+        let spec = &rest[start + 1..end];
+        let (name, width) = spec.split_once(':').unwrap_or((spec, ""));
+        let align_right = width.starts_with('>');
+        let width = width.trim_start_matches(['<', '>']).parse().ok();
+        pieces.push(Piece::Field {
+            name: name.trim().to_string(),
+            width,
+            align_right,
+        });
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest.to_string()));
+    }
+    pieces
+}
+
+/// The value of a template field, `None` for fields we don't know.
+fn field(post: &Post, name: &str, time: &TimeDisplay, now: DateTime<Utc>) -> Option<String> {
+    let value = match name {
+        "number" => post.post_number.to_string(),
+        "name" => post.name.clone(),
+        "full_name" => post.full_name.clone(),
+        "category" => post
+            .category
+            .as_ref()
+            .map(Category::to_string)
+            .unwrap_or_default(),
+        "tags" => post
+            .tags
+            .iter()
+            .map(|tag| tag.label.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+        "author" => post.updated_by.id.0.clone(),
+        "created_by" => post.created_by.id.0.clone(),
+        "updated" => time.format(post.updated_at, now),
+        "updated_relative" => time.relative(post.updated_at, now),
+        "created" => time.format(post.created_at, now),
+        "stars" => {
+            let icon = if post.starred { STAR_ICON } else { UNSTAR_ICON };
+            format!("{} {}", icon, post.stars)
+        }
+        "watches" => {
+            let icon = if post.watched {
+                WATCH_ICON
+            } else {
+                UNWATCH_ICON
+            };
+            format!("{} {}", icon, post.watches)
+        }
+        "kind" => match post.kind {
+            PostKind::Stock => STOCK_ICON.to_string(),
+            PostKind::Flow => FLOW_ICON.to_string(),
+        },
+        "revision" => post.revision_number.to_string(),
+        "comments" => format!("{} {}", COMMENTS_ICON, post.comments_count),
+        "tasks" if post.tasks_count > 0 => format!(
+            "{} {}/{}",
+            TASKS_ICON, post.done_tasks_count, post.tasks_count
+        ),
+        "tasks" => String::new(),
+        "wip" if post.wip => "WIP".to_string(),
+        "wip" => String::new(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Pads or truncates `text` to `width` terminal cells, counting wide characters as two
+/// and marking cut text with `…`.
+fn fit(text: &str, width: usize, align_right: bool) -> String {
+    let text = if text.width() > width {
+        let mut cut = String::new();
+        let mut used = 0;
+        for c in text.chars() {
+            let char_width = c.width().unwrap_or(0);
+            if used + char_width + 1 > width {
+                break;
+            }
+            cut.push(c);
+            used += char_width;
+        }
+        if width > 0 {
+            cut.push('…');
+        }
+        cut
+    } else {
+        text.to_string()
+    };
+    let padding = " ".repeat(width.saturating_sub(text.width()));
+    if align_right {
+        format!("{}{}", padding, text)
+    } else {
+        format!("{}{}", text, padding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::sample_post;
+    use chrono::TimeZone;
+    use insta::assert_snapshot;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn post() -> Post {
+        sample_post()
+    }
+
+    #[rstest]
+    #[case(
+        "default",
+        &[
+            "{kind} {number} {wip} {full_name} {tags}",
+            "@{author}  {updated}",
+            "{stars} {watches} {comments} {tasks}"
+        ]
+    )]
+    #[case(
+        "compact",
+        &["{number:>6} {name:12} {category:8}|{author:>6} {updated_relative:>8} {wip}"]
+    )]
+    #[case("unknown_field", &["{number} {unknown} {comments}"])]
+    fn test_render(post: Post, #[case] name: &str, #[case] lines: &[&str]) {
+        let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        let now = Utc.with_ymd_and_hms(2026, 5, 1, 12, 0, 0).unwrap();
+        let time = TimeDisplay::new(Some("Asia/Tokyo"), false);
+        let rendered = RowFormat::parse(&lines).render(&post, &time, now);
+        assert_snapshot!(name, rendered.join("\n"));
+    }
+
+    #[rstest]
+    fn test_empty_field_takes_its_space(post: Post) {
+        let post = Post {
+            wip: false,
+            tasks_count: 0,
+            ..post
+        };
+        let lines = vec!["{number} {wip} {name} {tasks} rev {revision}".to_string()];
+        let now = Utc.with_ymd_and_hms(2026, 5, 1, 12, 0, 0).unwrap();
+        let time = TimeDisplay::new(Some("Asia/Tokyo"), false);
+        assert_eq!(
+            RowFormat::parse(&lines).render(&post, &time, now),
+            vec!["#42 デプロイ手順のまとめ rev 7"]
+        );
+    }
+
+    #[rstest]
+    #[case("abc", 5, false, "abc  ")]
+    #[case("abc", 5, true, "  abc")]
+    #[case("abcdef", 4, false, "abc…")]
+    #[case("日本語の題名", 6, false, "日本… ")]
+    #[case("日本語の題名", 5, false, "日本…")]
+    fn test_fit(
+        #[case] text: &str,
+        #[case] width: usize,
+        #[case] align_right: bool,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(fit(text, width, align_right), expected);
+    }
+}
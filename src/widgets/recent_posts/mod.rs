@@ -0,0 +1,115 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, StatefulWidget, Widget,
+    },
+};
+
+use crate::domains::{PostNumber, RecentlyViewed, Theme};
+
+/// Popup listing the recently viewed posts of the current team.
+pub struct RecentPosts {
+    entries: Vec<RecentlyViewed>,
+    state: ListState,
+    open: bool,
+    theme: Theme,
+}
+
+impl RecentPosts {
+    pub fn new(theme: Theme) -> Self {
+        Self {
+            entries: vec![],
+            state: ListState::default(),
+            open: false,
+            theme,
+        }
+    }
+}
+
+impl RecentPosts {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self, entries: Vec<RecentlyViewed>) {
+        self.state
+            .select(if entries.is_empty() { None } else { Some(0) });
+        self.entries = entries;
+        self.open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Returns the entry to open when Enter is pressed.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<RecentlyViewed> {
+        if key.kind != KeyEventKind::Press {
+            return None;
+        }
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => self.state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.state.select_previous(),
+            KeyCode::Esc | KeyCode::Char('r') | KeyCode::Char('q') => self.close(),
+            KeyCode::Enter => {
+                let selected = self
+                    .state
+                    .selected()
+                    .and_then(|i| self.entries.get(i).cloned());
+                self.close();
+                return selected;
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+impl Widget for &mut RecentPosts {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if !self.open {
+            return;
+        }
+        let [area] = Layout::horizontal([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::vertical([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(area);
+        let block = Block::default()
+            .title("Recently Viewed")
+            .borders(Borders::ALL)
+            .border_style(Style::new().fg(self.theme.muted))
+            .title_style(Style::new().fg(self.theme.primary));
+
+        let items: Vec<ListItem> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{} ", PostNumber::from(entry.post_number)),
+                        Style::new().fg(self.theme.muted),
+                    ),
+                    Span::styled(entry.full_name.clone(), Style::new().fg(self.theme.primary)),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(
+                Style::new()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        Clear.render(area, buf);
+        StatefulWidget::render(list, area, buf, &mut self.state);
+    }
+}
@@ -0,0 +1,271 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Paragraph,
+        StatefulWidget, Widget,
+    },
+};
+
+use crate::domains::{Post, SearchHit, Snippet, Theme};
+
+/// Where the search popup looks: esa's own search, or the posts kept on disk.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SearchScope {
+    #[default]
+    Esa,
+    Local,
+}
+
+impl SearchScope {
+    fn label(self) -> &'static str {
+        match self {
+            SearchScope::Esa => "esa",
+            SearchScope::Local => "local",
+        }
+    }
+
+    fn toggled(self) -> Self {
+        match self {
+            SearchScope::Esa => SearchScope::Local,
+            SearchScope::Local => SearchScope::Esa,
+        }
+    }
+}
+
+/// What the app should do after a key in the search popup.
+#[derive(Clone, Debug)]
+pub enum SearchRequest {
+    Run { query: String, scope: SearchScope },
+    Open(Box<Post>),
+}
+
+/// Popup with a search prompt over its results, opened with `/`.
+///
+/// Enter searches for what was typed, or opens the selected result once the results are for
+/// it; Tab switches between esa's search and the local full-text index.
+pub struct Search {
+    input: String,
+    scope: SearchScope,
+    /// The query and scope the results are for.
+    searched: Option<(String, SearchScope)>,
+    hits: Vec<SearchHit>,
+    state: ListState,
+    open: bool,
+    theme: Theme,
+}
+
+impl Search {
+    pub fn new(theme: Theme) -> Self {
+        Self {
+            input: String::new(),
+            scope: SearchScope::default(),
+            searched: None,
+            hits: vec![],
+            state: ListState::default(),
+            open: false,
+            theme,
+        }
+    }
+}
+
+impl Search {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Opens with the last query and its results kept.
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn show_results(&mut self, query: String, scope: SearchScope, hits: Vec<SearchHit>) {
+        self.state
+            .select(if hits.is_empty() { None } else { Some(0) });
+        self.hits = hits;
+        self.searched = Some((query, scope));
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<SearchRequest> {
+        if key.kind != KeyEventKind::Press {
+            return None;
+        }
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => self.close(),
+            KeyCode::Tab => self.scope = self.scope.toggled(),
+            KeyCode::Down => self.state.select_next(),
+            KeyCode::Char('n') if control => self.state.select_next(),
+            KeyCode::Up => self.state.select_previous(),
+            KeyCode::Char('p') if control => self.state.select_previous(),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(c) if !control => self.input.push(c),
+            KeyCode::Enter => {
+                let query = self.input.trim().to_string();
+                if self.searched.as_ref() == Some(&(query.clone(), self.scope)) {
+                    let selected = self
+                        .state
+                        .selected()
+                        .and_then(|i| self.hits.get(i))
+                        .map(|hit| Box::new(hit.post.clone()))?;
+                    self.close();
+                    return Some(SearchRequest::Open(selected));
+                }
+                if !query.is_empty() {
+                    return Some(SearchRequest::Run {
+                        query,
+                        scope: self.scope,
+                    });
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn title(&self) -> String {
+        match &self.searched {
+            Some((_, scope)) => format!("Search ({}, {} hits)", scope.label(), self.hits.len()),
+            None => "Search".to_string(),
+        }
+    }
+
+    fn hit_item(&self, hit: &SearchHit) -> ListItem<'static> {
+        let mut lines = vec![Line::from(vec![
+            Span::styled(
+                format!("{} ", hit.post.post_number),
+                Style::new().fg(self.theme.muted),
+            ),
+            Span::styled(
+                hit.post.full_name.clone(),
+                Style::new().fg(self.theme.primary),
+            ),
+        ])];
+        if let Some(snippet) = &hit.snippet {
+            lines.push(self.snippet_line(snippet));
+        }
+        ListItem::new(lines)
+    }
+
+    fn snippet_line(&self, snippet: &Snippet) -> Line<'static> {
+        let muted = Style::new().fg(self.theme.muted);
+        let text = &snippet.text;
+        Line::from(vec![
+            Span::styled(format!("  {}: ", snippet.field.label()), muted),
+            Span::styled(text[..snippet.highlight.start].to_string(), muted),
+            Span::styled(
+                text[snippet.highlight.clone()].to_string(),
+                Style::new()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(text[snippet.highlight.end..].to_string(), muted),
+        ])
+    }
+}
+
+impl Widget for &mut Search {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if !self.open {
+            return;
+        }
+        let [area] = Layout::horizontal([Constraint::Percentage(70)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::vertical([Constraint::Percentage(70)])
+            .flex(Flex::Center)
+            .areas(area);
+        let block = Block::default()
+            .title(self.title())
+            .borders(Borders::ALL)
+            .border_style(Style::new().fg(self.theme.muted))
+            .title_style(Style::new().fg(self.theme.primary));
+        let inner = block.inner(area);
+        Clear.render(area, buf);
+        block.render(area, buf);
+
+        let [prompt_area, results_area] =
+            Layout::vertical([Constraint::Length(2), Constraint::Fill(1)]).areas(inner);
+        let prompt = Line::from(vec![
+            Span::styled(
+                format!("{}> ", self.scope.label()),
+                Style::new()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(self.input.clone(), Style::new().fg(self.theme.primary)),
+            Span::styled("▏", Style::new().fg(self.theme.accent)),
+        ]);
+        Paragraph::new(prompt).render(prompt_area, buf);
+
+        let items: Vec<ListItem> = self.hits.iter().map(|hit| self.hit_item(hit)).collect();
+        let list = List::new(items)
+            .highlight_style(
+                Style::new()
+                    .fg(self.theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+        StatefulWidget::render(list, results_area, buf, &mut self.state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::{ThemeConfig, sample_post};
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn post() -> Post {
+        sample_post()
+    }
+
+    fn press(search: &mut Search, code: KeyCode) -> Option<SearchRequest> {
+        search.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[rstest]
+    fn test_enter_searches_then_opens(post: Post) {
+        let mut search = Search::new(Theme::from_config(&ThemeConfig::default()));
+        search.open();
+        for c in "手順".chars() {
+            press(&mut search, KeyCode::Char(c));
+        }
+        press(&mut search, KeyCode::Tab);
+        let Some(SearchRequest::Run { query, scope }) = press(&mut search, KeyCode::Enter) else {
+            panic!("expected a search");
+        };
+        assert_eq!((query.as_str(), scope), ("手順", SearchScope::Local));
+
+        let hit = SearchHit {
+            post,
+            score: 1,
+            snippet: None,
+        };
+        search.show_results(query, scope, vec![hit]);
+        let Some(SearchRequest::Open(post)) = press(&mut search, KeyCode::Enter) else {
+            panic!("expected the result to open");
+        };
+        assert_eq!(post.post_number.to_i32(), 42);
+        assert!(!search.is_open());
+
+        // a changed query searches again
+        search.open();
+        press(&mut search, KeyCode::Backspace);
+        assert!(matches!(
+            press(&mut search, KeyCode::Enter),
+            Some(SearchRequest::Run { .. })
+        ));
+    }
+}
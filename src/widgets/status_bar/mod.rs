@@ -0,0 +1,64 @@
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Style,
+    text::Line,
+    widgets::{Paragraph, Widget},
+};
+
+use crate::domains::Theme;
+
+/// How long a message stays in the status bar.
+const MESSAGE_DURATION: Duration = Duration::from_secs(3);
+
+pub enum StatusMessage {
+    Info(String),
+    Error(String),
+}
+
+/// One-line bar at the bottom of the screen showing the outcome of the last action.
+pub struct StatusBar {
+    message: Option<(StatusMessage, Instant)>,
+    theme: Theme,
+}
+
+impl StatusBar {
+    pub fn new(theme: Theme) -> Self {
+        Self {
+            message: None,
+            theme,
+        }
+    }
+}
+
+impl StatusBar {
+    pub fn show(&mut self, message: StatusMessage) {
+        self.message = Some((message, Instant::now()));
+    }
+}
+
+impl Widget for &mut StatusBar {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if self
+            .message
+            .as_ref()
+            .is_some_and(|(_, shown_at)| shown_at.elapsed() > MESSAGE_DURATION)
+        {
+            self.message = None;
+        }
+        let Some((message, _)) = &self.message else {
+            return;
+        };
+        let line = match message {
+            StatusMessage::Info(text) => {
+                Line::styled(text.as_str(), Style::new().fg(self.theme.success))
+            }
+            StatusMessage::Error(text) => {
+                Line::styled(text.as_str(), Style::new().fg(self.theme.error))
+            }
+        };
+        Paragraph::new(line).render(area, buf);
+    }
+}